@@ -0,0 +1,111 @@
+//! Benchmarks proof verification of the whole block partition,
+//! comparing batched verification, with a single aggregated pairing check per row,
+//! with verification of each cell proof in a separate job.
+//!
+//! Run with `cargo bench --bench proof`.
+
+use avail_light::proof;
+use kate_recovery::{
+	data::Cell,
+	matrix::{Dimensions, Position},
+	proof::Error,
+	testnet,
+};
+use std::{
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+const ITERATIONS: u32 = 10;
+
+type Verify = fn(
+	u32,
+	Dimensions,
+	&[Cell],
+	&[[u8; 48]],
+	Arc<dusk_plonk::commitment_scheme::kzg10::PublicParameters>,
+) -> Result<(Vec<Position>, Vec<Position>), Error>;
+
+const COMMITMENT: [u8; 48] = [
+	181, 10, 104, 251, 33, 171, 87, 192, 13, 195, 93, 127, 215, 78, 114, 192, 95, 92, 167, 10, 49,
+	17, 20, 204, 222, 102, 70, 218, 173, 18, 30, 49, 232, 10, 137, 187, 186, 216, 97, 140, 16, 33,
+	52, 56, 170, 208, 118, 242,
+];
+
+fn cells(rows: u32) -> Vec<Cell> {
+	let contents: [(u16, [u8; 80]); 3] = [
+		(
+			0,
+			[
+				183, 56, 112, 134, 157, 186, 15, 255, 245, 173, 188, 37, 165, 224, 226, 80, 196,
+				137, 235, 233, 154, 4, 110, 142, 26, 95, 150, 132, 61, 23, 202, 212, 101, 6, 235,
+				6, 102, 188, 206, 147, 36, 121, 128, 63, 240, 37, 200, 236, 4, 44, 40, 4, 3, 0, 11,
+				35, 249, 222, 81, 135, 1, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+				0,
+			],
+		),
+		(
+			2,
+			[
+				153, 31, 34, 70, 221, 239, 97, 236, 3, 172, 44, 167, 114, 117, 186, 245, 171, 12,
+				70, 144, 204, 207, 82, 160, 29, 83, 245, 203, 40, 238, 96, 131, 68, 96, 9, 136,
+				151, 88, 218, 72, 79, 55, 193, 228, 71, 193, 120, 113, 48, 237, 151, 135, 246, 8,
+				251, 150, 106, 44, 29, 250, 250, 54, 133, 203, 162, 73, 252, 32, 42, 175, 24, 166,
+				142, 72, 226, 150, 163, 206, 115, 0,
+			],
+		),
+		(
+			3,
+			[
+				150, 6, 83, 12, 56, 17, 0, 225, 186, 238, 151, 181, 116, 1, 34, 240, 174, 192, 98,
+				201, 60, 208, 50, 215, 90, 231, 2, 27, 17, 204, 140, 30, 213, 253, 200, 176, 72,
+				98, 121, 25, 239, 76, 230, 154, 121, 246, 142, 37, 85, 184, 201, 218, 107, 88, 0,
+				87, 199, 169, 98, 172, 4, 140, 151, 65, 162, 162, 190, 205, 20, 95, 67, 114, 73,
+				59, 170, 52, 243, 140, 237, 0,
+			],
+		),
+	];
+
+	(0..rows)
+		.flat_map(|row| {
+			contents.iter().map(move |&(col, content)| Cell {
+				position: Position { row, col },
+				content,
+			})
+		})
+		.collect()
+}
+
+fn bench(name: &str, verify: Verify, rows: u32) -> Duration {
+	let public_parameters = Arc::new(testnet::public_params(1024));
+	let dimensions = Dimensions::new(1, 4).expect("Dimensions should be valid");
+	let cells = cells(rows);
+	let commitments = vec![COMMITMENT; rows as usize];
+
+	let start = Instant::now();
+	for _ in 0..ITERATIONS {
+		verify(
+			1,
+			dimensions,
+			&cells,
+			&commitments,
+			public_parameters.clone(),
+		)
+		.expect("Proofs should be verified");
+	}
+	let elapsed = start.elapsed() / ITERATIONS;
+
+	println!("{name}: {} cells in {elapsed:?}", cells.len());
+	elapsed
+}
+
+fn main() {
+	for rows in [1, 16, 256] {
+		let unbatched = bench("unbatched", proof::verify_unbatched, rows);
+		let batched = bench("batched", proof::verify, rows);
+		println!(
+			"speedup for {rows} rows: {:.2}x",
+			unbatched.as_secs_f64() / batched.as_secs_f64()
+		);
+	}
+}
@@ -11,22 +11,23 @@
 use crate::api::v2;
 use crate::{
 	api::v1,
+	data::Database,
 	rpc::Node,
-	types::{RuntimeConfig, State},
+	types::{AppBackfill, RuntimeConfig, State},
 };
 use anyhow::Context;
 use avail_subxt::avail;
-use rocksdb::DB;
 use std::{
 	net::SocketAddr,
 	str::FromStr,
 	sync::{Arc, Mutex},
 };
+use tokio::sync::mpsc;
 use tracing::info;
 use warp::{Filter, Reply};
 
-pub struct Server {
-	pub db: Arc<DB>,
+pub struct Server<T: Database> {
+	pub db: T,
 	pub cfg: RuntimeConfig,
 	pub state: Arc<Mutex<State>>,
 	pub version: String,
@@ -34,6 +35,8 @@ pub struct Server {
 	pub node: Node,
 	pub node_client: avail::Client,
 	pub ws_clients: v2::types::WsClients,
+	/// Sender for application data backfill requests, set if application client is started
+	pub backfill_sender: Option<mpsc::Sender<AppBackfill>>,
 }
 
 fn health_route() -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
@@ -42,7 +45,7 @@ fn health_route() -> impl Filter<Extract = impl Reply, Error = warp::Rejection>
 		.map(|| warp::reply::with_status("", warp::http::StatusCode::OK))
 }
 
-impl Server {
+impl<T: Database> Server<T> {
 	/// Runs HTTP server
 	pub async fn run(self) {
 		let RuntimeConfig {
@@ -61,7 +64,8 @@ impl Server {
 			self.cfg,
 			self.node_client.clone(),
 			self.ws_clients.clone(),
-			crate::data::RocksDB(self.db.clone()),
+			self.backfill_sender,
+			self.db,
 		);
 
 		let cors = warp::cors()
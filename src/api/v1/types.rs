@@ -19,6 +19,10 @@ pub struct ConfidenceResponse {
 	pub block: u32,
 	pub confidence: f64,
 	pub serialised_confidence: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub dht_confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rpc_confidence: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -1,8 +1,9 @@
 use super::{
-	transactions,
+	proofs, transactions,
 	types::{
-		block_status, filter_fields, Block, BlockStatus, DataQuery, DataResponse, DataTransaction,
-		Error, FieldsQueryParameter, Header, Status, SubmitResponse, Subscription, SubscriptionId,
+		block_status, filter_fields, AddApp, Apps, Block, BlockRange, BlockStatus, DataProof,
+		DataQuery, DataResponse, DataTransaction, Equivocations, Error, FieldsQueryParameter,
+		Header, Justification, Sampling, Status, SubmitResponse, Subscription, SubscriptionId,
 		Transaction, Version, WsClients,
 	},
 	ws,
@@ -10,16 +11,21 @@ use super::{
 use crate::{
 	api::v2::types::{ErrorCode, InternalServerError},
 	data::Database,
+	proof,
 	rpc::Node,
-	types::{RuntimeConfig, State},
-	utils::calculate_confidence,
+	types::{AppBackfill, RuntimeConfig, State},
+	utils::{calculate_confidence, decode_app_data, extract_kate},
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
+use avail_subxt::utils::H256;
+use codec::Encode;
 use hyper::StatusCode;
+use sp_core::{blake2_256, keccak_256};
 use std::{
 	convert::Infallible,
 	sync::{Arc, Mutex},
 };
+use tokio::sync::mpsc;
 use tracing::error;
 use uuid::Uuid;
 use warp::{ws::Ws, Rejection, Reply};
@@ -57,6 +63,7 @@ pub async fn ws(
 	node: Node,
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync + 'static>>,
 	state: Arc<Mutex<State>>,
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
 ) -> Result<impl Reply, Rejection> {
 	if !clients.has_subscription(&subscription_id).await {
 		return Err(warp::reject::not_found());
@@ -72,10 +79,73 @@ pub async fn ws(
 			node,
 			submitter.clone(),
 			state.clone(),
+			backfill_sender.clone(),
 		)
 	}))
 }
 
+pub async fn add_app(
+	backfill_sender: mpsc::Sender<AppBackfill>,
+	state: Arc<Mutex<State>>,
+	request: AddApp,
+) -> Result<Apps, Error> {
+	let AddApp { app_id, backfill } = request;
+	if app_id == 0 {
+		return Err(Error::bad_request_unknown("App ID 0 cannot be tracked"));
+	}
+
+	let app_ids = {
+		let mut state = state.lock().expect("Lock should be acquired");
+		if let Some(BlockRange { first, last }) = &backfill {
+			if state.app_ids.contains(&app_id) {
+				return Err(Error::bad_request_unknown(
+					"Backfill is allowed only for untracked app",
+				));
+			}
+			if first > last
+				|| *last > state.latest
+				|| state.is_header_pruned(*first)
+				|| state.is_data_pruned(*first)
+			{
+				return Err(Error::bad_request_unknown("Backfill range is not valid"));
+			}
+		}
+		// App is tracked before the backfill is requested, since backfill stops for untracked apps
+		state.app_ids.insert(app_id);
+		state.app_ids.iter().copied().collect()
+	};
+
+	if let Some(BlockRange { first, last }) = backfill {
+		let request = AppBackfill {
+			app_id,
+			first,
+			last,
+		};
+		let result = backfill_sender
+			.send(request)
+			.await
+			.context("Failed to send backfill request");
+		if let Err(error) = result {
+			// Backfill is allowed only for untracked app, so app is not tracked on failure
+			let mut state = state.lock().expect("Lock should be acquired");
+			state.app_ids.remove(&app_id);
+			return Err(Error::internal_server_error(error));
+		}
+	}
+
+	Ok(Apps { app_ids })
+}
+
+pub fn remove_app(app_id: u32, state: Arc<Mutex<State>>) -> Result<Apps, Error> {
+	let mut state = state.lock().expect("Lock should be acquired");
+	if !state.app_ids.remove(&app_id) {
+		return Err(Error::not_found());
+	}
+	state.apps.remove(&app_id);
+	let app_ids = state.app_ids.iter().copied().collect();
+	Ok(Apps { app_ids })
+}
+
 pub fn status(config: RuntimeConfig, node: Node, state: Arc<Mutex<State>>) -> impl Reply {
 	let state = state.lock().expect("Lock should be acquired");
 	Status::new(&config, &node, &state)
@@ -111,7 +181,15 @@ pub async fn block(
 		.map_err(Error::internal_server_error)?
 		.map(calculate_confidence);
 
-	Ok(Block::new(block_status, confidence))
+	let counts = db
+		.get_confidence_counts(block_number)
+		.map_err(Error::internal_server_error)?;
+
+	let failure = db
+		.get_availability_failure(block_number)
+		.map_err(Error::internal_server_error)?;
+
+	Ok(Block::new(block_status, confidence, counts, failure))
 }
 
 pub async fn block_header(
@@ -129,7 +207,8 @@ pub async fn block_header(
 	if matches!(
 		block_status,
 		BlockStatus::Unavailable | BlockStatus::Pending | BlockStatus::VerifyingHeader
-	) {
+	) || state.is_header_pruned(block_number)
+	{
 		return Err(Error::bad_request_unknown("Block header is not available"));
 	};
 
@@ -139,6 +218,115 @@ pub async fn block_header(
 		.map_err(Error::internal_server_error)
 }
 
+pub async fn block_header_by_hash(
+	block_hash: H256,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> Result<Header, Error> {
+	let Some(block_number) = db
+		.get_block_number(block_hash)
+		.map_err(Error::internal_server_error)?
+	else {
+		return Err(Error::not_found());
+	};
+
+	block_header(block_number, config, state, db).await
+}
+
+pub async fn block_justification(
+	block_number: u32,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> Result<Justification, Error> {
+	let state = state.lock().expect("Lock should be acquired");
+
+	let Some(block_status) = block_status(&config.sync_start_block, &state, block_number) else {
+		return Err(Error::not_found());
+	};
+
+	if matches!(
+		block_status,
+		BlockStatus::Unavailable | BlockStatus::Pending | BlockStatus::VerifyingHeader
+	) || state.is_header_pruned(block_number)
+	{
+		return Err(Error::bad_request_unknown(
+			"Block justification is not available",
+		));
+	};
+
+	// Justifications are stored only for blocks finalized by them, not for their ancestors
+	let Some(justification) = db
+		.get_justification(block_number)
+		.map_err(Error::internal_server_error)?
+	else {
+		return Err(Error::not_found());
+	};
+
+	Ok((block_number, justification).into())
+}
+
+pub async fn block_equivocations(
+	block_number: u32,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> Result<Equivocations, Error> {
+	let state = state.lock().expect("Lock should be acquired");
+
+	let Some(block_status) = block_status(&config.sync_start_block, &state, block_number) else {
+		return Err(Error::not_found());
+	};
+
+	if matches!(
+		block_status,
+		BlockStatus::Unavailable | BlockStatus::Pending | BlockStatus::VerifyingHeader
+	) || state.is_header_pruned(block_number)
+	{
+		return Err(Error::bad_request_unknown(
+			"Block equivocations are not available",
+		));
+	};
+
+	let equivocations = db
+		.get_equivocations(block_number)
+		.map_err(Error::internal_server_error)?;
+
+	Ok((block_number, equivocations).into())
+}
+
+pub async fn block_sampling(
+	block_number: u32,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> Result<Sampling, Error> {
+	let state = state.lock().expect("Lock should be acquired");
+
+	let Some(block_status) = block_status(&config.sync_start_block, &state, block_number) else {
+		return Err(Error::not_found());
+	};
+
+	if !matches!(
+		block_status,
+		BlockStatus::VerifyingData | BlockStatus::Finished | BlockStatus::Failed
+	) {
+		return Err(Error::bad_request_unknown(
+			"Block sampling is not available",
+		));
+	};
+
+	let Some(record) = db
+		.get_sampling(block_number)
+		.map_err(Error::internal_server_error)?
+	else {
+		return Err(Error::not_found());
+	};
+
+	Ok((block_number, record).into())
+}
+
 pub async fn block_data(
 	block_number: u32,
 	query: DataQuery,
@@ -148,15 +336,34 @@ pub async fn block_data(
 ) -> Result<DataResponse, Error> {
 	let state = state.lock().expect("Lock should be acquired");
 
-	let Some(app_id) = config.app_id else {
+	let Some(app_id) = query
+		.app_id
+		.or(config.app_id)
+		.or_else(|| state.app_ids.first().copied())
+	else {
 		return Err(Error::not_found());
 	};
 
+	// In full block reconstruction mode, data of all decoded applications is available
+	let is_decoded = config.full_block_reconstruction && state.apps.contains_key(&app_id);
+	if !state.app_ids.contains(&app_id) && !is_decoded {
+		return Err(Error::not_found());
+	}
+
 	let Some(block_status) = block_status(&config.sync_start_block, &state, block_number) else {
 		return Err(Error::not_found());
 	};
 
-	if block_status != BlockStatus::Finished {
+	let is_app_data_verified = state
+		.apps
+		.get(&app_id)
+		.map(|app_state| app_state.contains(block_number))
+		.unwrap_or(false);
+
+	if block_status != BlockStatus::Finished
+		|| !is_app_data_verified
+		|| state.is_data_pruned(block_number)
+	{
 		return Err(Error::bad_request_unknown("Block data is not available"));
 	};
 
@@ -187,6 +394,92 @@ pub async fn block_data(
 	})
 }
 
+pub async fn data_proof(
+	block_number: u32,
+	transaction_index: u32,
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+	prover: Arc<impl proofs::Prove>,
+) -> Result<DataProof, Error> {
+	let verified_app_ids = {
+		let state = state.lock().expect("Lock should be acquired");
+
+		let Some(block_status) = block_status(&config.sync_start_block, &state, block_number)
+		else {
+			return Err(Error::not_found());
+		};
+
+		if !matches!(
+			block_status,
+			BlockStatus::VerifyingData | BlockStatus::Finished
+		) || state.is_header_pruned(block_number)
+		{
+			return Err(Error::bad_request_unknown("Data proof is not available"));
+		};
+
+		if state.is_data_pruned(block_number) {
+			vec![]
+		} else {
+			state
+				.apps
+				.iter()
+				.filter(|(_, app_state)| app_state.contains(block_number))
+				.map(|(&app_id, _)| app_id)
+				.collect::<Vec<_>>()
+		}
+	};
+
+	let header = db
+		.get_header(block_number)
+		.and_then(|header| header.ok_or_else(|| anyhow!("Header not found")))
+		.map_err(Error::internal_server_error)?;
+
+	let block_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+	let (_, _, data_root, _) = extract_kate(&header.extension);
+
+	let data_proof = prover
+		.query_data_proof(transaction_index, block_hash)
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	if !proof::verify_data_proof(data_root, &data_proof) {
+		return Err(Error::internal_server_error(anyhow!(
+			"Data proof is not valid"
+		)));
+	}
+
+	if data_proof.leaf_index != transaction_index {
+		return Err(Error::internal_server_error(anyhow!(
+			"Data proof leaf index {} doesn't match transaction index {transaction_index}",
+			data_proof.leaf_index
+		)));
+	}
+
+	// Proven leaf has to be the hash of the data verified by the light client
+	let mut is_leaf_verified = false;
+	for app_id in verified_app_ids {
+		let data = db
+			.get_data(app_id, block_number)
+			.map_err(Error::internal_server_error)?
+			.unwrap_or_default();
+		for extrinsic in data {
+			let leaf = decode_app_data(&extrinsic)
+				.map_err(Error::internal_server_error)?
+				.map(|data| H256::from(keccak_256(&data)));
+			is_leaf_verified |= leaf == Some(data_proof.leaf);
+		}
+	}
+
+	if !is_leaf_verified {
+		return Err(Error::bad_request_unknown(
+			"Transaction data is not verified",
+		));
+	}
+
+	Ok((block_number, transaction_index, data_proof).into())
+}
+
 pub async fn handle_rejection(error: Rejection) -> Result<impl Reply, Rejection> {
 	if error.find::<InternalServerError>().is_some() {
 		return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
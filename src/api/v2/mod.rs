@@ -4,21 +4,22 @@ use self::{
 };
 use crate::{
 	api::v2::types::Topic,
-	data::{Database, RocksDB},
+	data::Database,
 	rpc::Node,
-	types::{RuntimeConfig, State},
+	types::{AppBackfill, RuntimeConfig, State},
 };
-use avail_subxt::avail;
+use avail_subxt::{avail, utils::H256};
 use std::{
 	convert::Infallible,
 	fmt::Display,
 	sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, error, info};
 use warp::{Filter, Rejection, Reply};
 
 mod handlers;
+mod proofs;
 mod transactions;
 pub mod types;
 mod ws;
@@ -85,6 +86,62 @@ fn block_header_route(
 		.map(log_internal_server_error)
 }
 
+fn block_header_by_hash_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / H256 / "header")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || db.clone()))
+		.then(handlers::block_header_by_hash)
+		.map(log_internal_server_error)
+}
+
+fn block_justification_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "justification")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || db.clone()))
+		.then(handlers::block_justification)
+		.map(log_internal_server_error)
+}
+
+fn block_equivocations_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "equivocations")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || db.clone()))
+		.then(handlers::block_equivocations)
+		.map(log_internal_server_error)
+}
+
+fn block_sampling_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "sampling")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || db.clone()))
+		.then(handlers::block_sampling)
+		.map(log_internal_server_error)
+}
+
 fn block_data_route(
 	config: RuntimeConfig,
 	state: Arc<Mutex<State>>,
@@ -100,6 +157,22 @@ fn block_data_route(
 		.map(log_internal_server_error)
 }
 
+fn data_proof_route(
+	config: RuntimeConfig,
+	state: Arc<Mutex<State>>,
+	db: impl Database,
+	prover: Arc<impl proofs::Prove + Clone + Send + Sync>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "data" / u32 / "proof")
+		.and(warp::get())
+		.and(warp::any().map(move || config.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || db.clone()))
+		.and(warp::any().map(move || prover.clone()))
+		.then(handlers::data_proof)
+		.map(log_internal_server_error)
+}
+
 fn submit_route(
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync>>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -111,6 +184,30 @@ fn submit_route(
 		.map(log_internal_server_error)
 }
 
+fn add_app_route(
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
+	state: Arc<Mutex<State>>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "apps")
+		.and(warp::post())
+		.and_then(move || optionally(backfill_sender.clone()))
+		.and(warp::any().map(move || state.clone()))
+		.and(warp::body::json())
+		.then(handlers::add_app)
+		.map(log_internal_server_error)
+}
+
+fn remove_app_route(
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
+	state: Arc<Mutex<State>>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "apps" / u32)
+		.and(warp::delete())
+		.and_then(move |app_id| optionally(backfill_sender.clone().map(|_| app_id)))
+		.and(warp::any().map(move || state.clone()))
+		.map(handlers::remove_app)
+}
+
 fn subscriptions_route(
 	clients: WsClients,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -128,6 +225,7 @@ fn ws_route(
 	node: Node,
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync + 'static>>,
 	state: Arc<Mutex<State>>,
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	warp::path!("v2" / "ws" / String)
 		.and(warp::ws())
@@ -137,6 +235,7 @@ fn ws_route(
 		.and(warp::any().map(move || node.clone()))
 		.and(warp::any().map(move || submitter.clone()))
 		.and(warp::any().map(move || state.clone()))
+		.and(warp::any().map(move || backfill_sender.clone()))
 		.and_then(handlers::ws)
 }
 
@@ -187,7 +286,8 @@ pub fn routes(
 	config: RuntimeConfig,
 	node_client: avail::Client,
 	ws_clients: WsClients,
-	db: RocksDB,
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
+	db: impl Database,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	let version = Version {
 		version,
@@ -197,6 +297,10 @@ pub fn routes(
 	let app_id = config.app_id.as_ref();
 	let pair_signer = config.avail_secret_key.clone().map(From::from);
 
+	let prover = Arc::new(proofs::Prover {
+		node_client: node_client.clone(),
+	});
+
 	let submitter = app_id.map(|&app_id| {
 		Arc::new(transactions::Submitter {
 			node_client,
@@ -213,11 +317,45 @@ pub fn routes(
 			state.clone(),
 			db.clone(),
 		))
+		.or(block_header_by_hash_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+		))
+		.or(block_justification_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+		))
+		.or(block_equivocations_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+		))
+		.or(block_sampling_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+		))
 		.or(block_data_route(config.clone(), state.clone(), db.clone()))
+		.or(data_proof_route(
+			config.clone(),
+			state.clone(),
+			db.clone(),
+			prover,
+		))
 		.or(subscriptions_route(ws_clients.clone()))
 		.or(submit_route(submitter.clone()))
+		.or(add_app_route(backfill_sender.clone(), state.clone()))
+		.or(remove_app_route(backfill_sender.clone(), state.clone()))
 		.or(ws_route(
-			ws_clients, version, config, node, submitter, state,
+			ws_clients,
+			version,
+			config,
+			node,
+			submitter,
+			state,
+			backfill_sender,
 		))
 		.recover(handle_rejection)
 }
@@ -225,17 +363,23 @@ pub fn routes(
 #[cfg(test)]
 mod tests {
 	use super::{
-		block_data_route, block_header_route, block_route, submit_route, transactions,
-		types::Transaction,
+		block_data_route, block_equivocations_route, block_header_route, block_justification_route,
+		block_route, proofs, submit_route, transactions, types::Transaction,
 	};
 	use crate::{
 		api::v2::types::{
 			DataField, ErrorCode, SubmitResponse, Subscription, SubscriptionId, Topic, Version,
 			WsClients, WsError, WsResponse,
 		},
-		data::Database,
+		data::{BlockBatch, Database, MemoryDB},
 		rpc::Node,
-		types::{BlockRange, OptionBlockRange, RuntimeConfig, State},
+		test_utils::header,
+		types::{
+			AppBackfill, AppState, AvailabilityFailure, BlockRange, CellSource, Commit,
+			ConfidenceCounts, DataProof, Equivocation, FailureReason, FinalityJustification,
+			GrandpaJustification, OptionBlockRange, Precommit, RuntimeConfig, SampledCell,
+			SamplingRecord, SignedPrecommit, State,
+		},
 	};
 	use async_trait::async_trait;
 	use avail_subxt::{
@@ -247,15 +391,15 @@ mod tests {
 		primitives::Header as DaHeader,
 	};
 	use hyper::StatusCode;
-	use kate_recovery::{com::AppData, matrix::Partition};
-	use sp_core::H256;
+	use kate_recovery::matrix::Partition;
+	use sp_core::{ed25519, keccak_256, H256};
 	use std::{
-		collections::HashSet,
+		collections::{BTreeSet, HashMap, HashSet},
 		str::FromStr,
 		sync::{Arc, Mutex},
 	};
-	use subxt::config::substrate::Digest;
 	use test_case::test_case;
+	use tokio::sync::mpsc;
 	use uuid::Uuid;
 
 	fn v1() -> Version {
@@ -334,6 +478,18 @@ mod tests {
 			state.sync_confidence_achieved.set(19);
 			state.sync_data_verified.set(10);
 			state.sync_data_verified.set(18);
+			state.app_ids = BTreeSet::from([1, 2]);
+			let app_state = state.apps.entry(1).or_default();
+			app_state.data_verified.set(20);
+			app_state.data_verified.set(29);
+			app_state.sync_data_verified.set(10);
+			app_state.sync_data_verified.set(18);
+			let app_state = state.apps.entry(2).or_default();
+			app_state.data_verified.set(25);
+			app_state.backfilled.set(21);
+			app_state.backfilled.set(23);
+			state.add_missed_block(24);
+			state.add_missed_block(25);
 		}
 
 		let route = super::status_route(runtime_config, Node::default(), state);
@@ -344,7 +500,7 @@ mod tests {
 			.await;
 
 		let expected = format!(
-			r#"{{"modes":["light","app","partition"],"app_id":1,"genesis_hash":"{GENESIS_HASH}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}}}},"partition":"1/10"}}"#
+			r#"{{"modes":["light","app","partition"],"app_id":1,"apps":{{"1":{{"app_data":{{"first":20,"last":29}},"historical_sync_app_data":{{"first":10,"last":18}}}},"2":{{"app_data":{{"first":25,"last":25}},"backfilled_app_data":{{"first":21,"last":23}}}}}},"genesis_hash":"{GENESIS_HASH}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}},"gaps":[{{"first":24,"last":25}}]}},"partition":"1/10"}}"#
 		);
 		assert_eq!(response.body(), &expected);
 	}
@@ -360,7 +516,7 @@ mod tests {
 			let mut state = state.lock().unwrap();
 			state.latest = latest;
 		}
-		let route = super::block_route(config, state, MockDatabase::default());
+		let route = super::block_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path(&format!("/v2/blocks/{block_number}"))
@@ -380,14 +536,9 @@ mod tests {
 			state.header_verified.set(10);
 			state.data_verified.set(10);
 		}
-		let route = super::block_route(
-			config,
-			state,
-			MockDatabase {
-				confidence: Some(4),
-				..Default::default()
-			},
-		);
+		let db = MemoryDB::default();
+		db.store_confidence(10, 4).unwrap();
+		let route = super::block_route(config, state, db);
 		let response = warp::test::request()
 			.method("GET")
 			.path("/v2/blocks/10")
@@ -401,6 +552,70 @@ mod tests {
 		);
 	}
 
+	#[tokio::test]
+	async fn block_route_confidence_counts() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			header_verified: Some(BlockRange::init(10)),
+			data_verified: Some(BlockRange::init(10)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		let block = BlockBatch {
+			confidence: Some(4),
+			confidence_counts: Some(ConfidenceCounts { dht: 3, rpc: 1 }),
+			..Default::default()
+		};
+		db.store_block(10, block).unwrap();
+		let route = super::block_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/10")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"status":"finished","confidence":93.75,"dht_confidence":87.5,"rpc_confidence":50.0}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn block_route_failed() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			header_verified: Some(BlockRange::init(10)),
+			availability_failed: BTreeSet::from([10]),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		let block = BlockBatch {
+			failure: Some(AvailabilityFailure {
+				reason: FailureReason::CellsUnavailable,
+				cells_requested: 10,
+				cells_fetched: 4,
+				cells_unverified: 0,
+			}),
+			..Default::default()
+		};
+		db.store_block(10, block).unwrap();
+		let route = super::block_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/10")
+			.reply(&route)
+			.await;
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"status":"failed","confidence":null,"failure":{"reason":"cells-unavailable","cells_requested":10,"cells_fetched":4,"cells_unverified":0}}"#
+		);
+	}
+
 	#[test_case(0, r#"Block header is not available"#  ; "Block is unavailable")]
 	#[test_case(6, r#"Block header is not available"#  ; "Block is pending")]
 	#[test_case(10, r#"Block header is not available"#  ; "Block is in verifying-header state")]
@@ -417,7 +632,7 @@ mod tests {
 			..Default::default()
 		}));
 
-		let route = super::block_header_route(config, state, MockDatabase::default());
+		let route = super::block_header_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path(&format!("/v2/blocks/{block_number}/header"))
@@ -435,7 +650,7 @@ mod tests {
 			..Default::default()
 		}));
 
-		let route = super::block_header_route(config, state, MockDatabase::default());
+		let route = super::block_header_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path("/v2/blocks/11/header")
@@ -444,47 +659,303 @@ mod tests {
 		assert_eq!(response.status(), StatusCode::NOT_FOUND);
 	}
 
-	fn header() -> DaHeader {
-		DaHeader {
-			parent_hash: H256::default(),
-			number: 1,
-			state_root: H256::default(),
-			extrinsics_root: H256::default(),
-			extension: HeaderExtension::V2(v2::HeaderExtension {
-				commitment: KateCommitment::default(),
-				app_lookup: CompactDataLookup {
-					size: 0,
-					index: vec![],
+	#[tokio::test]
+	async fn block_header_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_header(1, &header(1, H256::default())).unwrap();
+		let route = super::block_header_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/header")
+			.reply(&route)
+			.await;
+		assert_eq!(
+			response.body(),
+			r#"{"hash":"0x02419eab253b08659745c684e0bb26c15f327336d46c2c1e76e6cb67f734f79f","parent_hash":"0x0000000000000000000000000000000000000000000000000000000000000000","number":1,"state_root":"0x0000000000000000000000000000000000000000000000000000000000000000","extrinsics_root":"0x0000000000000000000000000000000000000000000000000000000000000000","extension":{"rows":0,"cols":0,"data_root":null,"commitments":[],"app_lookup":{"size":0,"index":[]}}}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn block_header_by_hash_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_header(1, &header(1, H256::default())).unwrap();
+		let route = super::block_header_by_hash_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/0x02419eab253b08659745c684e0bb26c15f327336d46c2c1e76e6cb67f734f79f/header")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		let header: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+		assert_eq!(header["number"], 1);
+	}
+
+	fn justification() -> FinalityJustification {
+		FinalityJustification {
+			set_id: 2,
+			justification: GrandpaJustification {
+				round: 1,
+				commit: Commit {
+					target_hash: H256::repeat_byte(1),
+					target_number: 5,
+					precommits: vec![],
 				},
-			}),
-			digest: Digest { logs: vec![] },
+				votes_ancestries: vec![],
+			},
 		}
 	}
 
 	#[tokio::test]
-	async fn block_header_route_ok() {
+	async fn block_justification_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 5,
+			header_verified: Some(BlockRange::init(5)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_justification(5, &justification()).unwrap();
+		let route = block_justification_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/5/justification")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"block_number":5,"block_hash":"0x0101010101010101010101010101010101010101010101010101010101010101","round":1,"set_id":2,"justification":"0x01000000000000000101010101010101010101010101010101010101010101010101010101010101050000000000"}"#
+		);
+	}
+
+	#[test_case(4, StatusCode::NOT_FOUND ; "Justification is not stored")]
+	#[test_case(6, StatusCode::NOT_FOUND ; "Block is not yet finalized")]
+	#[test_case(1, StatusCode::BAD_REQUEST ; "Block is unavailable")]
+	#[tokio::test]
+	async fn block_justification_route_not_available(block_number: u32, expected: StatusCode) {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 5,
+			header_verified: Some(BlockRange { first: 4, last: 5 }),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_justification(5, &justification()).unwrap();
+		let route = block_justification_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/{block_number}/justification"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+	}
+
+	fn signed_precommit(target_number: u32) -> SignedPrecommit {
+		SignedPrecommit {
+			precommit: Precommit {
+				target_hash: H256::repeat_byte(target_number as u8),
+				target_number,
+			},
+			signature: ed25519::Signature([1; 64]),
+			id: ed25519::Public([2; 32]),
+		}
+	}
+
+	#[tokio::test]
+	async fn block_equivocations_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 5,
+			header_verified: Some(BlockRange::init(5)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		let equivocation = Equivocation {
+			set_id: 2,
+			round: 1,
+			offender: ed25519::Public([2; 32]),
+			first: signed_precommit(5),
+			second: signed_precommit(6),
+		};
+		db.store_equivocations(5, &[equivocation]).unwrap();
+		let route = block_equivocations_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/5/equivocations")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		let signature = format!("0x{}", "01".repeat(64));
+		let expected = format!(
+			r#"{{"block_number":5,"equivocations":[{{"set_id":2,"round":1,"offender":"0x{}","first":{{"target_hash":"0x{}","target_number":5,"signature":"{signature}"}},"second":{{"target_hash":"0x{}","target_number":6,"signature":"{signature}"}}}}]}}"#,
+			"02".repeat(32),
+			"05".repeat(32),
+			"06".repeat(32),
+		);
+		assert_eq!(response.body(), &expected);
+	}
+
+	#[test_case(4, StatusCode::OK ; "No equivocations")]
+	#[test_case(6, StatusCode::NOT_FOUND ; "Block is not yet finalized")]
+	#[test_case(1, StatusCode::BAD_REQUEST ; "Block is unavailable")]
+	#[tokio::test]
+	async fn block_equivocations_route_no_equivocations(block_number: u32, expected: StatusCode) {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 5,
+			header_verified: Some(BlockRange { first: 4, last: 5 }),
+			..Default::default()
+		}));
+		let route = block_equivocations_route(config, state, MemoryDB::default());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/{block_number}/equivocations"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+		if expected == StatusCode::OK {
+			assert_eq!(
+				response.body(),
+				&format!(r#"{{"block_number":{block_number},"equivocations":[]}}"#)
+			);
+		}
+	}
+
+	#[tokio::test]
+	async fn block_header_by_hash_route_not_found() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let route = super::block_header_by_hash_route(config, state, MemoryDB::default());
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/0x02419eab253b08659745c684e0bb26c15f327336d46c2c1e76e6cb67f734f79f/header")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+
+	#[tokio::test]
+	async fn block_sampling_route_ok() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			confidence_achieved: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		let sampling = SamplingRecord {
+			cells: vec![
+				SampledCell {
+					row: 0,
+					col: 1,
+					source: CellSource::Dht,
+					verified: true,
+				},
+				SampledCell {
+					row: 1,
+					col: 0,
+					source: CellSource::Rpc,
+					verified: false,
+				},
+			],
+		};
+		let block = BlockBatch {
+			sampling: Some(sampling),
+			..Default::default()
+		};
+		db.store_block(1, block).unwrap();
+		let route = super::block_sampling_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/sampling")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"block_number":1,"cells":[{"row":0,"col":1,"source":"dht","verified":true},{"row":1,"col":0,"source":"rpc","verified":false}]}"#
+		);
+	}
+
+	#[tokio::test]
+	async fn block_sampling_route_failed() {
 		let config = RuntimeConfig::default();
 		let state = Arc::new(Mutex::new(State {
 			latest: 1,
 			header_verified: Some(BlockRange::init(1)),
+			availability_failed: BTreeSet::from([1]),
 			..Default::default()
 		}));
-		let database = MockDatabase {
-			header: Some(header()),
+		let db = MemoryDB::default();
+		let sampling = SamplingRecord {
+			cells: vec![SampledCell {
+				row: 0,
+				col: 1,
+				source: CellSource::Unavailable,
+				verified: false,
+			}],
+		};
+		let block = BlockBatch {
+			sampling: Some(sampling),
 			..Default::default()
 		};
-		let route = super::block_header_route(config, state, database);
+		db.store_block(1, block).unwrap();
+		let route = super::block_sampling_route(config, state, db);
 		let response = warp::test::request()
 			.method("GET")
-			.path("/v2/blocks/1/header")
+			.path("/v2/blocks/1/sampling")
 			.reply(&route)
 			.await;
+		assert_eq!(response.status(), StatusCode::OK);
 		assert_eq!(
 			response.body(),
-			r#"{"hash":"0x02419eab253b08659745c684e0bb26c15f327336d46c2c1e76e6cb67f734f79f","parent_hash":"0x0000000000000000000000000000000000000000000000000000000000000000","number":1,"state_root":"0x0000000000000000000000000000000000000000000000000000000000000000","extrinsics_root":"0x0000000000000000000000000000000000000000000000000000000000000000","extension":{"rows":0,"cols":0,"data_root":null,"commitments":[],"app_lookup":{"size":0,"index":[]}}}"#
+			r#"{"block_number":1,"cells":[{"row":0,"col":1,"source":"unavailable","verified":false}]}"#
 		);
 	}
 
+	#[tokio::test]
+	async fn block_sampling_route_bad_request() {
+		let config = RuntimeConfig::default();
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let route = super::block_sampling_route(config, state, MemoryDB::default());
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/sampling")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert_eq!(response.body(), "Block sampling is not available");
+	}
+
+	fn app_state(app_id: u32, block_number: u32) -> HashMap<u32, AppState> {
+		let app_state = AppState {
+			data_verified: Some(BlockRange::init(block_number)),
+			..Default::default()
+		};
+		HashMap::from([(app_id, app_state)])
+	}
+
 	#[test_case(0, r#"Block data is not available"#  ; "Block is unavailable")]
 	#[test_case(6, r#"Block data is not available"#  ; "Block is pending")]
 	#[test_case(8, r#"Block data is not available"#  ; "Block is in verifying-data state")]
@@ -503,10 +974,12 @@ mod tests {
 			header_verified: Some(BlockRange::init(10)),
 			confidence_achieved: Some(BlockRange::init(9)),
 			data_verified: Some(BlockRange::init(8)),
+			app_ids: BTreeSet::from([1]),
+			apps: app_state(1, 8),
 			..Default::default()
 		}));
 
-		let route = super::block_data_route(config, state, MockDatabase::default());
+		let route = super::block_data_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path(&format!("/v2/blocks/{block_number}/data"))
@@ -524,7 +997,7 @@ mod tests {
 			..Default::default()
 		}));
 
-		let route = super::block_data_route(config, state, MockDatabase::default());
+		let route = super::block_data_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path("/v2/blocks/11/data")
@@ -544,10 +1017,12 @@ mod tests {
 			header_verified: Some(BlockRange::init(5)),
 			confidence_achieved: Some(BlockRange::init(5)),
 			data_verified: Some(BlockRange::init(5)),
+			app_ids: BTreeSet::from([1]),
+			apps: app_state(1, 5),
 			..Default::default()
 		}));
 
-		let route = super::block_data_route(config, state, MockDatabase::default());
+		let route = super::block_data_route(config, state, MemoryDB::default());
 		let response = warp::test::request()
 			.method("GET")
 			.path("/v2/blocks/5/data")
@@ -571,10 +1046,15 @@ mod tests {
 			header_verified: Some(BlockRange::init(5)),
 			confidence_achieved: Some(BlockRange::init(5)),
 			data_verified: Some(BlockRange::init(5)),
+			app_ids: BTreeSet::from([1]),
+			apps: app_state(1, 5),
 			..Default::default()
 		}));
-		let db = MockDatabase {
-			app_data: Some(vec![vec![
+		let db = MemoryDB::default();
+		db.store_data(
+			1,
+			5,
+			&vec![vec![
 				189, 1, 132, 0, 212, 53, 147, 199, 21, 253, 211, 28, 97, 20, 26, 189, 4, 169, 159,
 				214, 130, 44, 133, 88, 133, 76, 205, 227, 154, 86, 132, 231, 165, 109, 162, 125, 1,
 				50, 12, 43, 176, 19, 42, 23, 73, 70, 223, 198, 180, 103, 34, 60, 246, 184, 49, 140,
@@ -582,9 +1062,9 @@ mod tests {
 				8, 4, 68, 137, 5, 156, 94, 209, 7, 169, 105, 62, 63, 1, 122, 253, 195, 112, 173,
 				239, 21, 73, 163, 240, 106, 109, 131, 0, 4, 0, 4, 29, 1, 20, 116, 101, 115, 116,
 				10,
-			]]),
-			..Default::default()
-		};
+			]],
+		)
+		.unwrap();
 
 		let route = super::block_data_route(config, state, db);
 		let response = warp::test::request()
@@ -599,11 +1079,182 @@ mod tests {
 		);
 	}
 
+	#[test_case(1, StatusCode::BAD_REQUEST ; "unverified app")]
+	#[test_case(2, StatusCode::OK ; "tracked app")]
+	#[test_case(3, StatusCode::NOT_FOUND ; "untracked app")]
+	#[tokio::test]
+	async fn block_data_route_app_id(app_id: u32, expected: StatusCode) {
+		let config = RuntimeConfig {
+			app_id: Some(1),
+			..Default::default()
+		};
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			header_verified: Some(BlockRange::init(5)),
+			confidence_achieved: Some(BlockRange::init(5)),
+			data_verified: Some(BlockRange::init(5)),
+			app_ids: BTreeSet::from([1, 2]),
+			apps: app_state(2, 5),
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_data(2, 5, &vec![]).unwrap();
+
+		let route = super::block_data_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/5/data?app_id={app_id}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+	}
+
+	#[test_case(1, StatusCode::OK ; "tracked app")]
+	#[test_case(2, StatusCode::OK ; "decoded app")]
+	#[test_case(3, StatusCode::NOT_FOUND ; "app not in blocks")]
+	#[tokio::test]
+	async fn block_data_route_full_block_reconstruction(app_id: u32, expected: StatusCode) {
+		let config = RuntimeConfig {
+			app_id: Some(1),
+			full_block_reconstruction: true,
+			..Default::default()
+		};
+		let mut apps = app_state(1, 5);
+		apps.extend(app_state(2, 5));
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			header_verified: Some(BlockRange::init(5)),
+			confidence_achieved: Some(BlockRange::init(5)),
+			data_verified: Some(BlockRange::init(5)),
+			app_ids: BTreeSet::from([1]),
+			apps,
+			..Default::default()
+		}));
+		let db = MemoryDB::default();
+		db.store_data(1, 5, &vec![]).unwrap();
+		db.store_data(2, 5, &vec![]).unwrap();
+
+		let route = super::block_data_route(config, state, db);
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/5/data?app_id={app_id}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+	}
+
+	#[test_case(r#"{"app_id":2}"#, StatusCode::OK, None ; "Add app")]
+	#[test_case(r#"{"app_id":2,"backfill":{"first":3,"last":5}}"#, StatusCode::OK, Some((3, 5)) ; "Add app with backfill")]
+	#[test_case(r#"{"app_id":0}"#, StatusCode::BAD_REQUEST, None ; "App ID 0")]
+	#[test_case(r#"{"app_id":2,"backfill":{"first":5,"last":3}}"#, StatusCode::BAD_REQUEST, None ; "Backfill range is inverted")]
+	#[test_case(r#"{"app_id":2,"backfill":{"first":3,"last":11}}"#, StatusCode::BAD_REQUEST, None ; "Backfill range is after latest block")]
+	#[test_case(r#"{"app_id":2,"backfill":{"first":1,"last":5}}"#, StatusCode::BAD_REQUEST, None ; "Backfill range is pruned")]
+	#[test_case(r#"{"app_id":1,"backfill":{"first":3,"last":5}}"#, StatusCode::BAD_REQUEST, None ; "Backfill of tracked app")]
+	#[tokio::test]
+	async fn add_app_route(body: &str, expected: StatusCode, backfill: Option<(u32, u32)>) {
+		let (backfill_tx, mut backfill_rx) = mpsc::channel(1);
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			data_pruned_below: Some(2),
+			app_ids: BTreeSet::from([1]),
+			..Default::default()
+		}));
+		let route = super::add_app_route(Some(backfill_tx), state.clone());
+		let response = warp::test::request()
+			.method("POST")
+			.path("/v2/apps")
+			.body(body)
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+
+		let app_ids = state.lock().unwrap().app_ids.clone();
+		if expected == StatusCode::OK {
+			assert_eq!(response.body(), r#"{"app_ids":[1,2]}"#);
+			assert_eq!(app_ids, BTreeSet::from([1, 2]));
+		} else {
+			assert_eq!(app_ids, BTreeSet::from([1]));
+		}
+
+		let request = backfill_rx.try_recv().ok().map(
+			|AppBackfill {
+			     app_id,
+			     first,
+			     last,
+			 }| (app_id, first, last),
+		);
+		assert_eq!(request, backfill.map(|(first, last)| (2, first, last)));
+	}
+
+	#[tokio::test]
+	async fn add_app_route_backfill_failed() {
+		let (backfill_tx, backfill_rx) = mpsc::channel(1);
+		drop(backfill_rx);
+		let state = Arc::new(Mutex::new(State {
+			latest: 10,
+			app_ids: BTreeSet::from([1]),
+			..Default::default()
+		}));
+		let route = super::add_app_route(Some(backfill_tx), state.clone());
+		let response = warp::test::request()
+			.method("POST")
+			.path("/v2/apps")
+			.body(r#"{"app_id":2,"backfill":{"first":3,"last":5}}"#)
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+		assert_eq!(state.lock().unwrap().app_ids, BTreeSet::from([1]));
+	}
+
+	#[tokio::test]
+	async fn add_app_route_not_enabled() {
+		let state = Arc::new(Mutex::new(State::default()));
+		let route = super::add_app_route(None, state);
+		let response = warp::test::request()
+			.method("POST")
+			.path("/v2/apps")
+			.body(r#"{"app_id":2}"#)
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+
+	#[test_case(2, StatusCode::OK ; "Remove app")]
+	#[test_case(3, StatusCode::NOT_FOUND ; "Remove untracked app")]
+	#[tokio::test]
+	async fn remove_app_route(app_id: u32, expected: StatusCode) {
+		let (backfill_tx, _) = mpsc::channel(1);
+		let state = Arc::new(Mutex::new(State {
+			app_ids: BTreeSet::from([1, 2]),
+			apps: app_state(2, 5),
+			..Default::default()
+		}));
+		let route = super::remove_app_route(Some(backfill_tx), state.clone());
+		let response = warp::test::request()
+			.method("DELETE")
+			.path(&format!("/v2/apps/{app_id}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), expected);
+
+		let state = state.lock().unwrap();
+		if expected == StatusCode::OK {
+			assert_eq!(response.body(), r#"{"app_ids":[1]}"#);
+			assert_eq!(state.app_ids, BTreeSet::from([1]));
+			assert!(state.apps.is_empty());
+		} else {
+			assert_eq!(state.app_ids, BTreeSet::from([1, 2]));
+		}
+	}
+
 	fn all_topics() -> HashSet<Topic> {
 		vec![
 			Topic::HeaderVerified,
 			Topic::ConfidenceAchieved,
 			Topic::DataVerified,
+			Topic::AvailabilityFailed,
+			Topic::ChainViolation,
+			Topic::Equivocation,
 		]
 		.into_iter()
 		.collect()
@@ -635,27 +1286,167 @@ mod tests {
 		}
 	}
 
-	#[derive(Clone, Default)]
-	struct MockDatabase {
-		confidence: Option<u32>,
-		header: Option<DaHeader>,
-		app_data: Option<AppData>,
+	#[derive(Clone)]
+	struct MockProver {
+		pub data_proof: DataProof,
 	}
 
-	impl Database for MockDatabase {
-		fn get_confidence(&self, _: u32) -> anyhow::Result<Option<u32>> {
-			Ok(self.confidence)
+	#[async_trait]
+	impl proofs::Prove for MockProver {
+		async fn query_data_proof(&self, _: u32, _: H256) -> anyhow::Result<DataProof> {
+			Ok(self.data_proof.clone())
 		}
+	}
 
-		fn get_header(&self, _: u32) -> anyhow::Result<Option<DaHeader>> {
-			Ok(self.header.clone())
+	fn data_proof(leaf: H256) -> DataProof {
+		DataProof {
+			root: leaf,
+			proof: vec![],
+			number_of_leaves: 1,
+			leaf_index: 0,
+			leaf,
 		}
+	}
+
+	fn data_proof_state() -> Arc<Mutex<State>> {
+		Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			data_verified: Some(BlockRange::init(1)),
+			app_ids: BTreeSet::from([1]),
+			apps: app_state(1, 1),
+			..Default::default()
+		}))
+	}
 
-		fn get_data(&self, _app_id: u32, _: u32) -> anyhow::Result<Option<AppData>> {
-			Ok(self.app_data.clone())
+	/// Extrinsic submitting `test\n` data
+	const DATA_EXTRINSIC: [u8; 113] = [
+		189, 1, 132, 0, 212, 53, 147, 199, 21, 253, 211, 28, 97, 20, 26, 189, 4, 169, 159, 214,
+		130, 44, 133, 88, 133, 76, 205, 227, 154, 86, 132, 231, 165, 109, 162, 125, 1, 50, 12, 43,
+		176, 19, 42, 23, 73, 70, 223, 198, 180, 103, 34, 60, 246, 184, 49, 140, 113, 174, 234, 229,
+		95, 71, 18, 92, 158, 185, 168, 140, 126, 12, 191, 156, 50, 234, 8, 4, 68, 137, 5, 156, 94,
+		209, 7, 169, 105, 62, 63, 1, 122, 253, 195, 112, 173, 239, 21, 73, 163, 240, 106, 109, 131,
+		0, 4, 0, 4, 29, 1, 20, 116, 101, 115, 116, 10,
+	];
+
+	fn data_proof_db(data_root: H256) -> MemoryDB {
+		let db = MemoryDB::default();
+		db.store_header(1, &data_root_header(data_root)).unwrap();
+		db.store_data(1, 1, &vec![DATA_EXTRINSIC.to_vec()]).unwrap();
+		db
+	}
+
+	fn data_root_header(data_root: H256) -> DaHeader {
+		DaHeader {
+			extension: HeaderExtension::V2(v2::HeaderExtension {
+				commitment: KateCommitment {
+					data_root,
+					..Default::default()
+				},
+				app_lookup: CompactDataLookup {
+					size: 0,
+					index: vec![],
+				},
+			}),
+			..header(1, H256::default())
 		}
 	}
 
+	#[tokio::test]
+	async fn data_proof_route_ok() {
+		let data_root: H256 = keccak_256(b"test\n").into();
+		let db = data_proof_db(data_root);
+		let prover = Arc::new(MockProver {
+			data_proof: data_proof(data_root),
+		});
+		let route =
+			super::data_proof_route(RuntimeConfig::default(), data_proof_state(), db, prover);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/data/0/proof")
+			.reply(&route)
+			.await;
+		let root = format!("{data_root:?}");
+		let expected = format!(
+			r#"{{"block_number":1,"transaction_index":0,"data_root":"{root}","proof":[],"number_of_leaves":1,"leaf_index":0,"leaf":"{root}"}}"#
+		);
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(response.body(), &expected);
+	}
+
+	#[tokio::test]
+	async fn data_proof_route_invalid_proof() {
+		let db = data_proof_db(H256::repeat_byte(1));
+		let prover = Arc::new(MockProver {
+			data_proof: data_proof(H256::repeat_byte(2)),
+		});
+		let route =
+			super::data_proof_route(RuntimeConfig::default(), data_proof_state(), db, prover);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/data/0/proof")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	}
+
+	#[tokio::test]
+	async fn data_proof_route_invalid_leaf_index() {
+		let data_root: H256 = keccak_256(b"test\n").into();
+		let db = data_proof_db(data_root);
+		let prover = Arc::new(MockProver {
+			data_proof: data_proof(data_root),
+		});
+		let route =
+			super::data_proof_route(RuntimeConfig::default(), data_proof_state(), db, prover);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/data/2/proof")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	}
+
+	#[tokio::test]
+	async fn data_proof_route_unverified_leaf() {
+		let data_root = H256::repeat_byte(1);
+		let db = data_proof_db(data_root);
+		let prover = Arc::new(MockProver {
+			data_proof: data_proof(data_root),
+		});
+		let route =
+			super::data_proof_route(RuntimeConfig::default(), data_proof_state(), db, prover);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/1/data/0/proof")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert_eq!(response.body(), "Transaction data is not verified");
+	}
+
+	#[test_case(1, StatusCode::BAD_REQUEST ; "Block is in verifying-confidence state")]
+	#[test_case(2, StatusCode::NOT_FOUND ; "Block is not found")]
+	#[tokio::test]
+	async fn data_proof_route_unavailable(block_number: u32, status: StatusCode) {
+		let state = Arc::new(Mutex::new(State {
+			latest: 1,
+			header_verified: Some(BlockRange::init(1)),
+			..Default::default()
+		}));
+		let prover = Arc::new(MockProver {
+			data_proof: data_proof(H256::default()),
+		});
+		let route =
+			super::data_proof_route(RuntimeConfig::default(), state, MemoryDB::default(), prover);
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/{block_number}/data/0/proof"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), status);
+	}
+
 	#[test_case(r#"{"raw":""}"#, b"Request body deserialize error: unknown variant `raw`" ; "Invalid json schema")]
 	#[test_case(r#"{"data":"dHJhbnooNhY3Rpb24:"}"#, b"Request body deserialize error: Invalid byte" ; "Invalid base64 value")]
 	#[tokio::test]
@@ -704,7 +1495,7 @@ mod tests {
 		let clients = WsClients::default();
 		let route = super::subscriptions_route(clients.clone());
 
-		let body = r#"{"topics":["confidence-achieved","data-verified","header-verified"],"data_fields":["data","extrinsic"]}"#;
+		let body = r#"{"topics":["confidence-achieved","data-verified","header-verified","availability-failed","chain-violation","equivocation"],"data_fields":["data","extrinsic"]}"#;
 		let response = warp::test::request()
 			.method("POST")
 			.body(body)
@@ -746,6 +1537,7 @@ mod tests {
 				Node::default(),
 				submitter.map(Arc::new),
 				state.clone(),
+				None,
 			);
 			let ws_client = warp::test::ws()
 				.path(&format!("/v2/ws/{client_uuid}"))
@@ -848,6 +1640,21 @@ mod tests {
 		assert!(error.message.contains(expected));
 	}
 
+	#[test_case(r#"{"type":"add-app","request_id":"5f7e4bb6-0a4c-4b3c-9d1e-2b3e0b2f5a11","message":{"app_id":2}}"# ; "Add app")]
+	#[test_case(r#"{"type":"remove-app","request_id":"5f7e4bb6-0a4c-4b3c-9d1e-2b3e0b2f5a11","message":{"app_id":2}}"# ; "Remove app")]
+	#[tokio::test]
+	async fn ws_route_apps_not_enabled(request: &str) {
+		let mut test = MockSetup::new(RuntimeConfig::default(), None).await;
+		let response = test.ws_send_text(request).await;
+		let WsError::Error(error) = serde_json::from_str(&response).unwrap();
+		assert_eq!(error.error_code, ErrorCode::BadRequest);
+		assert_eq!(
+			error.request_id,
+			Some(to_uuid("5f7e4bb6-0a4c-4b3c-9d1e-2b3e0b2f5a11"))
+		);
+		assert_eq!(error.message, "App client is not enabled.");
+	}
+
 	#[tokio::test]
 	async fn ws_route_submit_data() {
 		let submitter = Some(MockSubmitter { has_signer: true });
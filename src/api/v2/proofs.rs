@@ -0,0 +1,26 @@
+use crate::{rpc, types::DataProof};
+use anyhow::Result;
+use async_trait::async_trait;
+use avail_subxt::{avail, utils::H256};
+
+#[async_trait]
+pub trait Prove {
+	async fn query_data_proof(&self, transaction_index: u32, block_hash: H256)
+		-> Result<DataProof>;
+}
+
+#[derive(Clone)]
+pub struct Prover {
+	pub node_client: avail::Client,
+}
+
+#[async_trait]
+impl Prove for Prover {
+	async fn query_data_proof(
+		&self,
+		transaction_index: u32,
+		block_hash: H256,
+	) -> Result<DataProof> {
+		rpc::get_data_proof(&self.node_client, transaction_index, block_hash).await
+	}
+}
@@ -11,7 +11,7 @@ use kate_recovery::{com::AppData, commitments, config, matrix::Partition};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sp_core::{blake2_256, H256};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeMap, HashMap, HashSet},
 	sync::Arc,
 	time::Instant,
 };
@@ -25,9 +25,11 @@ use warp::{
 use crate::{
 	rpc::Node,
 	types::{
-		self, block_matrix_partition_format, BlockVerified, OptionBlockRange, RuntimeConfig, State,
+		self, block_matrix_partition_format, AvailabilityFailure, BlockVerified, ChainViolation,
+		ChainViolationKind, FailureReason, FinalityJustification, OptionBlockRange, RuntimeConfig,
+		SamplingRecord, State,
 	},
-	utils::decode_app_data,
+	utils::{calculate_confidence, decode_app_data},
 };
 
 #[derive(Debug)]
@@ -47,7 +49,7 @@ impl Reply for Version {
 	}
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BlockRange {
 	pub first: u32,
 	pub last: u32,
@@ -80,6 +82,18 @@ pub struct Blocks {
 	pub app_data: Option<BlockRange>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub historical_sync: Option<HistoricalSync>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub gaps: Vec<BlockRange>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AppStatus {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub app_data: Option<BlockRange>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub historical_sync_app_data: Option<BlockRange>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub backfilled_app_data: Option<BlockRange>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -87,6 +101,8 @@ pub struct Status {
 	pub modes: Vec<Mode>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub app_id: Option<u32>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+	pub apps: BTreeMap<u32, AppStatus>,
 	pub genesis_hash: String,
 	pub network: String,
 	pub blocks: Blocks,
@@ -127,6 +143,29 @@ impl From<Base64> for String {
 	}
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddApp {
+	pub app_id: u32,
+	/// Range of already verified blocks to fetch application data for
+	pub backfill: Option<BlockRange>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RemoveApp {
+	pub app_id: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Apps {
+	pub app_ids: Vec<u32>,
+}
+
+impl Reply for Apps {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Transaction {
@@ -160,20 +199,64 @@ impl Status {
 	pub fn new(config: &RuntimeConfig, node: &Node, state: &State) -> Self {
 		let historical_sync = state.synced.map(|synced| HistoricalSync {
 			synced,
-			available: state.sync_confidence_achieved.as_ref().map(From::from),
-			app_data: state.sync_data_verified.as_ref().map(From::from),
+			available: state
+				.sync_confidence_achieved
+				.retained(state.confidence_pruned_below)
+				.as_ref()
+				.map(From::from),
+			app_data: state
+				.sync_data_verified
+				.retained(state.data_pruned_below)
+				.as_ref()
+				.map(From::from),
 		});
 
 		let blocks = Blocks {
 			latest: state.latest,
-			available: state.confidence_achieved.as_ref().map(From::from),
-			app_data: state.data_verified.as_ref().map(From::from),
+			available: state
+				.confidence_achieved
+				.retained(state.confidence_pruned_below)
+				.as_ref()
+				.map(From::from),
+			app_data: state
+				.data_verified
+				.retained(state.data_pruned_below)
+				.as_ref()
+				.map(From::from),
 			historical_sync,
+			gaps: state.gaps.iter().map(From::from).collect(),
 		};
 
+		let apps = state
+			.app_ids
+			.iter()
+			.map(|&app_id| {
+				let app_state = state.apps.get(&app_id).cloned().unwrap_or_default();
+				let app_status = AppStatus {
+					app_data: app_state
+						.data_verified
+						.retained(state.data_pruned_below)
+						.as_ref()
+						.map(From::from),
+					historical_sync_app_data: app_state
+						.sync_data_verified
+						.retained(state.data_pruned_below)
+						.as_ref()
+						.map(From::from),
+					backfilled_app_data: app_state
+						.backfilled
+						.retained(state.data_pruned_below)
+						.as_ref()
+						.map(From::from),
+				};
+				(app_id, app_status)
+			})
+			.collect();
+
 		Status {
 			modes: config.into(),
 			app_id: config.app_id,
+			apps,
 			genesis_hash: format!("{:?}", node.genesis_hash),
 			network: node.network(),
 			blocks,
@@ -194,7 +277,7 @@ impl From<&RuntimeConfig> for Vec<Mode> {
 	fn from(value: &RuntimeConfig) -> Self {
 		let mut result: Vec<Mode> = vec![];
 		result.push(Mode::Light);
-		if value.app_id.is_some() {
+		if !value.app_ids().is_empty() || value.full_block_reconstruction {
 			result.push(Mode::App);
 		}
 		if value.block_matrix_partition.is_some() {
@@ -216,6 +299,9 @@ pub enum Topic {
 	HeaderVerified,
 	ConfidenceAchieved,
 	DataVerified,
+	AvailabilityFailed,
+	ChainViolation,
+	Equivocation,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -246,6 +332,7 @@ pub enum BlockStatus {
 	VerifyingConfidence,
 	VerifyingData,
 	Finished,
+	Failed,
 }
 
 pub fn block_status(
@@ -260,10 +347,19 @@ pub fn block_status(
 	let first_block = state.header_verified.first().unwrap_or(state.latest);
 	let first_sync_block = sync_start_block.unwrap_or(first_block);
 
-	if block_number < first_sync_block {
+	if block_number < first_sync_block || state.is_confidence_pruned(block_number) {
 		return Some(BlockStatus::Unavailable);
 	}
 
+	if state.availability_failed.contains(&block_number) {
+		return Some(BlockStatus::Failed);
+	}
+
+	// Missed blocks are pending until processed by the sync client
+	if state.gaps.iter().any(|gap| gap.contains(block_number)) {
+		return Some(BlockStatus::Pending);
+	}
+
 	if block_number < first_block {
 		if state.sync_data_verified.contains(block_number) {
 			return Some(BlockStatus::Finished);
@@ -300,11 +396,47 @@ pub fn block_status(
 pub struct Block {
 	pub status: BlockStatus,
 	pub confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub dht_confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rpc_confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub failure: Option<Failure>,
 }
 
 impl Block {
-	pub fn new(status: BlockStatus, confidence: Option<f64>) -> Self {
-		Self { status, confidence }
+	pub fn new(
+		status: BlockStatus,
+		confidence: Option<f64>,
+		counts: Option<types::ConfidenceCounts>,
+		failure: Option<AvailabilityFailure>,
+	) -> Self {
+		Self {
+			status,
+			confidence,
+			dht_confidence: counts.map(|counts| calculate_confidence(counts.dht)),
+			rpc_confidence: counts.map(|counts| calculate_confidence(counts.rpc)),
+			failure: failure.map(From::from),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+pub struct Failure {
+	pub reason: FailureReason,
+	pub cells_requested: u32,
+	pub cells_fetched: u32,
+	pub cells_unverified: u32,
+}
+
+impl From<AvailabilityFailure> for Failure {
+	fn from(failure: AvailabilityFailure) -> Self {
+		Failure {
+			reason: failure.reason,
+			cells_requested: failure.cells_requested,
+			cells_fetched: failure.cells_fetched,
+			cells_unverified: failure.cells_unverified,
+		}
 	}
 }
 
@@ -314,6 +446,186 @@ impl Reply for Block {
 	}
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CellSource {
+	Dht,
+	Rpc,
+	Cache,
+	Unavailable,
+}
+
+impl From<types::CellSource> for CellSource {
+	fn from(source: types::CellSource) -> Self {
+		match source {
+			types::CellSource::Dht => CellSource::Dht,
+			types::CellSource::Rpc => CellSource::Rpc,
+			types::CellSource::Cache => CellSource::Cache,
+			types::CellSource::Unavailable => CellSource::Unavailable,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SampledCell {
+	pub row: u32,
+	pub col: u16,
+	pub source: CellSource,
+	pub verified: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Sampling {
+	pub block_number: u32,
+	pub cells: Vec<SampledCell>,
+}
+
+impl From<(u32, SamplingRecord)> for Sampling {
+	fn from((block_number, record): (u32, SamplingRecord)) -> Self {
+		let cells = record
+			.cells
+			.into_iter()
+			.map(|cell| SampledCell {
+				row: cell.row,
+				col: cell.col,
+				source: cell.source.into(),
+				verified: cell.verified,
+			})
+			.collect();
+		Sampling {
+			block_number,
+			cells,
+		}
+	}
+}
+
+impl Reply for Sampling {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DataProof {
+	pub block_number: u32,
+	pub transaction_index: u32,
+	pub data_root: H256,
+	pub proof: Vec<H256>,
+	pub number_of_leaves: u32,
+	pub leaf_index: u32,
+	pub leaf: H256,
+}
+
+impl From<(u32, u32, types::DataProof)> for DataProof {
+	fn from((block_number, transaction_index, proof): (u32, u32, types::DataProof)) -> Self {
+		DataProof {
+			block_number,
+			transaction_index,
+			data_root: proof.root,
+			proof: proof.proof,
+			number_of_leaves: proof.number_of_leaves,
+			leaf_index: proof.leaf_index,
+			leaf: proof.leaf,
+		}
+	}
+}
+
+impl Reply for DataProof {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Justification {
+	pub block_number: u32,
+	pub block_hash: H256,
+	pub round: u64,
+	pub set_id: u64,
+	/// Hex encoded SCALE encoded GRANDPA justification
+	pub justification: String,
+}
+
+impl From<(u32, FinalityJustification)> for Justification {
+	fn from((block_number, finality): (u32, FinalityJustification)) -> Self {
+		let justification = finality.justification;
+		Justification {
+			block_number,
+			block_hash: justification.commit.target_hash,
+			round: justification.round,
+			set_id: finality.set_id,
+			justification: format!("0x{}", hex::encode(justification.encode())),
+		}
+	}
+}
+
+impl Reply for Justification {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SignedPrecommit {
+	pub target_hash: H256,
+	pub target_number: u32,
+	/// Hex encoded ed25519 signature of the precommit
+	pub signature: String,
+}
+
+impl From<types::SignedPrecommit> for SignedPrecommit {
+	fn from(signed: types::SignedPrecommit) -> Self {
+		SignedPrecommit {
+			target_hash: signed.precommit.target_hash,
+			target_number: signed.precommit.target_number,
+			signature: format!("0x{}", hex::encode(signed.signature.0)),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Equivocation {
+	pub set_id: u64,
+	pub round: u64,
+	/// Hex encoded ed25519 public key of the validator which signed both precommits
+	pub offender: H256,
+	pub first: SignedPrecommit,
+	pub second: SignedPrecommit,
+}
+
+impl From<types::Equivocation> for Equivocation {
+	fn from(equivocation: types::Equivocation) -> Self {
+		Equivocation {
+			set_id: equivocation.set_id,
+			round: equivocation.round,
+			offender: H256(equivocation.offender.0),
+			first: equivocation.first.into(),
+			second: equivocation.second.into(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Equivocations {
+	pub block_number: u32,
+	pub equivocations: Vec<Equivocation>,
+}
+
+impl From<(u32, Vec<types::Equivocation>)> for Equivocations {
+	fn from((block_number, equivocations): (u32, Vec<types::Equivocation>)) -> Self {
+		Equivocations {
+			block_number,
+			equivocations: equivocations.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+impl Reply for Equivocations {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 impl TryFrom<avail_subxt::primitives::Header> for HeaderMessage {
 	type Error = anyhow::Error;
 
@@ -472,6 +784,72 @@ impl TryFrom<BlockVerified> for PublishMessage {
 	}
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainViolationMessage {
+	block_number: u32,
+	kind: ChainViolationKind,
+	expected_hash: H256,
+	received_hash: H256,
+}
+
+impl TryFrom<ChainViolation> for PublishMessage {
+	type Error = anyhow::Error;
+
+	fn try_from(violation: ChainViolation) -> Result<Self, Self::Error> {
+		Ok(PublishMessage::ChainViolation(ChainViolationMessage {
+			block_number: violation.block_number,
+			kind: violation.kind,
+			expected_hash: violation.expected_hash,
+			received_hash: violation.received_hash,
+		}))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EquivocationMessage {
+	block_number: u32,
+	#[serde(flatten)]
+	equivocation: Equivocation,
+}
+
+impl TryFrom<(u32, types::Equivocation)> for PublishMessage {
+	type Error = anyhow::Error;
+
+	fn try_from(
+		(block_number, equivocation): (u32, types::Equivocation),
+	) -> Result<Self, Self::Error> {
+		Ok(PublishMessage::Equivocation(EquivocationMessage {
+			block_number,
+			equivocation: equivocation.into(),
+		}))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AvailabilityFailedMessage {
+	block_number: u32,
+	reason: FailureReason,
+	cells_requested: u32,
+	cells_fetched: u32,
+	cells_unverified: u32,
+}
+
+impl TryFrom<(u32, AvailabilityFailure)> for PublishMessage {
+	type Error = anyhow::Error;
+
+	fn try_from((block_number, failure): (u32, AvailabilityFailure)) -> Result<Self, Self::Error> {
+		Ok(PublishMessage::AvailabilityFailed(
+			AvailabilityFailedMessage {
+				block_number,
+				reason: failure.reason,
+				cells_requested: failure.cells_requested,
+				cells_fetched: failure.cells_fetched,
+				cells_unverified: failure.cells_unverified,
+			},
+		))
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(try_from = "String")]
 pub struct FieldsQueryParameter(pub HashSet<DataField>);
@@ -492,6 +870,7 @@ impl TryFrom<String> for FieldsQueryParameter {
 #[derive(Serialize, Deserialize)]
 pub struct DataQuery {
 	pub fields: Option<FieldsQueryParameter>,
+	pub app_id: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -509,6 +888,7 @@ impl Reply for DataResponse {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DataMessage {
 	block_number: u32,
+	app_id: u32,
 	data_transactions: Vec<DataTransaction>,
 }
 
@@ -544,16 +924,19 @@ pub fn filter_fields(data_transactions: &mut [DataTransaction], fields: &HashSet
 	}
 }
 
-impl TryFrom<(u32, AppData)> for PublishMessage {
+impl TryFrom<(u32, u32, AppData)> for PublishMessage {
 	type Error = anyhow::Error;
 
-	fn try_from((block_number, app_data): (u32, AppData)) -> Result<Self, Self::Error> {
+	fn try_from(
+		(block_number, app_id, app_data): (u32, u32, AppData),
+	) -> Result<Self, Self::Error> {
 		let data_transactions = app_data
 			.into_iter()
 			.map(TryFrom::try_from)
 			.collect::<anyhow::Result<Vec<_>>>()?;
 		Ok(PublishMessage::DataVerified(DataMessage {
 			block_number,
+			app_id,
 			data_transactions,
 		}))
 	}
@@ -565,6 +948,9 @@ pub enum PublishMessage {
 	HeaderVerified(Box<HeaderMessage>),
 	ConfidenceAchieved(ConfidenceMessage),
 	DataVerified(DataMessage),
+	AvailabilityFailed(AvailabilityFailedMessage),
+	ChainViolation(ChainViolationMessage),
+	Equivocation(EquivocationMessage),
 }
 
 impl PublishMessage {
@@ -575,6 +961,9 @@ impl PublishMessage {
 			PublishMessage::DataVerified(data) => {
 				filter_fields(&mut data.data_transactions, fields)
 			},
+			PublishMessage::AvailabilityFailed(_) => (),
+			PublishMessage::ChainViolation(_) => (),
+			PublishMessage::Equivocation(_) => (),
 		}
 	}
 }
@@ -683,6 +1072,8 @@ pub enum Payload {
 	Version,
 	Status,
 	Submit(Transaction),
+	AddApp(AddApp),
+	RemoveApp(RemoveApp),
 }
 
 #[derive(Deserialize)]
@@ -769,6 +1160,13 @@ impl Error {
 		Self::new(Some(request_id), None, ErrorCode::BadRequest, message)
 	}
 
+	pub fn with_request_id(self, request_id: Uuid) -> Self {
+		Error {
+			request_id: Some(request_id),
+			..self
+		}
+	}
+
 	fn status(&self) -> StatusCode {
 		match self.error_code {
 			ErrorCode::NotFound => StatusCode::NOT_FOUND,
@@ -800,6 +1198,7 @@ pub enum WsResponse {
 	Version(Response<Version>),
 	Status(Response<Status>),
 	DataTransactionSubmitted(Response<SubmitResponse>),
+	Apps(Response<Apps>),
 }
 
 #[derive(Serialize, Deserialize, From)]
@@ -870,6 +1269,7 @@ mod tests {
 	fn data_verified() -> PublishMessage {
 		PublishMessage::DataVerified(DataMessage {
 			block_number: 1,
+			app_id: 1,
 			data_transactions: vec![DataTransaction {
 				data: transaction_data(),
 				extrinsic: transaction_data(),
@@ -965,6 +1365,23 @@ mod tests {
 		assert_ne!(block_status(&Some(9), &state, 9), unavailable);
 	}
 
+	#[test]
+	fn block_status_pruned() {
+		let mut state = State {
+			latest: 10,
+			confidence_pruned_below: Some(5),
+			..Default::default()
+		};
+		state.header_verified.set(1);
+		state.header_verified.set(9);
+		state.confidence_achieved.set(1);
+		state.confidence_achieved.set(9);
+		let unavailable = Some(BlockStatus::Unavailable);
+		assert_eq!(block_status(&Some(1), &state, 1), unavailable);
+		assert_eq!(block_status(&Some(1), &state, 4), unavailable);
+		assert_ne!(block_status(&Some(1), &state, 5), unavailable);
+	}
+
 	#[test]
 	fn block_status_pending() {
 		let state = State {
@@ -976,6 +1393,20 @@ mod tests {
 		assert_eq!(block_status(&Some(0), &state, 1), pending);
 		assert_eq!(block_status(&Some(0), &state, 4), pending);
 		assert_ne!(block_status(&Some(0), &state, 5), pending);
+
+		let mut state = State {
+			latest: 10,
+			..Default::default()
+		};
+		state.header_verified.set(1);
+		state.header_verified.set(9);
+		state.confidence_achieved.set(1);
+		state.confidence_achieved.set(9);
+		state.add_missed_block(5);
+		assert_eq!(block_status(&None, &state, 5), pending);
+		assert_ne!(block_status(&None, &state, 6), pending);
+		state.remove_missed_block(5);
+		assert_ne!(block_status(&None, &state, 5), pending);
 	}
 
 	#[test]
@@ -1088,4 +1519,24 @@ mod tests {
 		assert_eq!(block_status(&Some(1), &state, 5), finished);
 		assert_ne!(block_status(&Some(1), &state, 6), finished);
 	}
+
+	#[test]
+	fn block_status_failed() {
+		let mut state = State::default();
+		let failed = Some(BlockStatus::Failed);
+		state.latest = 10;
+		state.header_verified.set(1);
+		state.header_verified.set(5);
+		state.confidence_achieved.set(1);
+		state.confidence_achieved.set(3);
+		state.availability_failed.insert(4);
+		assert_eq!(block_status(&None, &state, 4), failed);
+		assert_ne!(block_status(&None, &state, 3), failed);
+		assert_ne!(block_status(&None, &state, 5), failed);
+		state.confidence_pruned_below = Some(5);
+		assert_eq!(
+			block_status(&None, &state, 4),
+			Some(BlockStatus::Unavailable)
+		);
+	}
 }
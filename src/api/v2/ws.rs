@@ -1,13 +1,14 @@
 use super::{
-	transactions,
+	handlers, transactions,
 	types::{
-		Payload, Request, Response, Status, Transaction, Version, WsClients, WsError, WsResponse,
+		Payload, RemoveApp, Request, Response, Status, Transaction, Version, WsClients, WsError,
+		WsResponse,
 	},
 };
 use crate::{
 	api::v2::types::{Error, Sender},
 	rpc::Node,
-	types::{RuntimeConfig, State},
+	types::{AppBackfill, RuntimeConfig, State},
 };
 use anyhow::Context;
 use futures::{FutureExt, StreamExt};
@@ -28,6 +29,7 @@ pub async fn connect(
 	node: Node,
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync + 'static>>,
 	state: Arc<Mutex<State>>,
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
 ) {
 	let (web_socket_sender, mut web_socket_receiver) = web_socket.split();
 	let (sender, receiver) = mpsc::unbounded_channel();
@@ -66,17 +68,27 @@ pub async fn connect(
 
 		let submitter = submitter.clone();
 		let state = state.clone();
+		let backfill_sender = backfill_sender.clone();
 
-		let send_result =
-			match handle_request(message, &version, &config, &node, submitter, state).await {
-				Ok(response) => send(sender.clone(), response),
-				Err(error) => {
-					if let Some(cause) = error.cause.as_ref() {
-						error!("Failed to handle request: {cause:#}");
-					};
-					send::<WsError>(sender.clone(), error.into())
-				},
-			};
+		let send_result = match handle_request(
+			message,
+			&version,
+			&config,
+			&node,
+			submitter,
+			state,
+			backfill_sender,
+		)
+		.await
+		{
+			Ok(response) => send(sender.clone(), response),
+			Err(error) => {
+				if let Some(cause) = error.cause.as_ref() {
+					error!("Failed to handle request: {cause:#}");
+				};
+				send::<WsError>(sender.clone(), error.into())
+			},
+		};
 
 		if let Err(error) = send_result {
 			warn!("Error sending message: {error:#}");
@@ -91,6 +103,7 @@ async fn handle_request(
 	node: &Node,
 	submitter: Option<Arc<impl transactions::Submit>>,
 	state: Arc<Mutex<State>>,
+	backfill_sender: Option<mpsc::Sender<AppBackfill>>,
 ) -> Result<WsResponse, Error> {
 	let request = Request::try_from(message).map_err(|error| {
 		Error::bad_request_unknown(&format!("Failed to parse request: {error}"))
@@ -121,5 +134,22 @@ async fn handle_request(
 				.map(|response| Response::new(request_id, response).into())
 				.map_err(Error::internal_server_error)
 		},
+		Payload::AddApp(request) => {
+			let Some(backfill_sender) = backfill_sender else {
+				return Err(Error::bad_request(request_id, "App client is not enabled."));
+			};
+			handlers::add_app(backfill_sender, state, request)
+				.await
+				.map(|apps| Response::new(request_id, apps).into())
+				.map_err(|error| error.with_request_id(request_id))
+		},
+		Payload::RemoveApp(RemoveApp { app_id }) => {
+			if backfill_sender.is_none() {
+				return Err(Error::bad_request(request_id, "App client is not enabled."));
+			};
+			handlers::remove_app(app_id, state)
+				.map(|apps| Response::new(request_id, apps).into())
+				.map_err(|error| error.with_request_id(request_id))
+		},
 	}
 }
@@ -1,12 +1,21 @@
 //! Application client for data fetching and reconstruction.
 //!
-//! App client is enabled when app_id is configured and greater than 0 in avail-light configuration. [`Light client`](super::light_client) triggers application client if block is verified with high enough confidence. Currently [`run`] function is separate task and doesn't block main thread.
+//! App client is enabled when app_id or app_ids are configured with IDs greater than 0, or when full block reconstruction is enabled in avail-light configuration. [`Light client`](super::light_client) triggers application client if block is verified with high enough confidence. Currently [`run`] function is separate task and doesn't block main thread.
 //!
 //! # Flow
 //!
-//! Get app data rows from node
+//! Get union of tracked apps data rows from node
 //! Verify commitment equality for each row
-//! Decode app data and store it into local database under the `app_id:block_number` key
+//! Decode data of each app and store it into local database under the block number and app ID key,
+//! in a single write batch together with the verified rows to cache, separate from the block header and confidence
+//! Update the app data state once the write batch is committed
+//!
+//! # Full block reconstruction
+//!
+//! Fetch all cells of the extended matrix from DHT and verify them
+//! Reconstruct columns with missing cells, failing if there are not enough cells
+//! Verify commitment equality for rows of all apps in the block
+//! Decode data of all apps and store it into local database
 //!
 //! # Notes
 //!
@@ -14,9 +23,8 @@
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use avail_core::AppId;
+use avail_core::{AppId, DataLookup};
 use avail_subxt::{avail, utils::H256};
-use codec::Encode;
 use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
 use kate_recovery::{
 	com::{
@@ -26,24 +34,32 @@ use kate_recovery::{
 	commitments,
 	config::{self, CHUNK_SIZE},
 	data::{Cell, DataCell},
-	matrix::{Dimensions, Position},
+	matrix::{Dimensions, Partition, Position},
 };
 use mockall::automock;
 use rand::SeedableRng as _;
 use rand_chacha::ChaChaRng;
-use rocksdb::DB;
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeSet, HashMap, HashSet},
 	sync::{Arc, Mutex},
 };
-use tokio::sync::{broadcast, mpsc::Sender};
-use tracing::{debug, error, info, instrument};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
-	data::store_encoded_data_in_db,
+	data::{BlockBatch, Database},
 	network::Client,
 	proof, rpc,
-	types::{AppClientConfig, BlockVerified, OptionBlockRange, State},
+	types::{
+		AppBackfill, AppClientConfig, BlockRange, BlockVerified, OptionBlockRange, RetryConfig,
+		State,
+	},
+	utils::{can_reconstruct, extract_app_ids, retry},
+};
+
+const ENTIRE_BLOCK: Partition = Partition {
+	number: 1,
+	fraction: 1,
 };
 
 #[async_trait]
@@ -58,6 +74,15 @@ trait AppClient {
 		missing_rows: &[u32],
 	) -> Result<Vec<(u32, Vec<u8>)>>;
 
+	async fn reconstruct_block_from_dht(
+		&self,
+		pp: Arc<PublicParameters>,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		rows: &[u32],
+	) -> Result<Vec<Option<Vec<u8>>>>;
+
 	async fn fetch_rows_from_dht(
 		&self,
 		block_number: u32,
@@ -68,23 +93,19 @@ trait AppClient {
 	async fn get_kate_rows(&self, rows: Vec<u32>, block_hash: H256)
 		-> Result<Vec<Option<Vec<u8>>>>;
 
-	fn store_encoded_data_in_db<T: Encode + 'static>(
-		&self,
-		app_id: AppId,
-		block_number: u32,
-		data: &T,
-	) -> Result<()>;
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()>;
 }
 
 #[derive(Clone)]
-struct AppClientImpl {
-	db: Arc<DB>,
+struct AppClientImpl<T: Database> {
+	db: T,
 	network_client: Client,
 	rpc_client: avail::Client,
+	retry: RetryConfig,
 }
 
 #[async_trait]
-impl AppClient for AppClientImpl {
+impl<T: Database> AppClient for AppClientImpl<T> {
 	async fn reconstruct_rows_from_dht(
 		&self,
 		pp: Arc<PublicParameters>,
@@ -180,15 +201,135 @@ impl AppClient for AppClientImpl {
 			.collect::<Result<Vec<_>>>()
 	}
 
+	async fn reconstruct_block_from_dht(
+		&self,
+		pp: Arc<PublicParameters>,
+		block_number: u32,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		rows: &[u32],
+	) -> Result<Vec<Option<Vec<u8>>>> {
+		let positions = dimensions
+			.iter_extended_partition_positions(&ENTIRE_BLOCK)
+			.collect::<Vec<_>>();
+
+		debug!(
+			block_number,
+			"Fetching {} block cells from DHT",
+			positions.len()
+		);
+		let (fetched, unfetched) = fetch_verified(
+			pp,
+			&self.network_client,
+			block_number,
+			dimensions,
+			commitments,
+			&positions,
+		)
+		.await?;
+		debug!(
+			block_number,
+			"Fetched {} block cells, {} block cells is missing",
+			fetched.len(),
+			unfetched.len()
+		);
+
+		let columns = unfetched
+			.iter()
+			.map(|position| position.col)
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.collect::<Vec<_>>();
+
+		if !can_reconstruct(dimensions, &columns, &fetched) {
+			return Err(anyhow!(
+				"Block {block_number} is not recoverable, not enough cells to reconstruct columns {columns:?}"
+			));
+		}
+
+		let column_cells = fetched
+			.iter()
+			.filter(|cell| columns.contains(&cell.position.col))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		let reconstructed = reconstruct_columns(dimensions, &column_cells)?;
+
+		debug!(
+			block_number,
+			"Reconstructed {} columns: {:?}",
+			reconstructed.keys().len(),
+			reconstructed.keys()
+		);
+
+		let mut data_cells = fetched
+			.into_iter()
+			.filter(|cell| rows.contains(&cell.position.row))
+			.map(Into::into)
+			.collect::<Vec<DataCell>>();
+
+		let mut reconstructed_cells = unfetched
+			.into_iter()
+			.filter(|position| rows.contains(&position.row))
+			.map(|position| data_cell(position, &reconstructed))
+			.collect::<Result<Vec<_>>>()?;
+
+		data_cells.append(&mut reconstructed_cells);
+
+		data_cells.sort_by(|a, b| {
+			(a.position.row, a.position.col).cmp(&(b.position.row, b.position.col))
+		});
+
+		(0..dimensions.extended_rows())
+			.map(|row| {
+				if !rows.contains(&row) {
+					return Ok(None);
+				}
+
+				let data = data_cells
+					.iter()
+					.filter(|&cell| cell.position.row == row)
+					.flat_map(|cell| cell.data)
+					.collect::<Vec<_>>();
+
+				if data.len() != dimensions.width() * config::CHUNK_SIZE {
+					return Err(anyhow!("Row size is not valid after reconstruction"));
+				}
+
+				Ok(Some(data))
+			})
+			.collect::<Result<Vec<_>>>()
+	}
+
 	async fn fetch_rows_from_dht(
 		&self,
 		block_number: u32,
 		dimensions: Dimensions,
 		row_indexes: &[u32],
 	) -> Vec<Option<Vec<u8>>> {
-		self.network_client
-			.fetch_rows_from_dht(block_number, dimensions, row_indexes)
-			.await
+		let cached = self
+			.db
+			.get_cached_rows(block_number, row_indexes)
+			.unwrap_or_else(|error| {
+				warn!(block_number, "Cannot get cached rows: {error:#}");
+				vec![None; row_indexes.len()]
+			});
+		let uncached = row_indexes
+			.iter()
+			.zip(cached.iter())
+			.filter(|(_, row)| row.is_none())
+			.map(|(&row_index, _)| row_index)
+			.collect::<Vec<_>>();
+		let mut rows = self
+			.network_client
+			.fetch_rows_from_dht(block_number, dimensions, &uncached)
+			.await;
+		for (&row_index, row) in row_indexes.iter().zip(cached.into_iter()) {
+			if row.is_some() {
+				rows[row_index as usize] = row;
+			}
+		}
+		rows
 	}
 
 	async fn get_kate_rows(
@@ -196,17 +337,16 @@ impl AppClient for AppClientImpl {
 		rows: Vec<u32>,
 		block_hash: H256,
 	) -> Result<Vec<Option<Vec<u8>>>> {
-		rpc::get_kate_rows(&self.rpc_client, rows, block_hash).await
+		retry(&self.retry, rpc::is_transient, || {
+			rpc::get_kate_rows(&self.rpc_client, rows.clone(), block_hash)
+		})
+		.await
 	}
 
-	fn store_encoded_data_in_db<T: Encode + 'static>(
-		&self,
-		app_id: AppId,
-		block_number: u32,
-		data: &T,
-	) -> Result<()> {
-		store_encoded_data_in_db(self.db.clone(), app_id, block_number, &data)
-			.context("Failed to store data into database")
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()> {
+		self.db
+			.store_block(block_number, block)
+			.context("Failed to store block into database")
 	}
 }
 
@@ -275,21 +415,50 @@ async fn fetch_verified(
 	Ok((fetched, unfetched))
 }
 
+/// Verifies rows against commitments for each application, returning union of verified and missing rows
+fn verify_app_rows(
+	pp: &PublicParameters,
+	commitments: &[[u8; config::COMMITMENT_SIZE]],
+	rows: &[Option<Vec<u8>>],
+	lookup: &DataLookup,
+	dimensions: Dimensions,
+	app_ids: &[AppId],
+) -> Result<(Vec<u32>, Vec<u32>)> {
+	let mut verified_rows = BTreeSet::new();
+	let mut missing_rows = BTreeSet::new();
+	for &app_id in app_ids {
+		let (verified, missing) =
+			commitments::verify_equality(pp, commitments, rows, lookup, dimensions, app_id)?;
+		verified_rows.extend(verified);
+		missing_rows.extend(missing);
+	}
+	missing_rows.retain(|row| !verified_rows.contains(row));
+	Ok((
+		verified_rows.into_iter().collect(),
+		missing_rows.into_iter().collect(),
+	))
+}
+
 #[instrument(skip_all, fields(block = block.block_num), level = "trace")]
 async fn process_block(
 	app_client: impl AppClient,
 	cfg: &AppClientConfig,
-	app_id: AppId,
+	app_ids: &[AppId],
 	block: &BlockVerified,
 	pp: Arc<PublicParameters>,
-) -> Result<AppData> {
+) -> Result<Vec<(AppId, AppData)>> {
 	let lookup = &block.lookup;
 	let block_number = block.block_num;
 	let dimensions = block.dimensions;
 
 	let commitments = &block.commitments;
 
-	let app_rows = app_specific_rows(lookup, dimensions, app_id);
+	let app_rows = app_ids
+		.iter()
+		.flat_map(|&app_id| app_specific_rows(lookup, dimensions, app_id))
+		.collect::<BTreeSet<u32>>()
+		.into_iter()
+		.collect::<Vec<_>>();
 
 	debug!(
 		block_number,
@@ -305,7 +474,7 @@ async fn process_block(
 	debug!(block_number, "Fetched {dht_rows_count} app rows from DHT");
 
 	let (dht_verified_rows, dht_missing_rows) =
-		commitments::verify_equality(&pp, commitments, &dht_rows, lookup, dimensions, app_id)?;
+		verify_app_rows(&pp, commitments, &dht_rows, lookup, dimensions, app_ids)?;
 	debug!(
 		block_number,
 		"Verified {} app rows from DHT, missing {}",
@@ -326,7 +495,7 @@ async fn process_block(
 	};
 
 	let (rpc_verified_rows, mut missing_rows) =
-		commitments::verify_equality(&pp, commitments, &rpc_rows, lookup, dimensions, app_id)?;
+		verify_app_rows(&pp, commitments, &rpc_rows, lookup, dimensions, app_ids)?;
 	// Since verify_equality returns all missing rows, exclude DHT rows that are already verified
 	missing_rows.retain(|row| !dht_verified_rows.contains(row));
 
@@ -385,21 +554,203 @@ async fn process_block(
 		rows[i] = Some(row);
 	}
 
+	let mut batch = BlockBatch::default();
+	if cfg.cache {
+		batch.rows = verified_rows(&rows);
+	}
+
+	decode_and_store(&app_client, batch, block, rows, app_ids)
+}
+
+/// Reconstructs entire block from DHT and decodes data of all applications in the block.
+/// Fails if the block cannot be reconstructed, or if rows are not matching commitments.
+#[instrument(skip_all, fields(block = block.block_num), level = "trace")]
+async fn process_full_block(
+	app_client: impl AppClient,
+	cfg: &AppClientConfig,
+	block: &BlockVerified,
+	pp: Arc<PublicParameters>,
+) -> Result<Vec<(AppId, AppData)>> {
+	let lookup = &block.lookup;
+	let block_number = block.block_num;
+	let dimensions = block.dimensions;
+	let commitments = &block.commitments;
+
+	// Data of all applications is decoded, including the system application 0
+	let app_ids = extract_app_ids(lookup)?;
+
+	let app_rows = app_ids
+		.iter()
+		.flat_map(|&app_id| app_specific_rows(lookup, dimensions, app_id))
+		.collect::<BTreeSet<u32>>()
+		.into_iter()
+		.collect::<Vec<_>>();
+
+	debug!(
+		block_number,
+		"Reconstructing block from DHT, with {} app rows",
+		app_rows.len()
+	);
+
+	let rows = app_client
+		.reconstruct_block_from_dht(pp.clone(), block_number, dimensions, commitments, &app_rows)
+		.await?;
+
+	let (verified_rows, missing_rows) =
+		verify_app_rows(&pp, commitments, &rows, lookup, dimensions, &app_ids)?;
+
+	if !missing_rows.is_empty() {
+		return Err(anyhow!(
+			"Block {block_number} is not recoverable, rows {missing_rows:?} are not matching commitments"
+		));
+	}
+
+	debug!(
+		block_number,
+		"Verified {} reconstructed app rows",
+		verified_rows.len()
+	);
+
+	let mut batch = BlockBatch::default();
+	if cfg.cache {
+		batch.rows = verified_rows(&rows);
+	}
+
+	decode_and_store(&app_client, batch, block, rows, &app_ids)
+}
+
+/// Returns verified rows to cache, with their row indexes
+fn verified_rows(rows: &[Option<Vec<u8>>]) -> Vec<(u32, Vec<u8>)> {
+	rows.iter()
+		.zip(0..)
+		.filter_map(|(row, row_index)| Some((row_index, row.clone()?)))
+		.collect()
+}
+
+/// Decodes data of given applications from rows and stores it into database,
+/// in a single write batch together with the given rows to cache
+fn decode_and_store(
+	app_client: &impl AppClient,
+	mut batch: BlockBatch,
+	block: &BlockVerified,
+	rows: Vec<Option<Vec<u8>>>,
+	app_ids: &[AppId],
+) -> Result<Vec<(AppId, AppData)>> {
+	let block_number = block.block_num;
+	let (lookup, dimensions) = (&block.lookup, block.dimensions);
 	let data_cells =
 		data_cells_from_rows(rows).context("Failed to create data cells from rows got from RPC")?;
 
-	let data = decode_app_extrinsics(lookup, dimensions, data_cells, app_id)
-		.context("Failed to decode app extrinsics")?;
+	let apps_data = app_ids
+		.iter()
+		.map(|&app_id| {
+			decode_app_extrinsics(lookup, dimensions, data_cells.clone(), app_id)
+				.map(|data| (app_id, data))
+				.with_context(|| format!("Failed to decode app {app_id} extrinsics"))
+		})
+		.collect::<Result<Vec<_>>>()?;
 
 	debug!(block_number, "Storing data into database");
+	batch.app_data = apps_data
+		.iter()
+		.map(|(app_id, data)| (app_id.0, data.clone()))
+		.collect::<Vec<_>>();
 	app_client
-		.store_encoded_data_in_db(app_id, block_number, &data)
+		.store_block_in_db(block_number, batch)
 		.context("Failed to store data into database")?;
 
-	let bytes_count = data.iter().fold(0usize, |acc, x| acc + x.len());
+	let bytes_count = apps_data
+		.iter()
+		.flat_map(|(_, data)| data.iter())
+		.fold(0usize, |acc, x| acc + x.len());
 	debug!(block_number, "Stored {bytes_count} bytes into database");
 
-	Ok(data)
+	Ok(apps_data)
+}
+
+fn tracked_app_ids(state: &Arc<Mutex<State>>) -> Vec<AppId> {
+	let state = state.lock().expect("State lock can be acquired");
+	state.app_ids.iter().copied().map(AppId).collect()
+}
+
+/// Fetches and verifies application data for the range of blocks with achieved confidence.
+/// Backfill stops on the first block without stored confidence or header, on the first block
+/// which failed data availability verification, or if application is not tracked anymore.
+async fn backfill<T: Database>(
+	app_client: AppClientImpl<T>,
+	cfg: AppClientConfig,
+	request: AppBackfill,
+	pp: Arc<PublicParameters>,
+	state: Arc<Mutex<State>>,
+	data_verified_sender: broadcast::Sender<(u32, u32, AppData)>,
+) -> Result<()> {
+	let AppBackfill {
+		app_id,
+		first,
+		last,
+	} = request;
+	info!(app_id, "Backfilling app data for blocks {first}..={last}");
+
+	for block_number in first..=last {
+		if !tracked_app_ids(&state).contains(&AppId(app_id)) {
+			info!(app_id, "App is not tracked anymore, stopping backfill");
+			return Ok(());
+		}
+
+		let is_failed = {
+			let state = state.lock().expect("State lock can be acquired");
+			state.availability_failed.contains(&block_number)
+		};
+		let confidence = app_client
+			.db
+			.get_confidence(block_number)
+			.context("Failed to get confidence from database")?;
+		if is_failed || confidence.is_none() {
+			warn!(
+				app_id,
+				block_number, "Block confidence is not achieved, stopping backfill"
+			);
+			return Ok(());
+		}
+
+		let Some(header) = app_client
+			.db
+			.get_header(block_number)
+			.context("Failed to get header from database")?
+		else {
+			warn!(
+				app_id,
+				block_number, "Block header is not stored, stopping backfill"
+			);
+			return Ok(());
+		};
+
+		let block = BlockVerified::try_from((header, None))?;
+		if block.lookup.range_of(AppId(app_id)).is_some() {
+			let apps_data = process_block(
+				app_client.clone(),
+				&cfg,
+				&[AppId(app_id)],
+				&block,
+				pp.clone(),
+			)
+			.await?;
+			for (app_id, data) in apps_data {
+				data_verified_sender
+					.send((block_number, app_id.0, data))
+					.context("Cannot send data verified message")?;
+			}
+		}
+
+		let mut state = state.lock().expect("State lock can be acquired");
+		if state.app_ids.contains(&app_id) {
+			let app_state = state.apps.entry(app_id).or_default();
+			app_state.backfilled.set(block_number);
+		}
+	}
+
+	info!(app_id, "Backfilled app data for blocks {first}..={last}");
+	Ok(())
 }
 
 /// Runs application client.
@@ -410,31 +761,60 @@ async fn process_block(
 /// * `db` - Database to store data inot DB
 /// * `network_client` - Reference to a libp2p custom network client
 /// * `rpc_client` - Node's RPC subxt client for fetching data unavailable in DHT (if configured)
-/// * `app_id` - Application ID
 /// * `block_receive` - Channel used to receive header of verified block
+/// * `backfill_receive` - Channel used to receive requests for backfilling application data
 /// * `pp` - Public parameters (i.e. SRS) needed for proof verification
+/// * `state` - Processed blocks state, along with IDs of tracked applications
 #[allow(clippy::too_many_arguments)]
 pub async fn run(
 	cfg: AppClientConfig,
-	db: Arc<DB>,
+	db: impl Database,
 	network_client: Client,
 	rpc_client: avail::Client,
-	app_id: AppId,
 	mut block_receive: broadcast::Receiver<BlockVerified>,
+	mut backfill_receive: mpsc::Receiver<AppBackfill>,
 	pp: Arc<PublicParameters>,
 	state: Arc<Mutex<State>>,
 	sync_end_block: u32,
-	data_verified_sender: broadcast::Sender<(u32, AppData)>,
-	error_sender: Sender<anyhow::Error>,
+	data_verified_sender: broadcast::Sender<(u32, u32, AppData)>,
+	error_sender: mpsc::Sender<anyhow::Error>,
 ) {
-	info!("Starting for app {app_id}...");
+	info!("Starting for apps {:?}...", tracked_app_ids(&state));
 
-	fn set_data_verified_state(state: Arc<Mutex<State>>, sync_end_block: u32, block_number: u32) {
+	/// Sets the last block of the range, unless the range already spans the block
+	fn extend(range: &mut Option<BlockRange>, block_number: u32) {
+		if range.last().map_or(true, |last| last < block_number) {
+			range.set(block_number);
+		}
+	}
+
+	/// Updates the app data state, once app data is committed
+	fn set_data_verified_state(
+		state: Arc<Mutex<State>>,
+		sync_end_block: u32,
+		block_number: u32,
+		app_ids: &[AppId],
+		full_block_reconstruction: bool,
+	) {
 		let mut state = state.lock().expect("State lock can be acquired");
-		let first = state.confidence_achieved.first();
-		match first.map(|first| block_number < first) {
-			Some(true) => state.sync_data_verified.set(block_number),
-			Some(false) | None => state.data_verified.set(block_number),
+		// Blocks up to the sync end block are synced by the sync client
+		let is_sync = block_number <= sync_end_block;
+		if is_sync {
+			extend(&mut state.sync_data_verified, block_number);
+		} else {
+			extend(&mut state.data_verified, block_number);
+		}
+		for app_id in app_ids {
+			// Skip apps removed while block was processed, unless data of all apps is decoded
+			if !full_block_reconstruction && !state.app_ids.contains(&app_id.0) {
+				continue;
+			}
+			let app_state = state.apps.entry(app_id.0).or_default();
+			if is_sync {
+				extend(&mut app_state.sync_data_verified, block_number);
+			} else {
+				extend(&mut app_state.data_verified, block_number);
+			}
 		}
 		if sync_end_block == block_number {
 			state.synced.replace(true);
@@ -442,14 +822,39 @@ pub async fn run(
 	}
 
 	loop {
-		let block = match block_receive.recv().await {
-			Ok(block) => block,
-			Err(error) => {
-				error!("Cannot receive message: {error}");
-				if let Err(error) = error_sender.send(error.into()).await {
-					error!("Cannot send error message: {error}");
-				}
-				return;
+		let block = tokio::select! {
+			result = block_receive.recv() => match result {
+				Ok(block) => block,
+				Err(error) => {
+					error!("Cannot receive message: {error}");
+					if let Err(error) = error_sender.send(error.into()).await {
+						error!("Cannot send error message: {error}");
+					}
+					return;
+				},
+			},
+			Some(request) = backfill_receive.recv() => {
+				let app_id = request.app_id;
+				let app_client = AppClientImpl {
+					db: db.clone(),
+					network_client: network_client.clone(),
+					rpc_client: rpc_client.clone(),
+					retry: cfg.retry,
+				};
+				let backfill = backfill(
+					app_client,
+					cfg.clone(),
+					request,
+					pp.clone(),
+					state.clone(),
+					data_verified_sender.clone(),
+				);
+				tokio::task::spawn(async move {
+					if let Err(error) = backfill.await {
+						error!(app_id, "Cannot backfill app data: {error:#}");
+					}
+				});
+				continue;
 			},
 		};
 
@@ -458,38 +863,68 @@ pub async fn run(
 
 		info!(block_number, "Block available: {dimensions:?}");
 
-		if block.lookup.range_of(app_id).is_none() {
+		// Apps added while block is processed are tracked from the next block
+		let app_ids = tracked_app_ids(&state);
+		let block_app_ids = app_ids
+			.iter()
+			.copied()
+			.filter(|&app_id| block.lookup.range_of(app_id).is_some())
+			.collect::<Vec<_>>();
+
+		if block_app_ids.is_empty() && !cfg.full_block_reconstruction {
 			info!(
 				block_number,
-				"Skipping block with no cells for app {app_id}"
+				"Skipping block with no cells for apps {app_ids:?}"
 			);
-			set_data_verified_state(state.clone(), sync_end_block, block_number);
+			set_data_verified_state(state.clone(), sync_end_block, block_number, &app_ids, false);
 			continue;
 		}
 
-		let db_clone = db.clone();
 		let app_client = AppClientImpl {
-			db: db_clone,
+			db: db.clone(),
 			network_client: network_client.clone(),
 			rpc_client: rpc_client.clone(),
+			retry: cfg.retry,
+		};
+		let result = if cfg.full_block_reconstruction {
+			process_full_block(app_client, &cfg, &block, pp.clone()).await
+		} else {
+			process_block(app_client, &cfg, &block_app_ids, &block, pp.clone()).await
 		};
-		let data = match process_block(app_client, &cfg, app_id, &block, pp.clone()).await {
-			Ok(data) => data,
+		let apps_data = match result {
+			Ok(apps_data) => apps_data,
 			Err(error) => {
-				error!(block_number, "Cannot process block: {error}");
+				error!(block_number, "Cannot process block: {error:#}");
 				if let Err(error) = error_sender.send(error).await {
 					error!("Cannot send error message: {error}");
 				}
 				return;
 			},
 		};
-		set_data_verified_state(state.clone(), sync_end_block, block_number);
-		if let Err(error) = data_verified_sender.send((block_number, data)) {
-			error!("Cannot send data verified message: {error}");
-			if let Err(error) = error_sender.send(error.into()).await {
-				error!("Cannot send error message: {error}");
+		// In full block reconstruction mode, data of all decoded apps is verified
+		let verified_app_ids = if cfg.full_block_reconstruction {
+			let decoded_app_ids = apps_data.iter().map(|(app_id, _)| app_id.0);
+			let verified_app_ids = app_ids.iter().map(|app_id| app_id.0).chain(decoded_app_ids);
+			let verified_app_ids = verified_app_ids.collect::<BTreeSet<_>>();
+			verified_app_ids.into_iter().map(AppId).collect()
+		} else {
+			app_ids
+		};
+		set_data_verified_state(
+			state.clone(),
+			sync_end_block,
+			block_number,
+			&verified_app_ids,
+			cfg.full_block_reconstruction,
+		);
+		for (app_id, data) in apps_data {
+			if let Err(error) = data_verified_sender.send((block_number, app_id.0, data)) {
+				error!("Cannot send data verified message: {error}");
+				if let Err(error) = error_sender.send(error.into()).await {
+					error!("Cannot send error message: {error}");
+				}
+				return;
 			}
-			return;
 		}
 		debug!(block_number, "Block processed");
 	}
@@ -499,7 +934,6 @@ pub async fn run(
 mod tests {
 	use super::*;
 	use crate::types::{AppClientConfig, RuntimeConfig};
-	use avail_core::DataLookup;
 	use hex_literal::hex;
 	use kate_recovery::{matrix::Dimensions, testnet};
 
@@ -552,9 +986,10 @@ mod tests {
 			.expect_reconstruct_rows_from_dht()
 			.returning(|_, _, _, _, _| Box::pin(async move { Ok(vec![]) }));
 		mock_client
-			.expect_store_encoded_data_in_db()
-			.returning(|_, _, _: &Vec<Vec<u8>>| Ok(()));
-		process_block(mock_client, &cfg, AppId(1), &block, pp)
+			.expect_store_block_in_db()
+			.withf(|_, block| block.app_data.len() == 1 && block.app_data[0].0 == 1)
+			.returning(|_, _| Ok(()));
+		process_block(mock_client, &cfg, &[AppId(1)], &block, pp)
 			.await
 			.unwrap();
 	}
@@ -614,10 +1049,86 @@ mod tests {
 			.expect_reconstruct_rows_from_dht()
 			.returning(|_, _, _, _, _| Box::pin(async move { Ok(vec![]) }));
 		mock_client
-			.expect_store_encoded_data_in_db()
-			.returning(|_, _, _: &Vec<Vec<u8>>| Ok(()));
-		process_block(mock_client, &cfg, AppId(1), &block, pp)
+			.expect_store_block_in_db()
+			.withf(|_, block| block.app_data.len() == 1 && block.app_data[0].0 == 1)
+			.returning(|_, _| Ok(()));
+		process_block(mock_client, &cfg, &[AppId(1)], &block, pp)
+			.await
+			.unwrap();
+	}
+
+	fn full_block() -> BlockVerified {
+		let id_lens: Vec<(u32, usize)> = vec![(0, 1), (1, 11)];
+		let lookup = DataLookup::from_id_and_len_iter(id_lens.into_iter()).unwrap();
+		BlockVerified {
+			header_hash: hex!("5bc959e1d05c68f7e1b5bc3a83cfba4efe636ce7f86102c30bcd6a2794e75afe")
+				.into(),
+			block_num: 288,
+			dimensions: Dimensions::new(1, 16).unwrap(),
+			lookup,
+			commitments: [
+				[
+					165, 227, 207, 130, 59, 77, 78, 242, 184, 232, 114, 218, 145, 167, 149, 53, 89,
+					7, 230, 49, 85, 113, 218, 116, 43, 195, 144, 203, 149, 114, 106, 89, 73, 164,
+					17, 163, 3, 145, 173, 6, 119, 222, 17, 60, 251, 215, 40, 192,
+				],
+				[
+					165, 227, 207, 130, 59, 77, 78, 242, 184, 232, 114, 218, 145, 167, 149, 53, 89,
+					7, 230, 49, 85, 113, 218, 116, 43, 195, 144, 203, 149, 114, 106, 89, 73, 164,
+					17, 163, 3, 145, 173, 6, 119, 222, 17, 60, 251, 215, 40, 192,
+				],
+			]
+			.to_vec(),
+			confidence: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn test_process_full_block() {
+		let mut cfg = AppClientConfig::from(&RuntimeConfig::default());
+		cfg.full_block_reconstruction = true;
+		let pp = Arc::new(testnet::public_params(1024));
+		let mut mock_client = MockAppClient::new();
+		let rows: Vec<Option<Vec<u8>>> = [
+			Some(hex!("042c280403000ba3fa0ab887018000000000000000000000000000000000000004d904d1048400d43593c715fdd31c61141abd04a99fd6822c8558854ccde3009a5684e7a56da27d01a8cf58e1e9c735f93ebc7a94086aa27cfd77db173aac00803895886b8a4f49e85c68f469d570f0ed992750bf95329bb90ef56b45abcd009fedef0d9cbdd61c05a181d4013800041d0121033036343265356430346236003632353966363635666431353361613136646637343066323533373237386600613139316565393630343862663839393733343961303137353865346237610032643539663534353338393865626231643233626634353965363637613633003462313663663432326663393335336434623862623630386235393230653400353733663335663037303764333238616661343832316663656631363439660039643532653762353732356533303935643865656561356436633235333830006434658000000000000000000000000000000000000000000000000000000000346080be83f48ad1748c4ad339abdcb803368efdd1f65689619ff8c208755d0084eefcf837b61c479b3332059bc8e89b490a9d502baecaed448433d4e161710000a71cbb1a0387598e509d9fcab511022f437b0caf13591315c3f1bbf04f18009d83f014806210da6ee1d2f80cf0f9c08f1d132be042769015f6174fd2b24c00").to_vec()),
+			None,
+		]
+		.to_vec();
+		mock_client
+			.expect_reconstruct_block_from_dht()
+			.withf(|_, block_number, _, _, rows| *block_number == 288 && rows.to_vec() == vec![0])
+			.returning(move |_, _, _, _, _| {
+				let rows = rows.clone();
+				Box::pin(async move { Ok(rows) })
+			});
+		mock_client.expect_fetch_rows_from_dht().never();
+		mock_client.expect_get_kate_rows().never();
+		mock_client
+			.expect_store_block_in_db()
+			.withf(|block_number, block| {
+				let app_ids = block.app_data.iter().map(|(app_id, _)| *app_id);
+				*block_number == 288 && app_ids.eq([0, 1])
+			})
+			.times(1)
+			.returning(|_, _| Ok(()));
+		let apps_data = process_full_block(mock_client, &cfg, &full_block(), pp)
 			.await
 			.unwrap();
+		let app_ids = apps_data.iter().map(|(app_id, _)| *app_id);
+		assert!(app_ids.eq([AppId(0), AppId(1)]));
+	}
+
+	#[tokio::test]
+	async fn test_process_full_block_not_recoverable() {
+		let mut cfg = AppClientConfig::from(&RuntimeConfig::default());
+		cfg.full_block_reconstruction = true;
+		let pp = Arc::new(testnet::public_params(1024));
+		let mut mock_client = MockAppClient::new();
+		mock_client
+			.expect_reconstruct_block_from_dht()
+			.returning(|_, _, _, _, _| Box::pin(async move { Ok(vec![None, None]) }));
+		mock_client.expect_store_block_in_db().never();
+		let result = process_full_block(mock_client, &cfg, &full_block(), pp).await;
+		assert!(result.is_err());
 	}
 }
@@ -1,16 +1,22 @@
 #![doc = include_str!("../../README.md")]
 
 use anyhow::{anyhow, Context, Result};
-use avail_core::AppId;
 use avail_light::{
 	api,
 	consts::STATE_CF,
 	telemetry::{self},
 };
 use avail_light::{
-	consts::{APP_DATA_CF, BLOCK_HEADER_CF, CONFIDENCE_FACTOR_CF, EXPECTED_NETWORK_VERSION},
-	data::store_last_full_node_ws_in_db,
-	types::{CliOpts, Mode, RuntimeConfig, State},
+	consts::{
+		APP_DATA_CF, AVAILABILITY_FAILURE_CF, BLOCK_HASH_CF, BLOCK_HEADER_CF, CACHE_CF,
+		CONFIDENCE_COUNTS_CF, CONFIDENCE_FACTOR_CF, EQUIVOCATION_CF, EXPECTED_NETWORK_VERSION,
+		JUSTIFICATION_CF, SAMPLING_CF,
+	},
+	data::{migrations, snapshot, Database, MemoryDB, RocksDB},
+	types::{
+		AppBackfill, AvailabilityFailure, ChainViolation, CliOpts, Equivocation, Pruned,
+		PruningConfig, RetryConfig, RuntimeConfig, State,
+	},
 };
 use avail_subxt::primitives::Header;
 use clap::Parser;
@@ -25,9 +31,9 @@ use std::{
 };
 use tokio::sync::{
 	broadcast,
-	mpsc::{channel, Sender},
+	mpsc::{channel, unbounded_channel, Sender},
 };
-use tracing::{error, info, metadata::ParseLevelError, trace, warn, Level};
+use tracing::{error, info, metadata::ParseLevelError, warn, Level};
 use tracing_subscriber::{
 	fmt::format::{self, DefaultFields, Format, Full, Json},
 	FmtSubscriber,
@@ -51,19 +57,47 @@ fn init_db(path: &str) -> Result<Arc<DB>> {
 	let mut confidence_cf_opts = Options::default();
 	confidence_cf_opts.set_max_write_buffer_number(16);
 
+	let mut confidence_counts_cf_opts = Options::default();
+	confidence_counts_cf_opts.set_max_write_buffer_number(16);
+
+	let mut availability_failure_cf_opts = Options::default();
+	availability_failure_cf_opts.set_max_write_buffer_number(16);
+
 	let mut block_header_cf_opts = Options::default();
 	block_header_cf_opts.set_max_write_buffer_number(16);
 
+	let mut block_hash_cf_opts = Options::default();
+	block_hash_cf_opts.set_max_write_buffer_number(16);
+
+	let mut justification_cf_opts = Options::default();
+	justification_cf_opts.set_max_write_buffer_number(16);
+
+	let mut equivocation_cf_opts = Options::default();
+	equivocation_cf_opts.set_max_write_buffer_number(16);
+
 	let mut app_data_cf_opts = Options::default();
 	app_data_cf_opts.set_max_write_buffer_number(16);
 
+	let mut sampling_cf_opts = Options::default();
+	sampling_cf_opts.set_max_write_buffer_number(16);
+
+	let mut cache_cf_opts = Options::default();
+	cache_cf_opts.set_max_write_buffer_number(16);
+
 	let mut state_cf_opts = Options::default();
 	state_cf_opts.set_max_write_buffer_number(16);
 
 	let cf_opts = vec![
 		ColumnFamilyDescriptor::new(CONFIDENCE_FACTOR_CF, confidence_cf_opts),
+		ColumnFamilyDescriptor::new(CONFIDENCE_COUNTS_CF, confidence_counts_cf_opts),
+		ColumnFamilyDescriptor::new(AVAILABILITY_FAILURE_CF, availability_failure_cf_opts),
 		ColumnFamilyDescriptor::new(BLOCK_HEADER_CF, block_header_cf_opts),
+		ColumnFamilyDescriptor::new(BLOCK_HASH_CF, block_hash_cf_opts),
+		ColumnFamilyDescriptor::new(JUSTIFICATION_CF, justification_cf_opts),
+		ColumnFamilyDescriptor::new(EQUIVOCATION_CF, equivocation_cf_opts),
 		ColumnFamilyDescriptor::new(APP_DATA_CF, app_data_cf_opts),
+		ColumnFamilyDescriptor::new(SAMPLING_CF, sampling_cf_opts),
+		ColumnFamilyDescriptor::new(CACHE_CF, cache_cf_opts),
 		ColumnFamilyDescriptor::new(STATE_CF, state_cf_opts),
 	];
 
@@ -71,8 +105,9 @@ fn init_db(path: &str) -> Result<Arc<DB>> {
 	db_opts.create_if_missing(true);
 	db_opts.create_missing_column_families(true);
 
-	let db = DB::open_cf_descriptors(&db_opts, path, cf_opts)?;
-	Ok(Arc::new(db))
+	let db = Arc::new(DB::open_cf_descriptors(&db_opts, path, cf_opts)?);
+	migrations::migrate(db.clone()).context("Failed to migrate database")?;
+	Ok(db)
 }
 
 fn json_subscriber(log_level: Level) -> FmtSubscriber<DefaultFields, Format<Json>> {
@@ -125,14 +160,47 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		fs::remove_dir_all(&cfg.avail_path).context("Failed to remove local state directory")?;
 	}
 
+	if let Some(path) = opts.export_snapshot {
+		let db = init_db(&cfg.avail_path).context("Cannot initialize database")?;
+		let snapshot = snapshot::export(&db).context("Failed to export snapshot")?;
+		snapshot.write(&path)?;
+		info!(
+			"Exported snapshot with {} block headers into {path}",
+			snapshot.headers.len()
+		);
+		return Ok(());
+	}
+
 	if cfg.bootstraps.is_empty() {
 		Err(anyhow!("Bootstrap node list must not be empty. Either use a '--network' flag or add a list of bootstrap nodes in the configuration file"))?
 	}
 
+	if cfg.in_memory_db {
+		info!("Using in-memory database, data will not be persisted");
+		return start(cfg, MemoryDB::default(), opts.import_snapshot, error_sender).await;
+	}
+
 	let db = init_db(&cfg.avail_path).context(
 		"Cannot initialize database. Try running with '--clean' flag for a clean deployment.",
 	)?;
 
+	start(cfg, RocksDB(db), opts.import_snapshot, error_sender).await
+}
+
+async fn start(
+	mut cfg: RuntimeConfig,
+	db: impl Database,
+	import_snapshot: Option<String>,
+	error_sender: Sender<anyhow::Error>,
+) -> Result<()> {
+	let pp = avail_light::proof::load_public_params(
+		cfg.public_params_path.as_deref(),
+		cfg.public_params_preset.unwrap_or_default(),
+		cfg.public_params_hash.as_deref(),
+	)
+	.context("Failed to load public parameters")?;
+	let pp = Arc::new(pp);
+
 	// If in fat client mode, enable deleting local Kademlia records
 	// This is a fat client memory optimization
 	let kad_remove_local_record = cfg.block_matrix_partition.is_some();
@@ -156,6 +224,7 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		cfg.dht_parallelization_limit,
 		cfg.kad_record_ttl,
 		cfg.put_batch_size,
+		RetryConfig::dht(&cfg),
 		kad_remove_local_record,
 		id_keys,
 	)
@@ -188,13 +257,7 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 	#[cfg(feature = "network-analysis")]
 	tokio::task::spawn(network_analyzer::start_traffic_analyzer(cfg.port, 10));
 
-	let pp = Arc::new(kate_recovery::testnet::public_params(1024));
-	let raw_pp = pp.to_raw_var_bytes();
-	let public_params_hash = hex::encode(sp_core::blake2_128(&raw_pp));
-	let public_params_len = hex::encode(raw_pp).len();
-	trace!("Public params ({public_params_len}): hash: {public_params_hash}");
-
-	let last_full_node_ws = avail_light::data::get_last_full_node_ws_from_db(db.clone())?;
+	let last_full_node_ws = db.get_last_full_node_ws()?;
 
 	let (rpc_client, node) = avail_light::rpc::connect_to_the_full_node(
 		&cfg.full_node_ws,
@@ -203,10 +266,10 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 	)
 	.await?;
 
-	store_last_full_node_ws_in_db(db.clone(), node.host.clone())?;
+	db.store_last_full_node_ws(node.host.clone())?;
 
 	info!("Genesis hash: {:?}", node.genesis_hash);
-	if let Some(stored_genesis_hash) = avail_light::data::get_genesis_hash(db.clone())? {
+	if let Some(stored_genesis_hash) = db.get_genesis_hash()? {
 		if !node.genesis_hash.eq(&stored_genesis_hash) {
 			Err(anyhow!(
 				"Genesis hash doesn't match the stored one! Clear the db or change nodes."
@@ -214,19 +277,73 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		}
 	} else {
 		info!("No genesis hash is found in the db, storing the new hash now.");
-		avail_light::data::store_genesis_hash(db.clone(), node.genesis_hash)?;
+		db.store_genesis_hash(node.genesis_hash)?;
+	}
+
+	if let Some(path) = import_snapshot {
+		info!("Importing snapshot from {path}");
+		let snapshot = snapshot::Snapshot::read(&path)?;
+		let stored_checkpoint = db.get_finality_sync_checkpoint()?;
+		snapshot::validate(&snapshot, node.genesis_hash, stored_checkpoint.as_ref())
+			.context("Invalid snapshot")?;
+		// Anchor snapshot headers and finality sync checkpoint to the chain of the connected node
+		snapshot::validate_with_node(&rpc_client, &snapshot)
+			.await
+			.context("Invalid snapshot")?;
+		let headers_count = snapshot.headers.len();
+		snapshot::import(&db, snapshot).context("Failed to import snapshot")?;
+		info!("Imported snapshot with {headers_count} block headers");
 	}
 
 	let block_header = avail_light::rpc::get_chain_head_header(&rpc_client)
 		.await
 		.context("Failed to get chain header")?;
 
+	let app_ids = cfg.app_ids();
 	let state = Arc::new(Mutex::new(State::default()));
-	state.lock().unwrap().latest = block_header.number;
+	{
+		let mut state = state.lock().unwrap();
+		state.latest = block_header.number;
+		state.app_ids = app_ids.iter().copied().collect();
+		state.availability_failed = db
+			.get_availability_failed_blocks()
+			.context("Failed to get blocks with failed availability verification")?;
+		for pruned in Pruned::ALL {
+			*state.pruned_below_mut(pruned) = db
+				.get_pruned_below(pruned)
+				.context(format!("Failed to get pruned {pruned:?} bound"))?;
+		}
+	}
 	let sync_end_block = block_header.number.saturating_sub(1);
 
+	// Blocks finalized while the light client was not running are synced by the sync client
+	let last_processed_block = db
+		.get_last_processed_block()
+		.context("Failed to get last processed block")?;
+	if let Some(gap) = avail_light::sync_client::restart_gap(
+		last_processed_block,
+		cfg.sync_start_block,
+		sync_end_block,
+	) {
+		warn!(
+			"Blocks from {} to {} are missed since last run, scheduling sync",
+			gap.first, gap.last
+		);
+		cfg.sync_start_block = Some(gap.first);
+		state.lock().unwrap().gaps.push(gap);
+	}
+
 	let ws_clients = api::v2::types::WsClients::default();
 
+	// Application client is started if app IDs are configured or full block reconstruction is enabled,
+	// and tracked app IDs can be changed at runtime via API
+	let (backfill_tx, backfill_rx) = if !app_ids.is_empty() || cfg.full_block_reconstruction {
+		let (backfill_tx, backfill_rx) = channel::<AppBackfill>(1 << 7);
+		(Some(backfill_tx), Some(backfill_rx))
+	} else {
+		(None, None)
+	};
+
 	// Spawn tokio task which runs one http server for handling RPC
 	let server = api::server::Server {
 		db: db.clone(),
@@ -237,22 +354,23 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		node,
 		node_client: rpc_client.clone(),
 		ws_clients: ws_clients.clone(),
+		backfill_sender: backfill_tx,
 	};
 
 	tokio::task::spawn(server.run());
 
-	let (block_tx, data_rx) = if let Mode::AppClient(app_id) = Mode::from(cfg.app_id) {
+	let (block_tx, data_rx) = if let Some(backfill_rx) = backfill_rx {
 		// communication channels being established for talking to
 		// libp2p backed application client
 		let (block_tx, block_rx) = broadcast::channel::<avail_light::types::BlockVerified>(1 << 7);
-		let (data_tx, data_rx) = broadcast::channel::<(u32, AppData)>(1 << 7);
+		let (data_tx, data_rx) = broadcast::channel::<(u32, u32, AppData)>(1 << 7);
 		tokio::task::spawn(avail_light::app_client::run(
 			(&cfg).into(),
 			db.clone(),
 			network_client.clone(),
 			rpc_client.clone(),
-			AppId(app_id),
 			block_rx,
+			backfill_rx,
 			pp.clone(),
 			state.clone(),
 			sync_end_block,
@@ -283,7 +401,7 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		tokio::task::spawn(api::v2::publish(
 			api::v2::types::Topic::DataVerified,
 			data_rx,
-			ws_clients,
+			ws_clients.clone(),
 		));
 	}
 
@@ -298,8 +416,12 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		));
 	}
 
-	let sync_client =
-		avail_light::sync_client::new(db.clone(), network_client.clone(), rpc_client.clone());
+	let sync_client = avail_light::sync_client::new(
+		db.clone(),
+		network_client.clone(),
+		rpc_client.clone(),
+		(&cfg).into(),
+	);
 
 	if let Some(sync_start_block) = cfg.sync_start_block {
 		state.lock().unwrap().synced.replace(false);
@@ -314,8 +436,28 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		));
 	}
 
+	let (missed_block_tx, missed_block_rx) = unbounded_channel::<u32>();
+	let missed_blocks_client = avail_light::sync_client::new(
+		db.clone(),
+		network_client.clone(),
+		rpc_client.clone(),
+		(&cfg).into(),
+	);
+	tokio::task::spawn(avail_light::sync_client::run_missed_blocks(
+		missed_blocks_client,
+		(&cfg).into(),
+		pp.clone(),
+		block_tx.clone(),
+		state.clone(),
+		missed_block_rx,
+	));
+
 	if cfg.sync_finality_enable {
-		let sync_finality = avail_light::sync_finality::new(db.clone(), rpc_client.clone());
+		let sync_finality = avail_light::sync_finality::new(
+			db.clone(),
+			rpc_client.clone(),
+			cfg.trusted_checkpoint.clone(),
+		);
 		tokio::task::spawn(avail_light::sync_finality::run(
 			sync_finality,
 			error_sender.clone(),
@@ -329,28 +471,72 @@ async fn run(error_sender: Sender<anyhow::Error>) -> Result<()> {
 		s.finality_synced = true;
 	}
 
-	let light_client =
-		avail_light::light_client::new(db.clone(), network_client.clone(), rpc_client.clone());
+	let pruning_cfg: PruningConfig = (&cfg).into();
+	if pruning_cfg.is_enabled() {
+		tokio::task::spawn(avail_light::pruning::run(
+			db.clone(),
+			pruning_cfg,
+			state.clone(),
+		));
+	}
+
+	let light_client = avail_light::light_client::new(
+		db.clone(),
+		network_client.clone(),
+		rpc_client.clone(),
+		(&cfg).into(),
+	);
+
+	let (failure_tx, failure_rx) = broadcast::channel::<(u32, AvailabilityFailure)>(1 << 7);
+	tokio::task::spawn(api::v2::publish(
+		api::v2::types::Topic::AvailabilityFailed,
+		failure_rx,
+		ws_clients.clone(),
+	));
 
 	let lc_channels = avail_light::light_client::Channels {
 		block_sender: block_tx,
 		header_receiver: message_rx,
 		error_sender: error_sender.clone(),
+		failure_sender: failure_tx,
+		missed_block_sender: missed_block_tx.clone(),
 	};
 
 	tokio::task::spawn(avail_light::light_client::run(
 		light_client,
 		(&cfg).into(),
 		pp,
-		ot_metrics,
+		ot_metrics.clone(),
 		state.clone(),
 		lc_channels,
 	));
 
-	tokio::task::spawn(avail_light::subscriptions::finalized_headers(
-		rpc_client,
+	let (violation_tx, violation_rx) = broadcast::channel::<ChainViolation>(1 << 4);
+	tokio::task::spawn(api::v2::publish(
+		api::v2::types::Topic::ChainViolation,
+		violation_rx,
+		ws_clients.clone(),
+	));
+
+	let (equivocation_tx, equivocation_rx) = broadcast::channel::<(u32, Equivocation)>(1 << 4);
+	tokio::task::spawn(api::v2::publish(
+		api::v2::types::Topic::Equivocation,
+		equivocation_rx,
+		ws_clients.clone(),
+	));
+
+	let subscriptions_channels = avail_light::subscriptions::Channels {
 		message_tx,
+		violation_tx,
+		equivocation_tx,
+		missed_block_tx,
 		error_sender,
+	};
+
+	tokio::task::spawn(avail_light::subscriptions::finalized_headers(
+		rpc_client,
+		subscriptions_channels,
+		ot_metrics,
 		state,
 		db,
 	));
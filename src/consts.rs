@@ -5,15 +5,39 @@ use crate::rpc::ExpectedVersion;
 /// Column family for confidence factor
 pub const CONFIDENCE_FACTOR_CF: &str = "avail_light_confidence_factor_cf";
 
+/// Column family for number of verified cells per cell source
+pub const CONFIDENCE_COUNTS_CF: &str = "avail_light_confidence_counts_cf";
+
+/// Column family for failed data availability verifications
+pub const AVAILABILITY_FAILURE_CF: &str = "avail_light_availability_failure_cf";
+
 /// Column family for block header
 pub const BLOCK_HEADER_CF: &str = "avail_light_block_header_cf";
 
+/// Column family for block hash to block number index
+pub const BLOCK_HASH_CF: &str = "avail_light_block_hash_cf";
+
+/// Column family for GRANDPA justifications of finalized blocks
+pub const JUSTIFICATION_CF: &str = "avail_light_justification_cf";
+
+/// Column family for equivocations detected in GRANDPA justifications
+pub const EQUIVOCATION_CF: &str = "avail_light_equivocation_cf";
+
 /// Column family for app data
 pub const APP_DATA_CF: &str = "avail_light_app_data_cf";
 
+/// Column family for sampled cells and their verification outcome
+pub const SAMPLING_CF: &str = "avail_light_sampling_cf";
+
+/// Column family for locally cached verified cells and rows
+pub const CACHE_CF: &str = "avail_light_cache_cf";
+
 /// Column family for state
 pub const STATE_CF: &str = "avail_light_state_cf";
 
+/// Expected block time, in seconds
+pub const BLOCK_TIME_SECS: u64 = 20;
+
 /// Expected network version
 pub const EXPECTED_NETWORK_VERSION: ExpectedVersion = ExpectedVersion {
 	version: "1.7",
@@ -0,0 +1,476 @@
+//! In-memory implementation of the [`Database`] trait.
+//!
+//! Data is not persisted, so it is lost on restart. Suitable for running the client without
+//! RocksDB on disk, and for tests.
+
+use anyhow::Result;
+use avail_subxt::{primitives::Header as DaHeader, utils::H256};
+use codec::Encode;
+use kate_recovery::{com::AppData, data::Cell, matrix::Position};
+use sp_core::blake2_256;
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	sync::{Arc, RwLock},
+};
+
+use super::{cell_cache_key, row_cache_key, snapshot::Snapshot, BlockBatch, Database};
+use crate::types::{
+	AvailabilityFailure, ConfidenceCounts, Equivocation, FinalityJustification,
+	FinalitySyncCheckpoint, Pruned, SamplingRecord,
+};
+
+#[derive(Default)]
+struct MemoryStore {
+	confidence: BTreeMap<u32, u32>,
+	confidence_counts: BTreeMap<u32, ConfidenceCounts>,
+	sampling: BTreeMap<u32, SamplingRecord>,
+	availability_failures: BTreeMap<u32, AvailabilityFailure>,
+	headers: BTreeMap<u32, DaHeader>,
+	block_numbers: HashMap<H256, u32>,
+	justifications: BTreeMap<u32, FinalityJustification>,
+	equivocations: BTreeMap<u32, Vec<Equivocation>>,
+	app_data: HashMap<(u32, u32), AppData>,
+	cached_cells: BTreeMap<(u32, u32, u16), Cell>,
+	cached_rows: BTreeMap<(u32, u32), Vec<u8>>,
+	last_full_node_ws: Option<String>,
+	genesis_hash: Option<H256>,
+	finality_sync_checkpoint: Option<FinalitySyncCheckpoint>,
+	pruned_below: HashMap<Pruned, u32>,
+}
+
+#[derive(Clone, Default)]
+pub struct MemoryDB(Arc<RwLock<MemoryStore>>);
+
+impl MemoryDB {
+	fn read<T>(&self, f: impl FnOnce(&MemoryStore) -> T) -> T {
+		f(&self.0.read().expect("Lock should be acquired"))
+	}
+
+	fn write<T>(&self, f: impl FnOnce(&mut MemoryStore) -> T) -> T {
+		f(&mut self.0.write().expect("Lock should be acquired"))
+	}
+}
+
+impl Database for MemoryDB {
+	fn get_confidence(&self, block_number: u32) -> Result<Option<u32>> {
+		Ok(self.read(|store| store.confidence.get(&block_number).copied()))
+	}
+
+	fn store_confidence(&self, block_number: u32, count: u32) -> Result<()> {
+		self.write(|store| store.confidence.insert(block_number, count));
+		Ok(())
+	}
+
+	fn is_confidence_stored(&self, block_number: u32) -> Result<bool> {
+		Ok(self.read(|store| store.confidence.contains_key(&block_number)))
+	}
+
+	fn get_last_processed_block(&self) -> Result<Option<u32>> {
+		Ok(self.read(|store| {
+			let last_confidence_block = store.confidence.keys().next_back().copied();
+			let last_failed_block = store.availability_failures.keys().next_back().copied();
+			last_confidence_block.max(last_failed_block)
+		}))
+	}
+
+	fn prune_confidence(&self, below_block_number: u32) -> Result<()> {
+		self.write(|store| {
+			store.confidence = store.confidence.split_off(&below_block_number);
+			store.confidence_counts = store.confidence_counts.split_off(&below_block_number);
+			store.sampling = store.sampling.split_off(&below_block_number);
+			store.availability_failures =
+				store.availability_failures.split_off(&below_block_number);
+		});
+		Ok(())
+	}
+
+	fn get_confidence_counts(&self, block_number: u32) -> Result<Option<ConfidenceCounts>> {
+		Ok(self.read(|store| store.confidence_counts.get(&block_number).copied()))
+	}
+
+	fn get_sampling(&self, block_number: u32) -> Result<Option<SamplingRecord>> {
+		Ok(self.read(|store| store.sampling.get(&block_number).cloned()))
+	}
+
+	fn get_availability_failure(&self, block_number: u32) -> Result<Option<AvailabilityFailure>> {
+		Ok(self.read(|store| store.availability_failures.get(&block_number).copied()))
+	}
+
+	fn get_availability_failed_blocks(&self) -> Result<BTreeSet<u32>> {
+		Ok(self.read(|store| store.availability_failures.keys().copied().collect()))
+	}
+
+	fn get_header(&self, block_number: u32) -> Result<Option<DaHeader>> {
+		Ok(self.read(|store| store.headers.get(&block_number).cloned()))
+	}
+
+	fn store_header(&self, block_number: u32, header: &DaHeader) -> Result<()> {
+		let block_hash = Encode::using_encoded(header, blake2_256).into();
+		self.write(|store| {
+			store.headers.insert(block_number, header.clone());
+			store.block_numbers.insert(block_hash, block_number);
+		});
+		Ok(())
+	}
+
+	fn is_header_stored(&self, block_number: u32) -> Result<bool> {
+		Ok(self.read(|store| store.headers.contains_key(&block_number)))
+	}
+
+	fn get_block_number(&self, block_hash: H256) -> Result<Option<u32>> {
+		Ok(self.read(|store| store.block_numbers.get(&block_hash).copied()))
+	}
+
+	fn prune_headers(&self, below_block_number: u32) -> Result<()> {
+		self.write(|store| {
+			store.headers = store.headers.split_off(&below_block_number);
+			store.justifications = store.justifications.split_off(&below_block_number);
+			store.equivocations = store.equivocations.split_off(&below_block_number);
+			store
+				.block_numbers
+				.retain(|_, block_number| *block_number >= below_block_number);
+		});
+		Ok(())
+	}
+
+	fn get_justification(&self, block_number: u32) -> Result<Option<FinalityJustification>> {
+		Ok(self.read(|store| store.justifications.get(&block_number).cloned()))
+	}
+
+	fn store_justification(
+		&self,
+		block_number: u32,
+		justification: &FinalityJustification,
+	) -> Result<()> {
+		self.write(|store| {
+			store
+				.justifications
+				.insert(block_number, justification.clone())
+		});
+		Ok(())
+	}
+
+	fn get_equivocations(&self, block_number: u32) -> Result<Vec<Equivocation>> {
+		Ok(self.read(|store| {
+			store
+				.equivocations
+				.get(&block_number)
+				.cloned()
+				.unwrap_or_default()
+		}))
+	}
+
+	fn store_equivocations(&self, block_number: u32, equivocations: &[Equivocation]) -> Result<()> {
+		self.write(|store| {
+			store
+				.equivocations
+				.insert(block_number, equivocations.to_vec())
+		});
+		Ok(())
+	}
+
+	fn get_data(&self, app_id: u32, block_number: u32) -> Result<Option<AppData>> {
+		Ok(self.read(|store| store.app_data.get(&(app_id, block_number)).cloned()))
+	}
+
+	fn store_data(&self, app_id: u32, block_number: u32, data: &AppData) -> Result<()> {
+		self.write(|store| store.app_data.insert((app_id, block_number), data.clone()));
+		Ok(())
+	}
+
+	fn prune_data(&self, below_block_number: u32) -> Result<()> {
+		self.write(|store| {
+			store
+				.app_data
+				.retain(|&(_, block_number), _| block_number >= below_block_number)
+		});
+		Ok(())
+	}
+
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Result<Vec<Cell>> {
+		Ok(self.read(|store| {
+			positions
+				.iter()
+				.filter_map(|position| {
+					let key = (block_number, position.row, position.col);
+					store.cached_cells.get(&key).cloned()
+				})
+				.collect()
+		}))
+	}
+
+	fn get_cached_rows(
+		&self,
+		block_number: u32,
+		row_indexes: &[u32],
+	) -> Result<Vec<Option<Vec<u8>>>> {
+		Ok(self.read(|store| {
+			row_indexes
+				.iter()
+				.map(|&row_index| store.cached_rows.get(&(block_number, row_index)).cloned())
+				.collect()
+		}))
+	}
+
+	fn get_cache_sizes(&self) -> Result<BTreeMap<u32, u64>> {
+		Ok(self.read(|store| {
+			// Sizes include keys, as in RocksDB implementation
+			let mut sizes = BTreeMap::<u32, u64>::new();
+			for (&(block_number, _, _), cell) in &store.cached_cells {
+				let key = cell_cache_key(block_number, &cell.position);
+				*sizes.entry(block_number).or_default() += (key.len() + cell.content.len()) as u64;
+			}
+			for (&(block_number, row_index), row) in &store.cached_rows {
+				let key = row_cache_key(block_number, row_index);
+				*sizes.entry(block_number).or_default() += (key.len() + row.len()) as u64;
+			}
+			sizes
+		}))
+	}
+
+	fn prune_cache(&self, below_block_number: u32) -> Result<()> {
+		self.write(|store| {
+			store.cached_cells = store.cached_cells.split_off(&(below_block_number, 0, 0));
+			store.cached_rows = store.cached_rows.split_off(&(below_block_number, 0));
+		});
+		Ok(())
+	}
+
+	fn store_block(&self, block_number: u32, block: BlockBatch) -> Result<()> {
+		let block_hash = block
+			.header
+			.as_ref()
+			.map(|header| Encode::using_encoded(header, blake2_256).into());
+		self.write(|store| {
+			if let (Some(header), Some(block_hash)) = (block.header, block_hash) {
+				store.headers.insert(block_number, header);
+				store.block_numbers.insert(block_hash, block_number);
+			}
+			if let Some(count) = block.confidence {
+				store.confidence.insert(block_number, count);
+			}
+			if let Some(counts) = block.confidence_counts {
+				store.confidence_counts.insert(block_number, counts);
+			}
+			if let Some(sampling) = block.sampling {
+				store.sampling.insert(block_number, sampling);
+			}
+			if let Some(failure) = block.failure {
+				store.availability_failures.insert(block_number, failure);
+			}
+			for cell in block.cells {
+				let key = (block_number, cell.position.row, cell.position.col);
+				store.cached_cells.insert(key, cell);
+			}
+			for (row_index, row) in block.rows {
+				store.cached_rows.insert((block_number, row_index), row);
+			}
+			for (app_id, data) in block.app_data {
+				store.app_data.insert((app_id, block_number), data);
+			}
+		});
+		Ok(())
+	}
+
+	fn store_snapshot(&self, snapshot: Snapshot) -> Result<()> {
+		let headers = snapshot
+			.headers
+			.into_iter()
+			.map(|header| (Encode::using_encoded(&header, blake2_256).into(), header))
+			.collect::<Vec<(H256, DaHeader)>>();
+		self.write(|store| {
+			for (block_hash, header) in headers {
+				store.block_numbers.insert(block_hash, header.number);
+				store.headers.insert(header.number, header);
+			}
+			store.confidence.extend(snapshot.confidences);
+			if let Some(checkpoint) = snapshot.finality_sync_checkpoint {
+				store.finality_sync_checkpoint = Some(checkpoint);
+			}
+			store.genesis_hash = Some(snapshot.genesis_hash);
+		});
+		Ok(())
+	}
+
+	fn get_last_full_node_ws(&self) -> Result<Option<String>> {
+		Ok(self.read(|store| store.last_full_node_ws.clone()))
+	}
+
+	fn store_last_full_node_ws(&self, last_full_node_ws: String) -> Result<()> {
+		self.write(|store| store.last_full_node_ws = Some(last_full_node_ws));
+		Ok(())
+	}
+
+	fn get_genesis_hash(&self) -> Result<Option<H256>> {
+		Ok(self.read(|store| store.genesis_hash))
+	}
+
+	fn store_genesis_hash(&self, genesis_hash: H256) -> Result<()> {
+		self.write(|store| store.genesis_hash = Some(genesis_hash));
+		Ok(())
+	}
+
+	fn get_finality_sync_checkpoint(&self) -> Result<Option<FinalitySyncCheckpoint>> {
+		Ok(self.read(|store| store.finality_sync_checkpoint.clone()))
+	}
+
+	fn store_finality_sync_checkpoint(&self, checkpoint: FinalitySyncCheckpoint) -> Result<()> {
+		self.write(|store| store.finality_sync_checkpoint = Some(checkpoint));
+		Ok(())
+	}
+
+	fn get_pruned_below(&self, pruned: Pruned) -> Result<Option<u32>> {
+		Ok(self.read(|store| store.pruned_below.get(&pruned).copied()))
+	}
+
+	fn store_pruned_below(&self, pruned: Pruned, block_number: u32) -> Result<()> {
+		self.write(|store| store.pruned_below.insert(pruned, block_number));
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::MemoryDB;
+	use crate::{
+		data::{BlockBatch, Database},
+		types::{AvailabilityFailure, ConfidenceCounts, FailureReason},
+	};
+	use kate_recovery::{data::Cell, matrix::Position};
+	use std::collections::BTreeSet;
+
+	#[test]
+	fn last_processed_block() {
+		let db = MemoryDB::default();
+		assert_eq!(db.get_last_processed_block().unwrap(), None);
+
+		db.store_confidence(7, 1).unwrap();
+		db.store_confidence(3, 1).unwrap();
+		assert_eq!(db.get_last_processed_block().unwrap(), Some(7));
+
+		let failure = AvailabilityFailure {
+			reason: FailureReason::CellsUnavailable,
+			cells_requested: 10,
+			cells_fetched: 4,
+			cells_unverified: 0,
+		};
+		let block = BlockBatch {
+			failure: Some(failure),
+			..Default::default()
+		};
+		db.store_block(9, block).unwrap();
+		assert_eq!(db.get_last_processed_block().unwrap(), Some(9));
+	}
+
+	#[test]
+	fn prune() {
+		let db = MemoryDB::default();
+		for block_number in 0..10 {
+			db.store_confidence(block_number, block_number).unwrap();
+			db.store_data(1, block_number, &vec![vec![block_number as u8]])
+				.unwrap();
+			db.store_data(2, block_number, &vec![]).unwrap();
+		}
+
+		db.prune_confidence(5).unwrap();
+		db.prune_data(5).unwrap();
+
+		for block_number in 0..5 {
+			assert!(!db.is_confidence_stored(block_number).unwrap());
+			assert_eq!(db.get_data(1, block_number).unwrap(), None);
+			assert_eq!(db.get_data(2, block_number).unwrap(), None);
+		}
+		for block_number in 5..10 {
+			assert_eq!(db.get_confidence(block_number).unwrap(), Some(block_number));
+			assert_eq!(
+				db.get_data(1, block_number).unwrap(),
+				Some(vec![vec![block_number as u8]])
+			);
+			assert_eq!(db.get_data(2, block_number).unwrap(), Some(vec![]));
+		}
+	}
+
+	#[test]
+	fn store_block() {
+		let db = MemoryDB::default();
+		let counts = ConfidenceCounts { dht: 6, rpc: 4 };
+		let block = BlockBatch {
+			confidence: Some(10),
+			confidence_counts: Some(counts),
+			app_data: vec![(1, vec![vec![1]]), (2, vec![vec![2]])],
+			..Default::default()
+		};
+		db.store_block(5, block).unwrap();
+
+		assert_eq!(db.get_confidence(5).unwrap(), Some(10));
+		assert_eq!(db.get_confidence_counts(5).unwrap(), Some(counts));
+		assert_eq!(db.get_availability_failure(5).unwrap(), None);
+		db.prune_confidence(6).unwrap();
+		assert_eq!(db.get_confidence_counts(5).unwrap(), None);
+		assert!(!db.is_header_stored(5).unwrap());
+		assert_eq!(db.get_data(1, 5).unwrap(), Some(vec![vec![1]]));
+		assert_eq!(db.get_data(2, 5).unwrap(), Some(vec![vec![2]]));
+	}
+
+	#[test]
+	fn availability_failure() {
+		let db = MemoryDB::default();
+		let failure = AvailabilityFailure {
+			reason: FailureReason::CellsUnavailable,
+			cells_requested: 10,
+			cells_fetched: 4,
+			cells_unverified: 0,
+		};
+		let block = BlockBatch {
+			failure: Some(failure),
+			..Default::default()
+		};
+		db.store_block(5, block).unwrap();
+
+		assert_eq!(db.get_availability_failure(5).unwrap(), Some(failure));
+		assert_eq!(db.get_confidence(5).unwrap(), None);
+		assert_eq!(
+			db.get_availability_failed_blocks().unwrap(),
+			BTreeSet::from([5])
+		);
+		db.prune_confidence(6).unwrap();
+		assert_eq!(db.get_availability_failure(5).unwrap(), None);
+		assert!(db.get_availability_failed_blocks().unwrap().is_empty());
+	}
+
+	#[test]
+	fn cache() {
+		let db = MemoryDB::default();
+		let cell = |row, col| Cell {
+			position: Position { row, col },
+			content: [row as u8; 80],
+		};
+		for block_number in 0..4 {
+			let block = BlockBatch {
+				cells: vec![cell(0, 1), cell(1, 0)],
+				rows: vec![(1, vec![block_number as u8])],
+				..Default::default()
+			};
+			db.store_block(block_number, block).unwrap();
+		}
+
+		let positions = [Position { row: 0, col: 1 }, Position { row: 2, col: 2 }];
+		let cached = db.get_cached_cells(3, &positions).unwrap();
+		assert_eq!(cached.len(), 1);
+		assert_eq!(cached[0].position, positions[0]);
+		assert_eq!(
+			db.get_cached_rows(3, &[0, 1]).unwrap(),
+			vec![None, Some(vec![3])]
+		);
+		// Two cells of 80 bytes with 11 bytes keys, and a row of one byte with 9 bytes key per block
+		assert_eq!(db.get_cache_sizes().unwrap().get(&3), Some(&192));
+
+		db.prune_cache(2).unwrap();
+		let sizes = db.get_cache_sizes().unwrap();
+		assert_eq!(sizes.keys().copied().collect::<Vec<_>>(), vec![2, 3]);
+		assert!(db.get_cached_cells(1, &positions).unwrap().is_empty());
+		assert_eq!(db.get_cached_rows(1, &[1]).unwrap(), vec![None]);
+		assert_eq!(db.get_cached_cells(2, &positions).unwrap().len(), 1);
+		assert_eq!(db.get_cached_rows(2, &[1]).unwrap(), vec![Some(vec![2])]);
+	}
+}
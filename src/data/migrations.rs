@@ -0,0 +1,442 @@
+//! Database schema versioning and migrations.
+//!
+//! Schema version is stored in the state column family. Databases created before versioning was
+//! introduced have no stored version, and are treated as version 0. On startup, database is
+//! upgraded in place by applying migrations one version at a time, storing the new version after
+//! each successful migration. Databases with a version newer than the supported one are rejected.
+
+use anyhow::{anyhow, Context, Result};
+use avail_subxt::primitives::Header as DaHeader;
+use rocksdb::{IteratorMode, WriteBatch, DB};
+use sp_core::ed25519;
+use std::sync::Arc;
+use tracing::info;
+
+use super::{
+	app_data_key, get_schema_version, put_block_header, store_finality_sync_checkpoint,
+	store_schema_version, FINALITY_SYNC_CHECKPOINT_KEY,
+};
+use crate::{
+	consts::{
+		APP_DATA_CF, AVAILABILITY_FAILURE_CF, BLOCK_HASH_CF, BLOCK_HEADER_CF, CACHE_CF,
+		CONFIDENCE_COUNTS_CF, CONFIDENCE_FACTOR_CF, EQUIVOCATION_CF, JUSTIFICATION_CF, SAMPLING_CF,
+		STATE_CF,
+	},
+	types::{FinalitySyncCheckpoint, SamplingRecord},
+};
+use codec::{Decode, DecodeAll, Encode};
+
+/// Database schema version supported by this version of the light client
+pub const SCHEMA_VERSION: u32 = 5;
+
+/// Maximum number of entries written in a single write batch, bounding memory used by migrations
+const BATCH_SIZE: usize = 1024;
+
+type Migration = fn(Arc<DB>) -> Result<()>;
+
+/// Migrations where migration at index `n` upgrades schema from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+	migrate_v0_to_v1,
+	migrate_v1_to_v2,
+	migrate_v2_to_v3,
+	migrate_v3_to_v4,
+	migrate_v4_to_v5,
+];
+
+/// Version 0 has the same layout as version 1, which introduced schema versioning
+fn migrate_v0_to_v1(_db: Arc<DB>) -> Result<()> {
+	Ok(())
+}
+
+/// Re-encodes JSON block headers with SCALE, and indexes block numbers by block hashes.
+///
+/// Headers are written in multiple batches, so interrupted migration leaves some headers already
+/// SCALE encoded and indexed. Those headers are skipped, so migration can be rerun.
+fn migrate_v1_to_v2(db: Arc<DB>) -> Result<()> {
+	let handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+
+	let mut batch = WriteBatch::default();
+	for item in db.iterator_cf(&handle, IteratorMode::Start) {
+		let (key, value) = item.context("Failed to iterate over block headers")?;
+		let block_number = <[u8; 4]>::try_from(&key[..])
+			.map(u32::from_be_bytes)
+			.map_err(|_| anyhow!("Invalid block header key"))?;
+		let header: DaHeader = match serde_json::from_slice(&value) {
+			Ok(header) => header,
+			Err(_) if DaHeader::decode_all(&mut &value[..]).is_ok() => continue,
+			Err(error) => {
+				return Err(error)
+					.context(format!("Failed to deserialize block header {block_number}"))
+			},
+		};
+		put_block_header(&db, &mut batch, block_number, &header)?;
+		if batch.len() >= BATCH_SIZE {
+			db.write(std::mem::take(&mut batch))
+				.context("Failed to write block headers")?;
+		}
+	}
+
+	db.write(batch).context("Failed to write block headers")
+}
+
+/// Stores number of verified cells per cell source, counted from the stored sampling records
+fn migrate_v2_to_v3(db: Arc<DB>) -> Result<()> {
+	let sampling_handle = db
+		.cf_handle(SAMPLING_CF)
+		.context("Failed to get cf handle")?;
+	let counts_handle = db
+		.cf_handle(CONFIDENCE_COUNTS_CF)
+		.context("Failed to get cf handle")?;
+
+	let mut batch = WriteBatch::default();
+	for item in db.iterator_cf(&sampling_handle, IteratorMode::Start) {
+		let (key, value) = item.context("Failed to iterate over sampling records")?;
+		let record =
+			SamplingRecord::decode(&mut &value[..]).context("Failed to decode sampling record")?;
+		batch.put_cf(&counts_handle, key, record.confidence_counts().encode());
+		if batch.len() >= BATCH_SIZE {
+			db.write(std::mem::take(&mut batch))
+				.context("Failed to write confidence counts")?;
+		}
+	}
+
+	db.write(batch).context("Failed to write confidence counts")
+}
+
+/// Finality sync checkpoint stored before validator weights were introduced
+#[derive(Decode)]
+struct CheckpointV3 {
+	number: u32,
+	set_id: u64,
+	validator_set: Vec<ed25519::Public>,
+}
+
+/// Adds voting weights to the stored finality sync checkpoint validators.
+/// GRANDPA authorities have equal weights, so each validator is assigned weight 1.
+fn migrate_v3_to_v4(db: Arc<DB>) -> Result<()> {
+	let handle = db.cf_handle(STATE_CF).context("Failed to get cf handle")?;
+	let Some(value) = db
+		.get_cf(&handle, FINALITY_SYNC_CHECKPOINT_KEY.as_bytes())
+		.context("Failed to get finality sync checkpoint")?
+	else {
+		return Ok(());
+	};
+
+	let checkpoint = CheckpointV3::decode(&mut &value[..])
+		.context("Failed to decode finality sync checkpoint")?;
+	let validator_set = checkpoint
+		.validator_set
+		.into_iter()
+		.map(|validator| (validator, 1))
+		.collect();
+
+	store_finality_sync_checkpoint(
+		db.clone(),
+		FinalitySyncCheckpoint {
+			number: checkpoint.number,
+			set_id: checkpoint.set_id,
+			validator_set,
+		},
+	)
+}
+
+/// Re-keys app data from `app_id:block_number` string keys to keys prefixed with the block number,
+/// so app data can be pruned by the block range.
+///
+/// App data is written in multiple batches, so interrupted migration leaves some app data already
+/// re-keyed. Those 8 byte keys are skipped, so migration can be rerun.
+fn migrate_v4_to_v5(db: Arc<DB>) -> Result<()> {
+	let handle = db
+		.cf_handle(APP_DATA_CF)
+		.context("Failed to get cf handle")?;
+
+	let mut batch = WriteBatch::default();
+	for item in db.iterator_cf(&handle, IteratorMode::Start) {
+		let (key, value) = item.context("Failed to iterate over app data")?;
+		let parsed = std::str::from_utf8(&key)
+			.ok()
+			.and_then(|key| key.split_once(':'))
+			.and_then(|(app_id, block_number)| {
+				Some((
+					app_id.parse::<u32>().ok()?,
+					block_number.parse::<u32>().ok()?,
+				))
+			});
+		let Some((app_id, block_number)) = parsed else {
+			if key.len() == app_data_key(0, 0).len() {
+				continue;
+			}
+			return Err(anyhow!("Invalid app data key"));
+		};
+		batch.delete_cf(&handle, &key);
+		batch.put_cf(&handle, app_data_key(app_id, block_number), value);
+		if batch.len() >= BATCH_SIZE {
+			db.write(std::mem::take(&mut batch))
+				.context("Failed to write app data")?;
+		}
+	}
+
+	db.write(batch).context("Failed to write app data")
+}
+
+fn is_empty(db: Arc<DB>) -> Result<bool> {
+	for cf in [
+		CONFIDENCE_FACTOR_CF,
+		CONFIDENCE_COUNTS_CF,
+		AVAILABILITY_FAILURE_CF,
+		BLOCK_HEADER_CF,
+		BLOCK_HASH_CF,
+		JUSTIFICATION_CF,
+		EQUIVOCATION_CF,
+		APP_DATA_CF,
+		SAMPLING_CF,
+		CACHE_CF,
+		STATE_CF,
+	] {
+		let handle = db.cf_handle(cf).context("Failed to get cf handle")?;
+		if db
+			.iterator_cf(&handle, IteratorMode::Start)
+			.next()
+			.is_some()
+		{
+			return Ok(false);
+		}
+	}
+	Ok(true)
+}
+
+/// Upgrades database schema to the supported version.
+///
+/// Fails if database schema version is newer than the supported one.
+pub fn migrate(db: Arc<DB>) -> Result<()> {
+	let version = match get_schema_version(db.clone())? {
+		Some(version) => version,
+		None if is_empty(db.clone())? => {
+			info!("Initializing database schema version {SCHEMA_VERSION}");
+			return store_schema_version(db, SCHEMA_VERSION);
+		},
+		None => 0,
+	};
+
+	if version > SCHEMA_VERSION {
+		return Err(anyhow!(
+			"Database schema version {version} is newer than version {SCHEMA_VERSION} supported by this light client. Upgrade the light client, or run with '--clean' flag to remove the existing database."
+		));
+	}
+
+	for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+		let to = from as u32 + 1;
+		info!("Migrating database schema from version {from} to version {to}");
+		migration(db.clone())
+			.context(format!("Failed to migrate database schema to version {to}"))?;
+		store_schema_version(db.clone(), to)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{migrate, SCHEMA_VERSION};
+	use crate::{
+		consts::{APP_DATA_CF, BLOCK_HEADER_CF, SAMPLING_CF, STATE_CF},
+		data::{
+			app_data_key, get_block_header_from_db, get_block_number_from_db,
+			get_confidence_counts_from_db, get_decoded_data_from_db, get_finality_sync_checkpoint,
+			get_schema_version, prune_app_data_in_db, put_block_header, store_confidence_in_db,
+			store_schema_version, tests::temp_db, FINALITY_SYNC_CHECKPOINT_KEY,
+		},
+		test_utils::{header, header_hash},
+		types::{CellSource, ConfidenceCounts, SampledCell, SamplingRecord},
+	};
+	use codec::Encode;
+	use rocksdb::WriteBatch;
+	use sp_core::{ed25519, H256};
+
+	#[test]
+	fn migrate_empty_db() {
+		let (_dir, db) = temp_db();
+		migrate(db.clone()).unwrap();
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_unversioned_db() {
+		let (_dir, db) = temp_db();
+		store_confidence_in_db(db.clone(), 1, 1).unwrap();
+		migrate(db.clone()).unwrap();
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_newer_version() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), SCHEMA_VERSION + 1).unwrap();
+		assert!(migrate(db.clone()).is_err());
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION + 1));
+	}
+
+	#[test]
+	fn migrate_v1_to_v2() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 1).unwrap();
+		// Number of headers exceeds batch size, so headers are written in multiple batches
+		let block_numbers = 0..(super::BATCH_SIZE as u32 + 1);
+		let handle = db.cf_handle(BLOCK_HEADER_CF).unwrap();
+		for block_number in block_numbers.clone() {
+			let json = serde_json::to_vec(&header(block_number, H256::default())).unwrap();
+			db.put_cf(&handle, block_number.to_be_bytes(), json)
+				.unwrap();
+		}
+
+		migrate(db.clone()).unwrap();
+
+		for block_number in block_numbers {
+			let header = header(block_number, H256::default());
+			let stored = get_block_header_from_db(db.clone(), block_number).unwrap();
+			assert_eq!(stored.map(|header| header.encode()), Some(header.encode()));
+			let stored_number = get_block_number_from_db(db.clone(), header_hash(&header)).unwrap();
+			assert_eq!(stored_number, Some(block_number));
+		}
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_v1_to_v2_interrupted() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 1).unwrap();
+		let block_numbers = 0..(super::BATCH_SIZE as u32 + 1);
+		let handle = db.cf_handle(BLOCK_HEADER_CF).unwrap();
+		for block_number in block_numbers.clone() {
+			let json = serde_json::to_vec(&header(block_number, H256::default())).unwrap();
+			db.put_cf(&handle, block_number.to_be_bytes(), json)
+				.unwrap();
+		}
+		// Migration is interrupted after the first batch is written, before version is stored
+		let mut batch = WriteBatch::default();
+		for block_number in 0..super::BATCH_SIZE as u32 {
+			let header = header(block_number, H256::default());
+			put_block_header(&db, &mut batch, block_number, &header).unwrap();
+		}
+		db.write(batch).unwrap();
+		assert_eq!(get_schema_version(db.clone()).unwrap(), Some(1));
+
+		migrate(db.clone()).unwrap();
+
+		for block_number in block_numbers {
+			let header = header(block_number, H256::default());
+			let stored = get_block_header_from_db(db.clone(), block_number).unwrap();
+			assert_eq!(stored.map(|header| header.encode()), Some(header.encode()));
+			let stored_number = get_block_number_from_db(db.clone(), header_hash(&header)).unwrap();
+			assert_eq!(stored_number, Some(block_number));
+		}
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_v2_to_v3() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 2).unwrap();
+		let sampled = |source, verified| SampledCell {
+			row: 0,
+			col: 0,
+			source,
+			verified,
+		};
+		let record = SamplingRecord {
+			cells: vec![
+				sampled(CellSource::Dht, true),
+				sampled(CellSource::Dht, false),
+				sampled(CellSource::Rpc, true),
+			],
+		};
+		let handle = db.cf_handle(SAMPLING_CF).unwrap();
+		db.put_cf(&handle, 5u32.to_be_bytes(), record.encode())
+			.unwrap();
+
+		migrate(db.clone()).unwrap();
+
+		assert_eq!(
+			get_confidence_counts_from_db(db.clone(), 5).unwrap(),
+			Some(ConfidenceCounts { dht: 1, rpc: 1 })
+		);
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_v3_to_v4() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 3).unwrap();
+		let validator = ed25519::Public::from_raw([1u8; 32]);
+		// Checkpoint without validator weights, encoded as number, set ID and validator set
+		let checkpoint = (10u32, 2u64, vec![validator]).encode();
+		let handle = db.cf_handle(STATE_CF).unwrap();
+		db.put_cf(&handle, FINALITY_SYNC_CHECKPOINT_KEY.as_bytes(), checkpoint)
+			.unwrap();
+
+		migrate(db.clone()).unwrap();
+
+		let checkpoint = get_finality_sync_checkpoint(db.clone()).unwrap().unwrap();
+		assert_eq!(checkpoint.number, 10);
+		assert_eq!(checkpoint.set_id, 2);
+		assert_eq!(checkpoint.validator_set, vec![(validator, 1)]);
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_v4_to_v5_interrupted() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 4).unwrap();
+		let handle = db.cf_handle(APP_DATA_CF).unwrap();
+		let data = |app_id: u32, block_number: u32| -> Vec<Vec<u8>> {
+			vec![vec![app_id as u8, block_number as u8]]
+		};
+		db.put_cf(&handle, "1:10".as_bytes(), data(1, 10).encode())
+			.unwrap();
+		// Migration is interrupted after app data of app 2 is re-keyed, before version is stored
+		db.put_cf(&handle, app_data_key(2, 10), data(2, 10).encode())
+			.unwrap();
+
+		migrate(db.clone()).unwrap();
+
+		for (app_id, block_number) in [(1u32, 10u32), (2, 10)] {
+			let stored: Option<Vec<Vec<u8>>> =
+				get_decoded_data_from_db(db.clone(), app_id, block_number).unwrap();
+			assert_eq!(stored, Some(data(app_id, block_number)));
+		}
+		assert_eq!(get_schema_version(db).unwrap(), Some(SCHEMA_VERSION));
+	}
+
+	#[test]
+	fn migrate_v4_to_v5() {
+		let (_dir, db) = temp_db();
+		store_schema_version(db.clone(), 4).unwrap();
+		let handle = db.cf_handle(APP_DATA_CF).unwrap();
+		for (app_id, block_number) in [(1u32, 9u32), (1, 10), (2, 10)] {
+			let data: Vec<Vec<u8>> = vec![vec![app_id as u8, block_number as u8]];
+			let key = format!("{app_id}:{block_number}");
+			db.put_cf(&handle, key.as_bytes(), data.encode()).unwrap();
+		}
+
+		migrate(db.clone()).unwrap();
+
+		for (app_id, block_number) in [(1u32, 9u32), (1, 10), (2, 10)] {
+			let data: Option<Vec<Vec<u8>>> =
+				get_decoded_data_from_db(db.clone(), app_id, block_number).unwrap();
+			assert_eq!(data, Some(vec![vec![app_id as u8, block_number as u8]]));
+		}
+		assert_eq!(
+			get_schema_version(db.clone()).unwrap(),
+			Some(SCHEMA_VERSION)
+		);
+
+		// Re-keyed app data is pruned by the block range
+		prune_app_data_in_db(db.clone(), 10).unwrap();
+		let data: Option<Vec<Vec<u8>>> = get_decoded_data_from_db(db.clone(), 1, 9).unwrap();
+		assert_eq!(data, None);
+		let data: Option<Vec<Vec<u8>>> = get_decoded_data_from_db(db, 2, 10).unwrap();
+		assert!(data.is_some());
+	}
+}
@@ -0,0 +1,1049 @@
+//! Persistence to RocksDB.
+
+mod mem_db;
+pub mod migrations;
+pub mod snapshot;
+
+pub use mem_db::MemoryDB;
+
+use anyhow::{anyhow, Context, Result};
+use avail_core::AppId;
+use avail_subxt::primitives::Header as DaHeader;
+use avail_subxt::utils::H256;
+use codec::{Decode, Encode};
+use kate_recovery::{com::AppData, data::Cell, matrix::Position};
+use rocksdb::{IteratorMode, WriteBatch, DB};
+use sp_core::blake2_256;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	sync::Arc,
+};
+
+use self::snapshot::Snapshot;
+use crate::{
+	consts::{
+		APP_DATA_CF, AVAILABILITY_FAILURE_CF, BLOCK_HASH_CF, BLOCK_HEADER_CF, CACHE_CF,
+		CONFIDENCE_COUNTS_CF, CONFIDENCE_FACTOR_CF, EQUIVOCATION_CF, JUSTIFICATION_CF, SAMPLING_CF,
+		STATE_CF,
+	},
+	types::{
+		AvailabilityFailure, ConfidenceCounts, Equivocation, FinalityJustification,
+		FinalitySyncCheckpoint, Pruned, SamplingRecord,
+	},
+};
+
+const LAST_FULL_NODE_WS_KEY: &str = "last_full_node_ws";
+const GENESIS_HASH_KEY: &str = "genesis_hash";
+const FINALITY_SYNC_CHECKPOINT_KEY: &str = "finality_sync_checkpoint";
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+fn pruned_below_key(pruned: Pruned) -> &'static str {
+	match pruned {
+		Pruned::Headers => "header_pruned_below",
+		Pruned::Confidence => "confidence_pruned_below",
+		Pruned::AppData => "data_pruned_below",
+		Pruned::Cache => "cache_pruned_below",
+	}
+}
+
+pub fn store_last_full_node_ws_in_db(db: Arc<DB>, last_full_node_ws: String) -> Result<()> {
+	let cf_handle = db.cf_handle(STATE_CF).context("Failed to get cf handle")?;
+
+	db.put_cf(
+		&cf_handle,
+		LAST_FULL_NODE_WS_KEY.as_bytes(),
+		last_full_node_ws,
+	)
+	.context("Failed to write last full node ws to db")
+}
+
+pub fn get_last_full_node_ws_from_db(db: Arc<DB>) -> Result<Option<String>> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	let result = db
+		.get_cf(&cf_handle, LAST_FULL_NODE_WS_KEY.as_bytes())
+		.context("Couldn't get last full node ws from db")?;
+
+	let Some(last_full_node_ws) = result else {
+		return Ok(None);
+	};
+
+	Ok(std::str::from_utf8(&last_full_node_ws)
+		.map(String::from)
+		.map(Some)?)
+}
+
+/// App data keys are prefixed with the block number, so app data can be pruned by the block range
+pub(crate) fn app_data_key(app_id: u32, block_number: u32) -> Vec<u8> {
+	let mut key = block_number.to_be_bytes().to_vec();
+	key.extend(app_id.to_be_bytes());
+	key
+}
+
+fn store_data_in_db(db: Arc<DB>, app_id: AppId, block_number: u32, data: &[u8]) -> Result<()> {
+	let cf_handle = db
+		.cf_handle(APP_DATA_CF)
+		.context("Failed to get cf handle")?;
+
+	db.put_cf(&cf_handle, app_data_key(app_id.0, block_number), data)
+		.context("Failed to write application data")
+}
+
+fn get_data_from_db(db: Arc<DB>, app_id: u32, block_number: u32) -> Result<Option<Vec<u8>>> {
+	let cf_handle = db
+		.cf_handle(APP_DATA_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.get_cf(&cf_handle, app_data_key(app_id, block_number))
+		.context("Couldn't get app_data from db")
+}
+
+/// Encodes and stores app data into database under the block number and app ID key
+pub fn store_encoded_data_in_db<T: Encode>(
+	db: Arc<DB>,
+	app_id: AppId,
+	block_number: u32,
+	data: &T,
+) -> Result<()> {
+	store_data_in_db(db, app_id, block_number, &data.encode())
+}
+
+/// Gets and decodes app data from database for the block number and app ID key
+pub fn get_decoded_data_from_db<T: Decode>(
+	db: Arc<DB>,
+	app_id: u32,
+	block_number: u32,
+) -> Result<Option<T>> {
+	let res = get_data_from_db(db, app_id, block_number)
+		.map(|e| e.map(|v| <T>::decode(&mut &v[..]).context("Failed decoding the app data.")));
+
+	match res {
+		Ok(Some(Err(e))) => Err(e),
+		Ok(Some(Ok(s))) => Ok(Some(s)),
+		Ok(None) => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+/// Gets the block header from database
+pub fn get_block_header_from_db(db: Arc<DB>, block_number: u32) -> Result<Option<DaHeader>> {
+	let handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get block header")?
+		.map(|value| DaHeader::decode(&mut &value[..]).context("Failed to decode header"))
+		.transpose()
+}
+
+/// Gets the block number for given block hash from database
+pub fn get_block_number_from_db(db: Arc<DB>, block_hash: H256) -> Result<Option<u32>> {
+	let handle = db
+		.cf_handle(BLOCK_HASH_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_hash.as_bytes())
+		.context("Failed to get block number")?
+		.map(|data| {
+			data.try_into()
+				.map_err(|_| anyhow!("Conversion failed"))
+				.context("Unable to convert block number (wrong number of bytes)")
+				.map(u32::from_be_bytes)
+		})
+		.transpose()
+}
+
+/// Checks if block header for given block number is in database
+pub fn is_block_header_in_db(db: Arc<DB>, block_number: u32) -> Result<bool> {
+	let handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_pinned_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get block header")
+		.map(|value| value.is_some())
+}
+
+pub(crate) fn put_block_header(
+	db: &DB,
+	batch: &mut WriteBatch,
+	block_number: u32,
+	header: &DaHeader,
+) -> Result<()> {
+	let header_handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+	let hash_handle = db
+		.cf_handle(BLOCK_HASH_CF)
+		.context("Failed to get cf handle")?;
+
+	let encoded_header = header.encode();
+	let block_hash = blake2_256(&encoded_header);
+	batch.put_cf(&header_handle, block_number.to_be_bytes(), encoded_header);
+	batch.put_cf(&hash_handle, block_hash, block_number.to_be_bytes());
+	Ok(())
+}
+
+/// Stores SCALE encoded block header into database under the given block number key,
+/// and indexes block number by the block hash
+pub fn store_block_header_in_db(db: Arc<DB>, block_number: u32, header: &DaHeader) -> Result<()> {
+	let mut batch = WriteBatch::default();
+	put_block_header(&db, &mut batch, block_number, header)?;
+	db.write(batch).context("Failed to write block header")
+}
+
+/// Gets the GRANDPA justification of the block from database
+pub fn get_justification_from_db(
+	db: Arc<DB>,
+	block_number: u32,
+) -> Result<Option<FinalityJustification>> {
+	let handle = db
+		.cf_handle(JUSTIFICATION_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get justification")?
+		.map(|value| FinalityJustification::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode justification")
+}
+
+/// Stores SCALE encoded GRANDPA justification into database under the given block number key
+pub fn store_justification_in_db(
+	db: Arc<DB>,
+	block_number: u32,
+	justification: &FinalityJustification,
+) -> Result<()> {
+	let handle = db
+		.cf_handle(JUSTIFICATION_CF)
+		.context("Failed to get cf handle")?;
+
+	db.put_cf(&handle, block_number.to_be_bytes(), justification.encode())
+		.context("Failed to write justification")
+}
+
+/// Gets equivocations detected in the GRANDPA justification of the block from database
+pub fn get_equivocations_from_db(db: Arc<DB>, block_number: u32) -> Result<Vec<Equivocation>> {
+	let handle = db
+		.cf_handle(EQUIVOCATION_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get equivocations")?
+		.map(|value| Vec::<Equivocation>::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode equivocations")
+		.map(Option::unwrap_or_default)
+}
+
+/// Stores SCALE encoded equivocations into database under the given block number key
+pub fn store_equivocations_in_db(
+	db: Arc<DB>,
+	block_number: u32,
+	equivocations: &[Equivocation],
+) -> Result<()> {
+	let handle = db
+		.cf_handle(EQUIVOCATION_CF)
+		.context("Failed to get cf handle")?;
+
+	db.put_cf(&handle, block_number.to_be_bytes(), equivocations.encode())
+		.context("Failed to write equivocations")
+}
+
+/// Stores all block writes into database in a single write batch
+pub fn store_block_in_db(db: Arc<DB>, block_number: u32, block: BlockBatch) -> Result<()> {
+	let mut batch = WriteBatch::default();
+
+	if let Some(header) = &block.header {
+		put_block_header(&db, &mut batch, block_number, header)?;
+	}
+
+	if let Some(count) = block.confidence {
+		let handle = db
+			.cf_handle(CONFIDENCE_FACTOR_CF)
+			.context("Failed to get cf handle")?;
+		batch.put_cf(&handle, block_number.to_be_bytes(), count.to_be_bytes());
+	}
+
+	if let Some(counts) = &block.confidence_counts {
+		let handle = db
+			.cf_handle(CONFIDENCE_COUNTS_CF)
+			.context("Failed to get cf handle")?;
+		batch.put_cf(&handle, block_number.to_be_bytes(), counts.encode());
+	}
+
+	if let Some(failure) = &block.failure {
+		let handle = db
+			.cf_handle(AVAILABILITY_FAILURE_CF)
+			.context("Failed to get cf handle")?;
+		batch.put_cf(&handle, block_number.to_be_bytes(), failure.encode());
+	}
+
+	if let Some(sampling) = &block.sampling {
+		let handle = db
+			.cf_handle(SAMPLING_CF)
+			.context("Failed to get cf handle")?;
+		batch.put_cf(&handle, block_number.to_be_bytes(), sampling.encode());
+	}
+
+	if !block.cells.is_empty() || !block.rows.is_empty() {
+		let handle = db.cf_handle(CACHE_CF).context("Failed to get cf handle")?;
+		for cell in &block.cells {
+			batch.put_cf(
+				&handle,
+				cell_cache_key(block_number, &cell.position),
+				cell.content,
+			);
+		}
+		for (row_index, row) in &block.rows {
+			batch.put_cf(&handle, row_cache_key(block_number, *row_index), row);
+		}
+	}
+
+	if !block.app_data.is_empty() {
+		let handle = db
+			.cf_handle(APP_DATA_CF)
+			.context("Failed to get cf handle")?;
+		for (app_id, data) in &block.app_data {
+			batch.put_cf(&handle, app_data_key(*app_id, block_number), data.encode());
+		}
+	}
+
+	db.write(batch)
+		.context(format!("Failed to write block {block_number}"))
+}
+
+/// Stores all snapshot writes into database in a single write batch
+pub fn store_snapshot_in_db(db: Arc<DB>, snapshot: Snapshot) -> Result<()> {
+	let mut batch = WriteBatch::default();
+
+	for header in &snapshot.headers {
+		put_block_header(&db, &mut batch, header.number, header)?;
+	}
+
+	let handle = db
+		.cf_handle(CONFIDENCE_FACTOR_CF)
+		.context("Failed to get cf handle")?;
+	for (block_number, count) in snapshot.confidences {
+		batch.put_cf(&handle, block_number.to_be_bytes(), count.to_be_bytes());
+	}
+
+	let handle = db.cf_handle(STATE_CF).context("Failed to get cf handle")?;
+	if let Some(checkpoint) = snapshot.finality_sync_checkpoint {
+		batch.put_cf(
+			&handle,
+			FINALITY_SYNC_CHECKPOINT_KEY.as_bytes(),
+			checkpoint.encode(),
+		);
+	}
+	batch.put_cf(
+		&handle,
+		GENESIS_HASH_KEY.as_bytes(),
+		snapshot.genesis_hash.as_bytes(),
+	);
+
+	db.write(batch).context("Failed to write snapshot")
+}
+
+const CELL_CACHE_PREFIX: u8 = 0;
+const ROW_CACHE_PREFIX: u8 = 1;
+
+/// Cache keys are prefixed with the block number, so cache can be pruned by the block range
+pub(crate) fn cell_cache_key(block_number: u32, position: &Position) -> Vec<u8> {
+	let mut key = block_number.to_be_bytes().to_vec();
+	key.push(CELL_CACHE_PREFIX);
+	key.extend(position.row.to_be_bytes());
+	key.extend(position.col.to_be_bytes());
+	key
+}
+
+pub(crate) fn row_cache_key(block_number: u32, row_index: u32) -> Vec<u8> {
+	let mut key = block_number.to_be_bytes().to_vec();
+	key.push(ROW_CACHE_PREFIX);
+	key.extend(row_index.to_be_bytes());
+	key
+}
+
+/// Gets cached cells of the block from database, skipping cells which are not cached
+pub fn get_cached_cells_from_db(
+	db: Arc<DB>,
+	block_number: u32,
+	positions: &[Position],
+) -> Result<Vec<Cell>> {
+	let handle = db.cf_handle(CACHE_CF).context("Failed to get cf handle")?;
+
+	let keys = positions
+		.iter()
+		.map(|position| (&handle, cell_cache_key(block_number, position)));
+
+	db.multi_get_cf(keys)
+		.into_iter()
+		.zip(positions)
+		.filter_map(|(value, position)| match value {
+			Ok(Some(content)) => Some(
+				content
+					.try_into()
+					.map(|content| Cell {
+						position: *position,
+						content,
+					})
+					.map_err(|_| anyhow!("Invalid cached cell content")),
+			),
+			Ok(None) => None,
+			Err(error) => Some(Err(error).context("Failed to get cached cell")),
+		})
+		.collect()
+}
+
+/// Gets cached rows of the block from database, with `None` for rows which are not cached
+pub fn get_cached_rows_from_db(
+	db: Arc<DB>,
+	block_number: u32,
+	row_indexes: &[u32],
+) -> Result<Vec<Option<Vec<u8>>>> {
+	let handle = db.cf_handle(CACHE_CF).context("Failed to get cf handle")?;
+
+	let keys = row_indexes
+		.iter()
+		.map(|&row_index| (&handle, row_cache_key(block_number, row_index)));
+
+	db.multi_get_cf(keys)
+		.into_iter()
+		.map(|value| value.context("Failed to get cached row"))
+		.collect()
+}
+
+/// Gets the sampling record of the block from database
+pub fn get_sampling_from_db(db: Arc<DB>, block_number: u32) -> Result<Option<SamplingRecord>> {
+	let handle = db
+		.cf_handle(SAMPLING_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get sampling record")?
+		.map(|value| SamplingRecord::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode sampling record")
+}
+
+/// Gets the number of verified cells per cell source from database
+pub fn get_confidence_counts_from_db(
+	db: Arc<DB>,
+	block_number: u32,
+) -> Result<Option<ConfidenceCounts>> {
+	let handle = db
+		.cf_handle(CONFIDENCE_COUNTS_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get confidence counts")?
+		.map(|value| ConfidenceCounts::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode confidence counts")
+}
+
+/// Gets failed data availability verification of the block from database
+pub fn get_availability_failure_from_db(
+	db: Arc<DB>,
+	block_number: u32,
+) -> Result<Option<AvailabilityFailure>> {
+	let handle = db
+		.cf_handle(AVAILABILITY_FAILURE_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get availability failure")?
+		.map(|value| AvailabilityFailure::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode availability failure")
+}
+
+/// Gets numbers of all blocks with failed data availability verification from database
+pub fn get_availability_failed_blocks_from_db(db: Arc<DB>) -> Result<BTreeSet<u32>> {
+	let handle = db
+		.cf_handle(AVAILABILITY_FAILURE_CF)
+		.context("Failed to get cf handle")?;
+
+	db.iterator_cf(&handle, IteratorMode::Start)
+		.map(|item| {
+			let (key, _) = item.context("Failed to iterate over availability failures")?;
+			<[u8; 4]>::try_from(&key[..])
+				.map(u32::from_be_bytes)
+				.context("Failed to decode block number")
+		})
+		.collect()
+}
+
+/// Checks if confidence factor for given block number is in database
+pub fn is_confidence_in_db(db: Arc<DB>, block_number: u32) -> Result<bool> {
+	let handle = db
+		.cf_handle(CONFIDENCE_FACTOR_CF)
+		.context("Failed to get cf handle")?;
+
+	db.get_pinned_cf(&handle, block_number.to_be_bytes())
+		.context("Failed to get confidence")
+		.map(|value| value.is_some())
+}
+
+fn get_last_block_from_db(db: Arc<DB>, cf: &str) -> Result<Option<u32>> {
+	let handle = db.cf_handle(cf).context("Failed to get cf handle")?;
+
+	db.iterator_cf(&handle, IteratorMode::End)
+		.next()
+		.transpose()
+		.context("Failed to iterate over blocks")?
+		.map(|(key, _)| {
+			<[u8; 4]>::try_from(&key[..])
+				.map(u32::from_be_bytes)
+				.context("Failed to decode block number")
+		})
+		.transpose()
+}
+
+/// Gets the highest block number with either confidence factor or availability failure in database
+pub fn get_last_processed_block_from_db(db: Arc<DB>) -> Result<Option<u32>> {
+	let last_confidence_block = get_last_block_from_db(db.clone(), CONFIDENCE_FACTOR_CF)?;
+	let last_failed_block = get_last_block_from_db(db, AVAILABILITY_FAILURE_CF)?;
+	Ok(last_confidence_block.max(last_failed_block))
+}
+
+fn delete_blocks_below(db: Arc<DB>, cf: &str, block_number: u32) -> Result<()> {
+	let handle = db.cf_handle(cf).context("Failed to get cf handle")?;
+
+	db.delete_range_cf(&handle, 0u32.to_be_bytes(), block_number.to_be_bytes())
+		.context("Failed to delete blocks range")
+}
+
+/// Deletes block headers, their hash index entries, justifications and equivocations
+/// for all blocks below the given block number
+pub fn prune_block_headers_in_db(db: Arc<DB>, block_number: u32) -> Result<()> {
+	let header_handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+	let hash_handle = db
+		.cf_handle(BLOCK_HASH_CF)
+		.context("Failed to get cf handle")?;
+
+	// Hash index keys are hashes of the stored encoded headers, so only pruned headers are iterated
+	let mut batch = WriteBatch::default();
+	for item in db.iterator_cf(&header_handle, IteratorMode::Start) {
+		let (key, value) = item.context("Failed to iterate over block headers")?;
+		let key_block_number = <[u8; 4]>::try_from(&key[..])
+			.map(u32::from_be_bytes)
+			.context("Failed to decode block number")?;
+		if key_block_number >= block_number {
+			break;
+		}
+		batch.delete_cf(&hash_handle, blake2_256(&value));
+	}
+	db.write(batch).context("Failed to delete block hashes")?;
+
+	delete_blocks_below(db.clone(), JUSTIFICATION_CF, block_number)?;
+	delete_blocks_below(db.clone(), EQUIVOCATION_CF, block_number)?;
+	delete_blocks_below(db, BLOCK_HEADER_CF, block_number)
+}
+
+/// Deletes confidence factors, confidence counts, sampling records and availability failures
+/// for all blocks below the given block number
+pub fn prune_confidence_in_db(db: Arc<DB>, block_number: u32) -> Result<()> {
+	delete_blocks_below(db.clone(), SAMPLING_CF, block_number)?;
+	delete_blocks_below(db.clone(), AVAILABILITY_FAILURE_CF, block_number)?;
+	delete_blocks_below(db.clone(), CONFIDENCE_COUNTS_CF, block_number)?;
+	delete_blocks_below(db, CONFIDENCE_FACTOR_CF, block_number)
+}
+
+/// Gets total size in bytes of cached cells and rows (including keys) per block
+pub fn get_cache_sizes_from_db(db: Arc<DB>) -> Result<BTreeMap<u32, u64>> {
+	let handle = db.cf_handle(CACHE_CF).context("Failed to get cf handle")?;
+
+	let mut sizes = BTreeMap::new();
+	for item in db.iterator_cf(&handle, IteratorMode::Start) {
+		let (key, value) = item.context("Failed to iterate over cache")?;
+		let block_number = key
+			.get(..4)
+			.and_then(|prefix| <[u8; 4]>::try_from(prefix).ok())
+			.map(u32::from_be_bytes)
+			.context("Failed to decode block number")?;
+		*sizes.entry(block_number).or_default() += (key.len() + value.len()) as u64;
+	}
+	Ok(sizes)
+}
+
+/// Deletes cached cells and rows for all blocks below the given block number
+pub fn prune_cache_in_db(db: Arc<DB>, block_number: u32) -> Result<()> {
+	delete_blocks_below(db, CACHE_CF, block_number)
+}
+
+/// Deletes app data of all applications for blocks below the given block number
+pub fn prune_app_data_in_db(db: Arc<DB>, block_number: u32) -> Result<()> {
+	delete_blocks_below(db, APP_DATA_CF, block_number)
+}
+
+/// Writes of a single block, committed to the database atomically.
+///
+/// Header, confidence, sampling record and failure are written together by the light client or
+/// the sync client. App data and rows are fetched by the application client only after the confidence
+/// is achieved, and are written together in a separate batch. Block with stored confidence can therefore
+/// lack app data after a crash, in which case app data of the block is reported as not available.
+#[derive(Clone, Debug, Default)]
+pub struct BlockBatch {
+	pub header: Option<DaHeader>,
+	/// Number of verified cells
+	pub confidence: Option<u32>,
+	/// Number of verified cells per cell source
+	pub confidence_counts: Option<ConfidenceCounts>,
+	/// Sampled cells and their verification outcome
+	pub sampling: Option<SamplingRecord>,
+	/// Failed data availability verification
+	pub failure: Option<AvailabilityFailure>,
+	/// Verified cells to cache
+	pub cells: Vec<Cell>,
+	/// Verified rows to cache, with their row indexes
+	pub rows: Vec<(u32, Vec<u8>)>,
+	/// Application data per application ID
+	pub app_data: Vec<(u32, AppData)>,
+}
+
+/// Storage of the light client data
+pub trait Database: Clone + Send + Sync + 'static {
+	fn get_confidence(&self, block_number: u32) -> Result<Option<u32>>;
+	fn store_confidence(&self, block_number: u32, count: u32) -> Result<()>;
+	fn is_confidence_stored(&self, block_number: u32) -> Result<bool>;
+	/// Gets the highest block number with stored confidence or availability failure,
+	/// i.e. the last processed block
+	fn get_last_processed_block(&self) -> Result<Option<u32>>;
+	fn prune_confidence(&self, below_block_number: u32) -> Result<()>;
+	/// Gets number of verified cells per cell source, not stored for blocks without sampling record
+	fn get_confidence_counts(&self, block_number: u32) -> Result<Option<ConfidenceCounts>>;
+	fn get_sampling(&self, block_number: u32) -> Result<Option<SamplingRecord>>;
+	/// Gets failed data availability verification, stored only for blocks which failed it
+	fn get_availability_failure(&self, block_number: u32) -> Result<Option<AvailabilityFailure>>;
+	fn get_availability_failed_blocks(&self) -> Result<BTreeSet<u32>>;
+	fn get_header(&self, block_number: u32) -> Result<Option<DaHeader>>;
+	fn store_header(&self, block_number: u32, header: &DaHeader) -> Result<()>;
+	fn is_header_stored(&self, block_number: u32) -> Result<bool>;
+	fn get_block_number(&self, block_hash: H256) -> Result<Option<u32>>;
+	fn get_header_by_hash(&self, block_hash: H256) -> Result<Option<DaHeader>> {
+		self.get_block_number(block_hash)?
+			.map(|block_number| self.get_header(block_number))
+			.transpose()
+			.map(Option::flatten)
+	}
+	fn prune_headers(&self, below_block_number: u32) -> Result<()>;
+	fn get_justification(&self, block_number: u32) -> Result<Option<FinalityJustification>>;
+	fn store_justification(
+		&self,
+		block_number: u32,
+		justification: &FinalityJustification,
+	) -> Result<()>;
+	/// Gets equivocations detected in the justification of the block, empty if none is detected
+	fn get_equivocations(&self, block_number: u32) -> Result<Vec<Equivocation>>;
+	fn store_equivocations(&self, block_number: u32, equivocations: &[Equivocation]) -> Result<()>;
+	fn get_data(&self, app_id: u32, block_number: u32) -> Result<Option<AppData>>;
+	fn store_data(&self, app_id: u32, block_number: u32, data: &AppData) -> Result<()>;
+	fn prune_data(&self, below_block_number: u32) -> Result<()>;
+	/// Gets cached cells on given positions, skipping cells which are not cached
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Result<Vec<Cell>>;
+	/// Gets cached rows with given indexes, with `None` for rows which are not cached
+	fn get_cached_rows(
+		&self,
+		block_number: u32,
+		row_indexes: &[u32],
+	) -> Result<Vec<Option<Vec<u8>>>>;
+	/// Gets total size in bytes of cached cells and rows per block
+	fn get_cache_sizes(&self) -> Result<BTreeMap<u32, u64>>;
+	fn prune_cache(&self, below_block_number: u32) -> Result<()>;
+	/// Stores all block writes atomically, so block is never partially stored
+	fn store_block(&self, block_number: u32, block: BlockBatch) -> Result<()>;
+	/// Stores all snapshot writes atomically, so snapshot is never partially imported
+	fn store_snapshot(&self, snapshot: Snapshot) -> Result<()>;
+	fn get_last_full_node_ws(&self) -> Result<Option<String>>;
+	fn store_last_full_node_ws(&self, last_full_node_ws: String) -> Result<()>;
+	fn get_genesis_hash(&self) -> Result<Option<H256>>;
+	fn store_genesis_hash(&self, genesis_hash: H256) -> Result<()>;
+	fn get_finality_sync_checkpoint(&self) -> Result<Option<FinalitySyncCheckpoint>>;
+	fn store_finality_sync_checkpoint(&self, checkpoint: FinalitySyncCheckpoint) -> Result<()>;
+	/// Gets block number below which given block data is pruned, so pruned ranges survive restarts
+	fn get_pruned_below(&self, pruned: Pruned) -> Result<Option<u32>>;
+	fn store_pruned_below(&self, pruned: Pruned, block_number: u32) -> Result<()>;
+}
+
+#[derive(Clone)]
+pub struct RocksDB(pub Arc<DB>);
+
+impl Database for RocksDB {
+	fn get_confidence(&self, block_number: u32) -> Result<Option<u32>> {
+		get_confidence_from_db(self.0.clone(), block_number)
+	}
+
+	fn store_confidence(&self, block_number: u32, count: u32) -> Result<()> {
+		store_confidence_in_db(self.0.clone(), block_number, count)
+	}
+
+	fn is_confidence_stored(&self, block_number: u32) -> Result<bool> {
+		is_confidence_in_db(self.0.clone(), block_number)
+	}
+
+	fn get_last_processed_block(&self) -> Result<Option<u32>> {
+		get_last_processed_block_from_db(self.0.clone())
+	}
+
+	fn prune_confidence(&self, below_block_number: u32) -> Result<()> {
+		prune_confidence_in_db(self.0.clone(), below_block_number)
+	}
+
+	fn get_confidence_counts(&self, block_number: u32) -> Result<Option<ConfidenceCounts>> {
+		get_confidence_counts_from_db(self.0.clone(), block_number)
+	}
+
+	fn get_sampling(&self, block_number: u32) -> Result<Option<SamplingRecord>> {
+		get_sampling_from_db(self.0.clone(), block_number)
+	}
+
+	fn get_availability_failure(&self, block_number: u32) -> Result<Option<AvailabilityFailure>> {
+		get_availability_failure_from_db(self.0.clone(), block_number)
+	}
+
+	fn get_availability_failed_blocks(&self) -> Result<BTreeSet<u32>> {
+		get_availability_failed_blocks_from_db(self.0.clone())
+	}
+
+	fn get_header(&self, block_number: u32) -> Result<Option<DaHeader>> {
+		get_block_header_from_db(self.0.clone(), block_number)
+	}
+
+	fn store_header(&self, block_number: u32, header: &DaHeader) -> Result<()> {
+		store_block_header_in_db(self.0.clone(), block_number, header)
+	}
+
+	fn is_header_stored(&self, block_number: u32) -> Result<bool> {
+		is_block_header_in_db(self.0.clone(), block_number)
+	}
+
+	fn get_block_number(&self, block_hash: H256) -> Result<Option<u32>> {
+		get_block_number_from_db(self.0.clone(), block_hash)
+	}
+
+	fn prune_headers(&self, below_block_number: u32) -> Result<()> {
+		prune_block_headers_in_db(self.0.clone(), below_block_number)
+	}
+
+	fn get_justification(&self, block_number: u32) -> Result<Option<FinalityJustification>> {
+		get_justification_from_db(self.0.clone(), block_number)
+	}
+
+	fn store_justification(
+		&self,
+		block_number: u32,
+		justification: &FinalityJustification,
+	) -> Result<()> {
+		store_justification_in_db(self.0.clone(), block_number, justification)
+	}
+
+	fn get_equivocations(&self, block_number: u32) -> Result<Vec<Equivocation>> {
+		get_equivocations_from_db(self.0.clone(), block_number)
+	}
+
+	fn store_equivocations(&self, block_number: u32, equivocations: &[Equivocation]) -> Result<()> {
+		store_equivocations_in_db(self.0.clone(), block_number, equivocations)
+	}
+
+	fn get_data(&self, app_id: u32, block_number: u32) -> Result<Option<AppData>> {
+		get_decoded_data_from_db(self.0.clone(), app_id, block_number)
+	}
+
+	fn store_data(&self, app_id: u32, block_number: u32, data: &AppData) -> Result<()> {
+		store_encoded_data_in_db(self.0.clone(), AppId(app_id), block_number, data)
+	}
+
+	fn prune_data(&self, below_block_number: u32) -> Result<()> {
+		prune_app_data_in_db(self.0.clone(), below_block_number)
+	}
+
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Result<Vec<Cell>> {
+		get_cached_cells_from_db(self.0.clone(), block_number, positions)
+	}
+
+	fn get_cached_rows(
+		&self,
+		block_number: u32,
+		row_indexes: &[u32],
+	) -> Result<Vec<Option<Vec<u8>>>> {
+		get_cached_rows_from_db(self.0.clone(), block_number, row_indexes)
+	}
+
+	fn get_cache_sizes(&self) -> Result<BTreeMap<u32, u64>> {
+		get_cache_sizes_from_db(self.0.clone())
+	}
+
+	fn prune_cache(&self, below_block_number: u32) -> Result<()> {
+		prune_cache_in_db(self.0.clone(), below_block_number)
+	}
+
+	fn store_block(&self, block_number: u32, block: BlockBatch) -> Result<()> {
+		store_block_in_db(self.0.clone(), block_number, block)
+	}
+
+	fn store_snapshot(&self, snapshot: Snapshot) -> Result<()> {
+		store_snapshot_in_db(self.0.clone(), snapshot)
+	}
+
+	fn get_last_full_node_ws(&self) -> Result<Option<String>> {
+		get_last_full_node_ws_from_db(self.0.clone())
+	}
+
+	fn store_last_full_node_ws(&self, last_full_node_ws: String) -> Result<()> {
+		store_last_full_node_ws_in_db(self.0.clone(), last_full_node_ws)
+	}
+
+	fn get_genesis_hash(&self) -> Result<Option<H256>> {
+		get_genesis_hash(self.0.clone())
+	}
+
+	fn store_genesis_hash(&self, genesis_hash: H256) -> Result<()> {
+		store_genesis_hash(self.0.clone(), genesis_hash)
+	}
+
+	fn get_finality_sync_checkpoint(&self) -> Result<Option<FinalitySyncCheckpoint>> {
+		get_finality_sync_checkpoint(self.0.clone())
+	}
+
+	fn store_finality_sync_checkpoint(&self, checkpoint: FinalitySyncCheckpoint) -> Result<()> {
+		store_finality_sync_checkpoint(self.0.clone(), checkpoint)
+	}
+
+	fn get_pruned_below(&self, pruned: Pruned) -> Result<Option<u32>> {
+		get_pruned_below_from_db(self.0.clone(), pruned)
+	}
+
+	fn store_pruned_below(&self, pruned: Pruned, block_number: u32) -> Result<()> {
+		store_pruned_below_in_db(self.0.clone(), pruned, block_number)
+	}
+}
+
+/// Gets confidence factor from database for given block number
+pub fn get_confidence_from_db(db: Arc<DB>, block_number: u32) -> Result<Option<u32>> {
+	let cf_handle = db
+		.cf_handle(crate::consts::CONFIDENCE_FACTOR_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.get_cf(&cf_handle, block_number.to_be_bytes())
+		.context("Couldn't get confidence in db")?
+		.map(|data| {
+			data.try_into()
+				.map_err(|_| anyhow!("Conversion failed"))
+				.context("Unable to convert confindence (wrong number of bytes)")
+				.map(u32::from_be_bytes)
+		})
+		.transpose()
+}
+
+/// Stores confidence factor into database under the given block number key
+pub fn store_confidence_in_db(db: Arc<DB>, block_number: u32, count: u32) -> Result<()> {
+	let handle = db
+		.cf_handle(CONFIDENCE_FACTOR_CF)
+		.context("Failed to get cf handle")?;
+
+	db.put_cf(&handle, block_number.to_be_bytes(), count.to_be_bytes())
+		.context("Failed to write confidence")
+}
+
+pub fn get_genesis_hash(db: Arc<DB>) -> Result<Option<H256>> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	let result = db
+		.get_cf(&cf_handle, GENESIS_HASH_KEY.as_bytes())
+		.context("Couldn't get genesis hash from db")?;
+
+	result.map_or(Ok(None), |e| {
+		let raw_hash: std::result::Result<[u8; 32], _> = e.try_into();
+		raw_hash
+			.map(|e| Some(H256::from(e)))
+			.map_err(|_| anyhow!("Bad genesis hash format!"))
+	})
+}
+
+pub fn store_genesis_hash(db: Arc<DB>, genesis_hash: H256) -> Result<()> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+	db.put_cf(
+		&cf_handle,
+		GENESIS_HASH_KEY.as_bytes(),
+		genesis_hash.as_bytes(),
+	)
+	.context("Failed to write genesis hash to db")
+}
+
+/// Gets database schema version, if stored
+pub fn get_schema_version(db: Arc<DB>) -> Result<Option<u32>> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.get_cf(&cf_handle, SCHEMA_VERSION_KEY.as_bytes())
+		.context("Couldn't get schema version from db")?
+		.map(|data| {
+			data.try_into()
+				.map_err(|_| anyhow!("Conversion failed"))
+				.context("Unable to convert schema version (wrong number of bytes)")
+				.map(u32::from_be_bytes)
+		})
+		.transpose()
+}
+
+/// Stores database schema version
+pub fn store_schema_version(db: Arc<DB>, version: u32) -> Result<()> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.put_cf(
+		&cf_handle,
+		SCHEMA_VERSION_KEY.as_bytes(),
+		version.to_be_bytes(),
+	)
+	.context("Failed to write schema version to db")
+}
+
+pub fn get_finality_sync_checkpoint(db: Arc<DB>) -> Result<Option<FinalitySyncCheckpoint>> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	let result = db
+		.get_cf(&cf_handle, FINALITY_SYNC_CHECKPOINT_KEY.as_bytes())
+		.context("Couldn't get finality sync checkpoint from db")?;
+
+	result.map_or(Ok(None), |e| {
+		FinalitySyncCheckpoint::decode(&mut &e[..])
+			.context("Failed to decoded finality sync checkpoint")
+			.map(Some)
+	})
+}
+
+pub fn store_finality_sync_checkpoint(
+	db: Arc<DB>,
+	checkpoint: FinalitySyncCheckpoint,
+) -> Result<()> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+	db.put_cf(
+		&cf_handle,
+		FINALITY_SYNC_CHECKPOINT_KEY.as_bytes(),
+		checkpoint.encode().as_slice(),
+	)
+	.context("Failed to write finality sync checkpoint data")
+}
+
+/// Gets block number below which given block data is pruned from database
+pub fn get_pruned_below_from_db(db: Arc<DB>, pruned: Pruned) -> Result<Option<u32>> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.get_cf(&cf_handle, pruned_below_key(pruned).as_bytes())
+		.context("Couldn't get pruned block number from db")?
+		.map(|data| {
+			data.try_into()
+				.map_err(|_| anyhow!("Conversion failed"))
+				.context("Unable to convert pruned block number (wrong number of bytes)")
+				.map(u32::from_be_bytes)
+		})
+		.transpose()
+}
+
+/// Stores block number below which given block data is pruned into database
+pub fn store_pruned_below_in_db(db: Arc<DB>, pruned: Pruned, block_number: u32) -> Result<()> {
+	let cf_handle = db
+		.cf_handle(STATE_CF)
+		.context("Couldn't get column handle from db")?;
+
+	db.put_cf(
+		&cf_handle,
+		pruned_below_key(pruned).as_bytes(),
+		block_number.to_be_bytes(),
+	)
+	.context("Failed to write pruned block number to db")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::{
+		get_block_header_from_db, get_block_number_from_db, prune_block_headers_in_db,
+		store_block_header_in_db,
+	};
+	use crate::{
+		consts::{
+			APP_DATA_CF, AVAILABILITY_FAILURE_CF, BLOCK_HASH_CF, BLOCK_HEADER_CF, CACHE_CF,
+			CONFIDENCE_COUNTS_CF, CONFIDENCE_FACTOR_CF, EQUIVOCATION_CF, JUSTIFICATION_CF,
+			SAMPLING_CF, STATE_CF,
+		},
+		test_utils::{header, header_hash},
+	};
+	use rocksdb::{Options, DB};
+	use sp_core::H256;
+	use std::sync::Arc;
+	use tempfile::TempDir;
+
+	/// Opens database with all column families in a temporary directory,
+	/// which is removed when returned directory is dropped
+	pub(crate) fn temp_db() -> (TempDir, Arc<DB>) {
+		let dir = tempfile::tempdir().unwrap();
+		let mut options = Options::default();
+		options.create_if_missing(true);
+		options.create_missing_column_families(true);
+		let cfs = [
+			CONFIDENCE_FACTOR_CF,
+			CONFIDENCE_COUNTS_CF,
+			AVAILABILITY_FAILURE_CF,
+			BLOCK_HEADER_CF,
+			BLOCK_HASH_CF,
+			JUSTIFICATION_CF,
+			EQUIVOCATION_CF,
+			APP_DATA_CF,
+			SAMPLING_CF,
+			CACHE_CF,
+			STATE_CF,
+		];
+		let db = DB::open_cf(&options, dir.path(), cfs).unwrap();
+		(dir, Arc::new(db))
+	}
+
+	#[test]
+	fn prune_block_headers() {
+		let (_dir, db) = temp_db();
+		let header = |number| header(number, H256::default());
+		let hash = |number| header_hash(&header(number));
+		for number in 1..=5 {
+			store_block_header_in_db(db.clone(), number, &header(number)).unwrap();
+		}
+
+		prune_block_headers_in_db(db.clone(), 4).unwrap();
+
+		for number in 1..=3 {
+			assert!(get_block_header_from_db(db.clone(), number)
+				.unwrap()
+				.is_none());
+			assert_eq!(
+				get_block_number_from_db(db.clone(), hash(number)).unwrap(),
+				None
+			);
+		}
+		for number in 4..=5 {
+			assert!(get_block_header_from_db(db.clone(), number)
+				.unwrap()
+				.is_some());
+			assert_eq!(
+				get_block_number_from_db(db.clone(), hash(number)).unwrap(),
+				Some(number)
+			);
+		}
+	}
+}
@@ -0,0 +1,384 @@
+//! Snapshot export and import of the light client state.
+//!
+//! Snapshot contains genesis hash, finality sync checkpoint, block headers and confidence factors,
+//! and it is stored into file SCALE encoded. Export reads from a consistent RocksDB snapshot, so
+//! concurrent writes are not captured partially. Before import, snapshot is validated against the
+//! expected genesis hash and the stored finality sync checkpoint, block headers are checked to
+//! form a gapless chain, and the chain and finality sync checkpoint are checked against the node.
+//! Snapshot is imported atomically.
+
+use anyhow::{anyhow, Context, Result};
+use avail_subxt::{avail, primitives::Header as DaHeader, utils::H256};
+use codec::{Decode, Encode};
+use rocksdb::{IteratorMode, DB};
+use sp_core::blake2_256;
+use std::{fs, path::Path};
+
+use super::{Database, FINALITY_SYNC_CHECKPOINT_KEY, GENESIS_HASH_KEY};
+use crate::{
+	consts::{BLOCK_HEADER_CF, CONFIDENCE_FACTOR_CF, STATE_CF},
+	rpc,
+	types::FinalitySyncCheckpoint,
+};
+
+/// Version of the snapshot format
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Decode, Encode)]
+pub struct Snapshot {
+	pub version: u32,
+	pub genesis_hash: H256,
+	pub finality_sync_checkpoint: Option<FinalitySyncCheckpoint>,
+	/// Block headers, ordered by block number
+	pub headers: Vec<DaHeader>,
+	/// Block numbers and confidence factors, ordered by block number
+	pub confidences: Vec<(u32, u32)>,
+}
+
+impl Snapshot {
+	/// Latest block header in the snapshot
+	pub fn latest_header(&self) -> Option<&DaHeader> {
+		self.headers.last()
+	}
+
+	/// Block header in the snapshot with the given block number
+	pub fn header(&self, block_number: u32) -> Option<&DaHeader> {
+		let first = self.headers.first()?.number;
+		let index = block_number.checked_sub(first)?;
+		self.headers
+			.get(index as usize)
+			.filter(|header| header.number == block_number)
+	}
+
+	/// Reads snapshot from the file
+	pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+		let encoded = fs::read(path).context("Failed to read snapshot file")?;
+		let version =
+			u32::decode(&mut &encoded[..]).context("Failed to decode snapshot version")?;
+		if version != SNAPSHOT_VERSION {
+			return Err(anyhow!(
+				"Snapshot version {version} is not supported, expected version {SNAPSHOT_VERSION}"
+			));
+		}
+		Snapshot::decode(&mut &encoded[..]).context("Failed to decode snapshot")
+	}
+
+	/// Writes snapshot into the file
+	pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+		fs::write(path, self.encode()).context("Failed to write snapshot file")
+	}
+}
+
+fn decode_block_number(key: &[u8]) -> Result<u32> {
+	<[u8; 4]>::try_from(key)
+		.map(u32::from_be_bytes)
+		.map_err(|_| anyhow!("Invalid block number key"))
+}
+
+/// Exports snapshot from a consistent point in time view of the database
+pub fn export(db: &DB) -> Result<Snapshot> {
+	let snapshot = db.snapshot();
+
+	let state_handle = db.cf_handle(STATE_CF).context("Failed to get cf handle")?;
+	let genesis_hash = snapshot
+		.get_cf(&state_handle, GENESIS_HASH_KEY.as_bytes())
+		.context("Failed to get genesis hash")?
+		.ok_or_else(|| anyhow!("Genesis hash is not stored"))?;
+	let genesis_hash = <[u8; 32]>::try_from(&genesis_hash[..])
+		.map(H256::from)
+		.map_err(|_| anyhow!("Bad genesis hash format!"))?;
+
+	let finality_sync_checkpoint = snapshot
+		.get_cf(&state_handle, FINALITY_SYNC_CHECKPOINT_KEY.as_bytes())
+		.context("Failed to get finality sync checkpoint")?
+		.map(|value| FinalitySyncCheckpoint::decode(&mut &value[..]))
+		.transpose()
+		.context("Failed to decode finality sync checkpoint")?;
+
+	let header_handle = db
+		.cf_handle(BLOCK_HEADER_CF)
+		.context("Failed to get cf handle")?;
+	let headers = snapshot
+		.iterator_cf(&header_handle, IteratorMode::Start)
+		.map(|item| {
+			let (_, value) = item.context("Failed to iterate over block headers")?;
+			DaHeader::decode(&mut &value[..]).context("Failed to decode header")
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	let confidence_handle = db
+		.cf_handle(CONFIDENCE_FACTOR_CF)
+		.context("Failed to get cf handle")?;
+	let confidences = snapshot
+		.iterator_cf(&confidence_handle, IteratorMode::Start)
+		.map(|item| {
+			let (key, value) = item.context("Failed to iterate over confidence factors")?;
+			let count = <[u8; 4]>::try_from(&value[..])
+				.map(u32::from_be_bytes)
+				.map_err(|_| anyhow!("Invalid confidence factor"))?;
+			Ok((decode_block_number(&key)?, count))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(Snapshot {
+		version: SNAPSHOT_VERSION,
+		genesis_hash,
+		finality_sync_checkpoint,
+		headers,
+		confidences,
+	})
+}
+
+/// Validates snapshot before import. Snapshot has to be validated against the node as well,
+/// using [`validate_with_node`].
+///
+/// # Arguments
+///
+/// * `snapshot` - Snapshot to validate
+/// * `genesis_hash` - Genesis hash of the network the light client is connected to
+/// * `stored_checkpoint` - Finality sync checkpoint stored in the database, if any
+pub fn validate(
+	snapshot: &Snapshot,
+	genesis_hash: H256,
+	stored_checkpoint: Option<&FinalitySyncCheckpoint>,
+) -> Result<()> {
+	if snapshot.version != SNAPSHOT_VERSION {
+		return Err(anyhow!(
+			"Snapshot version {} is not supported, expected version {SNAPSHOT_VERSION}",
+			snapshot.version
+		));
+	}
+
+	if snapshot.genesis_hash != genesis_hash {
+		return Err(anyhow!(
+			"Snapshot genesis hash {:?} doesn't match the expected genesis hash {genesis_hash:?}",
+			snapshot.genesis_hash
+		));
+	}
+
+	let Some(checkpoint) = &snapshot.finality_sync_checkpoint else {
+		return Err(anyhow!("Snapshot has no finality sync checkpoint"));
+	};
+
+	if checkpoint.validator_set.is_empty() {
+		return Err(anyhow!(
+			"Snapshot finality sync checkpoint has no validators"
+		));
+	}
+
+	if snapshot.header(checkpoint.number).is_none() {
+		return Err(anyhow!(
+			"Snapshot has no header of the finality sync checkpoint block {}",
+			checkpoint.number
+		));
+	}
+
+	if let Some(stored) = stored_checkpoint {
+		if checkpoint.number < stored.number || checkpoint.set_id < stored.set_id {
+			return Err(anyhow!(
+				"Snapshot finality sync checkpoint (block {}, set {}) is behind the stored one (block {}, set {})",
+				checkpoint.number,
+				checkpoint.set_id,
+				stored.number,
+				stored.set_id
+			));
+		}
+	}
+
+	for (parent, header) in snapshot.headers.iter().zip(snapshot.headers.iter().skip(1)) {
+		if Some(header.number) != parent.number.checked_add(1) {
+			return Err(anyhow!(
+				"Snapshot headers are not consecutive ({} after {})",
+				header.number,
+				parent.number
+			));
+		}
+		let parent_hash: H256 = Encode::using_encoded(parent, blake2_256).into();
+		if header.parent_hash != parent_hash {
+			return Err(anyhow!(
+				"Snapshot header {} is not a child of header {}",
+				header.number,
+				parent.number
+			));
+		}
+	}
+
+	let is_ordered = snapshot
+		.confidences
+		.windows(2)
+		.all(|pair| pair[0].0 < pair[1].0);
+	if !is_ordered {
+		return Err(anyhow!(
+			"Snapshot confidence factors are not ordered by block number"
+		));
+	}
+
+	// Confidence factors are trusted only for blocks of the header chain anchored to the node
+	if let Some((block_number, _)) = snapshot
+		.confidences
+		.iter()
+		.find(|(block_number, _)| snapshot.header(*block_number).is_none())
+	{
+		return Err(anyhow!(
+			"Snapshot has confidence factor of block {block_number} without its header"
+		));
+	}
+
+	Ok(())
+}
+
+/// Validates snapshot against the node, after it is validated by [`validate`].
+///
+/// Latest snapshot header has to be finalized by the node, which anchors the whole header chain,
+/// and finality sync checkpoint has to match the set ID and validator set at the checkpoint block.
+pub async fn validate_with_node(client: &avail::Client, snapshot: &Snapshot) -> Result<()> {
+	if let Some(header) = snapshot.latest_header() {
+		let hash: H256 = Encode::using_encoded(header, blake2_256).into();
+		let block_hash = rpc::get_block_hash(client, header.number)
+			.await
+			.context("Failed to get block hash")?;
+		if hash != block_hash {
+			return Err(anyhow!(
+				"Snapshot block {} doesn't match the block {block_hash:?} from the node",
+				header.number
+			));
+		}
+	}
+
+	let Some(checkpoint) = &snapshot.finality_sync_checkpoint else {
+		return Err(anyhow!("Snapshot has no finality sync checkpoint"));
+	};
+	let header = snapshot.header(checkpoint.number).ok_or_else(|| {
+		anyhow!(
+			"Snapshot has no header of the finality sync checkpoint block {}",
+			checkpoint.number
+		)
+	})?;
+	let hash: H256 = Encode::using_encoded(header, blake2_256).into();
+
+	let set_id = rpc::get_set_id_by_hash(client, hash).await?;
+	if checkpoint.set_id != set_id {
+		return Err(anyhow!(
+			"Snapshot finality sync checkpoint set ID {} doesn't match set ID {set_id} at block {}",
+			checkpoint.set_id,
+			checkpoint.number
+		));
+	}
+
+	let validator_set = rpc::get_valset_by_hash(client, hash).await?;
+	if checkpoint.validator_set != validator_set {
+		return Err(anyhow!(
+			"Snapshot finality sync checkpoint validator set doesn't match validator set at block {}",
+			checkpoint.number
+		));
+	}
+
+	Ok(())
+}
+
+/// Imports validated snapshot into the database atomically
+pub fn import(db: &impl Database, snapshot: Snapshot) -> Result<()> {
+	db.store_snapshot(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{import, validate, Snapshot, SNAPSHOT_VERSION};
+	use crate::{
+		data::{Database, MemoryDB},
+		test_utils::{header, header_hash},
+		types::FinalitySyncCheckpoint,
+	};
+	use sp_core::{ed25519, H256};
+
+	fn checkpoint(number: u32, set_id: u64) -> FinalitySyncCheckpoint {
+		FinalitySyncCheckpoint {
+			number,
+			set_id,
+			validator_set: vec![(ed25519::Public::from_raw([1u8; 32]), 1)],
+		}
+	}
+
+	fn snapshot() -> Snapshot {
+		let first = header(1, H256::default());
+		let second = header(2, header_hash(&first));
+		Snapshot {
+			version: SNAPSHOT_VERSION,
+			genesis_hash: H256::repeat_byte(1),
+			finality_sync_checkpoint: Some(checkpoint(2, 1)),
+			headers: vec![first, second],
+			confidences: vec![(1, 10), (2, 12)],
+		}
+	}
+
+	#[test]
+	fn validate_ok() {
+		let snapshot = snapshot();
+		validate(&snapshot, H256::repeat_byte(1), None).unwrap();
+		validate(&snapshot, H256::repeat_byte(1), Some(&checkpoint(1, 1))).unwrap();
+	}
+
+	#[test]
+	fn validate_genesis_hash_mismatch() {
+		assert!(validate(&snapshot(), H256::repeat_byte(2), None).is_err());
+	}
+
+	#[test]
+	fn validate_checkpoint() {
+		let mut snapshot = snapshot();
+		let genesis_hash = snapshot.genesis_hash;
+		assert!(validate(&snapshot, genesis_hash, Some(&checkpoint(3, 1))).is_err());
+		assert!(validate(&snapshot, genesis_hash, Some(&checkpoint(1, 2))).is_err());
+		snapshot.finality_sync_checkpoint = None;
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+	}
+
+	#[test]
+	fn validate_checkpoint_header() {
+		let mut snapshot = snapshot();
+		let genesis_hash = snapshot.genesis_hash;
+		snapshot.finality_sync_checkpoint = Some(checkpoint(3, 1));
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+	}
+
+	#[test]
+	fn validate_headers_gap() {
+		let mut snapshot = snapshot();
+		let genesis_hash = snapshot.genesis_hash;
+		let parent_hash = header_hash(&snapshot.headers[1]);
+		snapshot.headers.push(header(4, parent_hash));
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+	}
+
+	#[test]
+	fn validate_confidence_without_header() {
+		let mut snapshot = snapshot();
+		let genesis_hash = snapshot.genesis_hash;
+		snapshot.confidences.push((3, 10));
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+	}
+
+	#[test]
+	fn validate_broken_chain() {
+		let mut snapshot = snapshot();
+		let genesis_hash = snapshot.genesis_hash;
+		snapshot.headers[1].parent_hash = H256::repeat_byte(3);
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+		snapshot.headers.swap(0, 1);
+		assert!(validate(&snapshot, genesis_hash, None).is_err());
+	}
+
+	#[test]
+	fn import_snapshot() {
+		let db = MemoryDB::default();
+		let snapshot = snapshot();
+		let latest_hash = header_hash(snapshot.latest_header().unwrap());
+		import(&db, snapshot).unwrap();
+
+		assert_eq!(db.get_genesis_hash().unwrap(), Some(H256::repeat_byte(1)));
+		assert_eq!(db.get_confidence(2).unwrap(), Some(12));
+		assert_eq!(db.get_block_number(latest_hash).unwrap(), Some(2));
+		let stored = db.get_finality_sync_checkpoint().unwrap().unwrap();
+		assert_eq!((stored.number, stored.set_id), (2, 1));
+	}
+}
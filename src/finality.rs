@@ -0,0 +1,412 @@
+//! GRANDPA justification verification and equivocation detection.
+//!
+//! Justification is accepted if precommits are signed by the validators holding more than 2/3 of
+//! the total voting weight of the validator set. Every precommit signature is verified against its
+//! own precommit message, since precommits can target descendants of the committed block. Such
+//! precommits are accepted only if the descent is proven by the justification votes ancestries.
+
+use anyhow::{anyhow, Result};
+use avail_subxt::{primitives::Header as DaHeader, utils::H256};
+use codec::Encode;
+use sp_core::{blake2_256, ed25519, Pair};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use crate::types::{
+	Commit, Equivocation, GrandpaJustification, Precommit, SignedPrecommit, SignerMessage,
+};
+
+/// Returns minimal voting weight required to finalize a block, which is more than 2/3 of the total weight
+pub fn supermajority_threshold(total_weight: u64) -> u64 {
+	let faulty = total_weight.saturating_sub(1) / 3;
+	total_weight - faulty
+}
+
+/// Checks that the precommit targets the commit target, or its descendant, by walking the votes ancestries
+/// from the precommit target down to the commit target. Walked ancestry headers are added to `visited`.
+fn is_commit_target_or_descendant(
+	ancestries: &HashMap<H256, &DaHeader>,
+	commit: &Commit,
+	precommit: &Precommit,
+	visited: &mut HashSet<H256>,
+) -> bool {
+	let mut hash = precommit.target_hash;
+	let mut number = precommit.target_number;
+	loop {
+		if hash == commit.target_hash {
+			return number == commit.target_number;
+		}
+		if number <= commit.target_number {
+			return false;
+		}
+		let Some(header) = ancestries.get(&hash) else {
+			return false;
+		};
+		if header.number != number {
+			return false;
+		}
+		visited.insert(hash);
+		hash = header.parent_hash;
+		number -= 1;
+	}
+}
+
+/// Verifies that justification is signed by the supermajority of the validator set, and returns signed weight.
+///
+/// Fails if any of the precommit signatures is invalid, if any precommit target is neither the commit
+/// target nor its descendant proven by the votes ancestries, or if any of the votes ancestries is unused.
+/// Precommits signed by validators outside of the validator set are ignored, and weight of each
+/// validator is counted only once.
+///
+/// # Arguments
+///
+/// * `justification` - GRANDPA justification to verify
+/// * `set_id` - ID of the validator set which signed the justification
+/// * `validator_set` - Validators with their voting weights
+pub fn verify_justification(
+	justification: &GrandpaJustification,
+	set_id: u64,
+	validator_set: &[(ed25519::Public, u64)],
+) -> Result<u64> {
+	let total_weight = validator_set
+		.iter()
+		.fold(0u64, |total, (_, weight)| total.saturating_add(*weight));
+	if total_weight == 0 {
+		return Err(anyhow!("Validator set has no voting weight"));
+	}
+
+	let commit = &justification.commit;
+	let ancestries: HashMap<H256, &DaHeader> = justification
+		.votes_ancestries
+		.iter()
+		.map(|header| (Encode::using_encoded(header, blake2_256).into(), header))
+		.collect();
+	let mut visited = HashSet::new();
+	let mut signers = HashSet::new();
+	let mut signed_weight = 0u64;
+
+	for signed in &commit.precommits {
+		if !is_commit_target_or_descendant(&ancestries, commit, &signed.precommit, &mut visited) {
+			return Err(anyhow!(
+				"Precommit target {} ({:?}) is not a descendant of commit target {} ({:?})",
+				signed.precommit.target_number,
+				signed.precommit.target_hash,
+				commit.target_number,
+				commit.target_hash
+			));
+		}
+
+		let message = Encode::encode(&(
+			&SignerMessage::PrecommitMessage(signed.precommit.clone()),
+			&justification.round,
+			&set_id,
+		));
+		if !<ed25519::Pair as Pair>::verify(&signed.signature, &message, &signed.id) {
+			return Err(anyhow!("Invalid precommit signature of {:?}", signed.id));
+		}
+
+		let Some((_, weight)) = validator_set.iter().find(|(id, _)| id == &signed.id) else {
+			continue;
+		};
+		if signers.insert(signed.id.0) {
+			signed_weight = signed_weight.saturating_add(*weight);
+		}
+	}
+
+	// Same as in Substrate, justification must not contain ancestries which are not needed
+	if visited.len() != ancestries.len() {
+		return Err(anyhow!(
+			"Invalid votes ancestries, {} of {} headers are unused",
+			ancestries.len() - visited.len(),
+			ancestries.len()
+		));
+	}
+
+	let threshold = supermajority_threshold(total_weight);
+	if signed_weight < threshold {
+		return Err(anyhow!(
+			"Not signed by the supermajority of the validator set (signed weight {signed_weight}, required {threshold} of {total_weight})"
+		));
+	}
+
+	Ok(signed_weight)
+}
+
+/// Finds validators which signed conflicting precommits in the justification.
+///
+/// Precommits are conflicting if they are signed by the same validator for different targets.
+/// Precommit signatures are expected to be verified already. Only the first equivocation of each
+/// validator is returned, and precommits signed by validators outside of the validator set are ignored.
+///
+/// # Arguments
+///
+/// * `justification` - Verified GRANDPA justification
+/// * `set_id` - ID of the validator set which signed the justification
+/// * `validator_set` - Validators with their voting weights
+pub fn find_equivocations(
+	justification: &GrandpaJustification,
+	set_id: u64,
+	validator_set: &[(ed25519::Public, u64)],
+) -> Vec<Equivocation> {
+	let mut precommits: HashMap<[u8; 32], &SignedPrecommit> = HashMap::new();
+	let mut offenders = HashSet::new();
+	let mut equivocations = vec![];
+
+	for signed in &justification.commit.precommits {
+		if !validator_set.iter().any(|(id, _)| id == &signed.id) {
+			continue;
+		}
+
+		let first = match precommits.entry(signed.id.0) {
+			Entry::Vacant(entry) => {
+				entry.insert(signed);
+				continue;
+			},
+			Entry::Occupied(entry) => *entry.get(),
+		};
+
+		if first.precommit == signed.precommit || !offenders.insert(signed.id.0) {
+			continue;
+		}
+
+		equivocations.push(Equivocation {
+			set_id,
+			round: justification.round,
+			offender: signed.id,
+			first: first.clone(),
+			second: signed.clone(),
+		});
+	}
+
+	equivocations
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{find_equivocations, supermajority_threshold, verify_justification};
+	use crate::{
+		test_utils::{header, header_hash},
+		types::{Commit, GrandpaJustification, Precommit, SignedPrecommit, SignerMessage},
+	};
+	use avail_subxt::primitives::Header as DaHeader;
+	use codec::Encode;
+	use sp_core::{ed25519, Pair, H256};
+	use test_case::test_case;
+
+	const ROUND: u64 = 7;
+	const SET_ID: u64 = 3;
+	const TARGET_NUMBER: u32 = 100;
+
+	fn pair(seed: u8) -> ed25519::Pair {
+		ed25519::Pair::from_seed(&[seed; 32])
+	}
+
+	fn precommit(target_number: u32) -> Precommit {
+		Precommit {
+			target_hash: H256::repeat_byte(target_number as u8),
+			target_number,
+		}
+	}
+
+	fn signed_precommit(
+		signer: &ed25519::Pair,
+		set_id: u64,
+		target_number: u32,
+	) -> SignedPrecommit {
+		sign(signer, set_id, precommit(target_number))
+	}
+
+	fn sign(signer: &ed25519::Pair, set_id: u64, precommit: Precommit) -> SignedPrecommit {
+		let message = Encode::encode(&(
+			&SignerMessage::PrecommitMessage(precommit.clone()),
+			&ROUND,
+			&set_id,
+		));
+		SignedPrecommit {
+			precommit,
+			signature: signer.sign(&message),
+			id: signer.public(),
+		}
+	}
+
+	/// Returns headers descending from the given parent, starting with the given block number
+	fn descendants(parent_hash: H256, first_number: u32, count: u32) -> Vec<DaHeader> {
+		let mut parent_hash = parent_hash;
+		(first_number..first_number + count)
+			.map(|number| {
+				let header = header(number, parent_hash);
+				parent_hash = header_hash(&header);
+				header
+			})
+			.collect()
+	}
+
+	fn target(header: &DaHeader) -> Precommit {
+		Precommit {
+			target_hash: header_hash(header),
+			target_number: header.number,
+		}
+	}
+
+	fn justification(precommits: Vec<SignedPrecommit>) -> GrandpaJustification {
+		GrandpaJustification {
+			round: ROUND,
+			commit: Commit {
+				target_hash: precommit(TARGET_NUMBER).target_hash,
+				target_number: TARGET_NUMBER,
+				precommits,
+			},
+			votes_ancestries: vec![],
+		}
+	}
+
+	fn signed_by(seeds: &[u8]) -> GrandpaJustification {
+		let precommits = seeds
+			.iter()
+			.map(|&seed| signed_precommit(&pair(seed), SET_ID, TARGET_NUMBER))
+			.collect();
+		justification(precommits)
+	}
+
+	fn validator_set(weights: &[(u8, u64)]) -> Vec<(ed25519::Public, u64)> {
+		weights
+			.iter()
+			.map(|&(seed, weight)| (pair(seed).public(), weight))
+			.collect()
+	}
+
+	#[test_case(1 => 1 ; "single validator")]
+	#[test_case(3 => 3 ; "three validators")]
+	#[test_case(4 => 3 ; "four validators")]
+	#[test_case(10 => 7 ; "ten validators")]
+	#[test_case(100 => 67 ; "hundred validators")]
+	fn threshold(total_weight: u64) -> u64 {
+		supermajority_threshold(total_weight)
+	}
+
+	#[test_case(&[1, 2, 3] => Some(3) ; "supermajority")]
+	#[test_case(&[1, 2, 3, 4] => Some(4) ; "all validators")]
+	#[test_case(&[1, 2] => None ; "half of validators")]
+	#[test_case(&[1, 1, 1, 2] => None ; "duplicate signers")]
+	#[test_case(&[1, 2, 5, 6] => None ; "signers outside of the set")]
+	fn equal_weights(seeds: &[u8]) -> Option<u64> {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1), (4, 1)]);
+		verify_justification(&signed_by(seeds), SET_ID, &validator_set).ok()
+	}
+
+	#[test_case(&[1] => Some(10) ; "heavy validator")]
+	#[test_case(&[2, 3, 4] => None ; "majority of signers")]
+	#[test_case(&[1, 2] => Some(11) ; "heavy and light validator")]
+	fn weighted(seeds: &[u8]) -> Option<u64> {
+		let validator_set = validator_set(&[(1, 10), (2, 1), (3, 1), (4, 1)]);
+		verify_justification(&signed_by(seeds), SET_ID, &validator_set).ok()
+	}
+
+	#[test]
+	fn invalid_signature() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let mut justification = signed_by(&[1, 2]);
+		justification
+			.commit
+			.precommits
+			.push(signed_precommit(&pair(3), SET_ID + 1, TARGET_NUMBER));
+		assert!(verify_justification(&justification, SET_ID, &validator_set).is_err());
+	}
+
+	#[test]
+	fn precommits_for_descendants() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let ancestries = descendants(precommit(TARGET_NUMBER).target_hash, TARGET_NUMBER + 1, 2);
+		let precommits = vec![
+			signed_precommit(&pair(1), SET_ID, TARGET_NUMBER),
+			sign(&pair(2), SET_ID, target(&ancestries[0])),
+			sign(&pair(3), SET_ID, target(&ancestries[1])),
+		];
+		let mut justification = justification(precommits);
+		justification.votes_ancestries = ancestries;
+		assert_eq!(
+			verify_justification(&justification, SET_ID, &validator_set).unwrap(),
+			3
+		);
+	}
+
+	#[test]
+	fn precommits_for_unrelated_blocks() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let precommits = vec![
+			signed_precommit(&pair(1), SET_ID, TARGET_NUMBER + 1),
+			signed_precommit(&pair(2), SET_ID, TARGET_NUMBER + 1),
+			signed_precommit(&pair(3), SET_ID, TARGET_NUMBER + 1),
+		];
+		let justification = justification(precommits);
+		assert!(verify_justification(&justification, SET_ID, &validator_set).is_err());
+	}
+
+	#[test]
+	fn precommits_for_conflicting_fork() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		// Fork descends from the block at the commit target height, other than the commit target
+		let fork = descendants(H256::repeat_byte(0xff), TARGET_NUMBER + 1, 2);
+		let precommits = vec![
+			sign(&pair(1), SET_ID, target(&fork[1])),
+			sign(&pair(2), SET_ID, target(&fork[1])),
+			sign(&pair(3), SET_ID, target(&fork[1])),
+		];
+		let mut justification = justification(precommits);
+		justification.votes_ancestries = fork;
+		assert!(verify_justification(&justification, SET_ID, &validator_set).is_err());
+	}
+
+	#[test]
+	fn unused_votes_ancestries() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let mut justification = signed_by(&[1, 2, 3]);
+		justification.votes_ancestries =
+			descendants(precommit(TARGET_NUMBER).target_hash, TARGET_NUMBER + 1, 1);
+		assert!(verify_justification(&justification, SET_ID, &validator_set).is_err());
+	}
+
+	#[test]
+	fn precommit_below_target() {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let precommits = vec![
+			signed_precommit(&pair(1), SET_ID, TARGET_NUMBER),
+			signed_precommit(&pair(2), SET_ID, TARGET_NUMBER),
+			signed_precommit(&pair(3), SET_ID, TARGET_NUMBER - 1),
+		];
+		let justification = justification(precommits);
+		assert!(verify_justification(&justification, SET_ID, &validator_set).is_err());
+	}
+
+	#[test]
+	fn empty_validator_set() {
+		assert!(verify_justification(&signed_by(&[1]), SET_ID, &[]).is_err());
+	}
+
+	#[test_case(&[(1, 0), (2, 0), (3, 0)] => Vec::<u8>::new() ; "no equivocations")]
+	#[test_case(&[(1, 0), (2, 0), (1, 0)] => Vec::<u8>::new() ; "duplicate precommits")]
+	#[test_case(&[(1, 0), (2, 0), (1, 1)] => vec![1] ; "conflicting precommits")]
+	#[test_case(&[(1, 0), (1, 1), (1, 2)] => vec![1] ; "reported once per validator")]
+	#[test_case(&[(1, 0), (2, 0), (1, 1), (2, 2)] => vec![1, 2] ; "multiple validators")]
+	#[test_case(&[(5, 0), (5, 1)] => Vec::<u8>::new() ; "signer outside of the set")]
+	fn equivocations(precommits: &[(u8, u32)]) -> Vec<u8> {
+		let validator_set = validator_set(&[(1, 1), (2, 1), (3, 1)]);
+		let precommits = precommits
+			.iter()
+			.map(|&(seed, offset)| signed_precommit(&pair(seed), SET_ID, TARGET_NUMBER + offset))
+			.collect();
+		let justification = justification(precommits);
+		find_equivocations(&justification, SET_ID, &validator_set)
+			.into_iter()
+			.map(|equivocation| {
+				assert_eq!(equivocation.set_id, SET_ID);
+				assert_eq!(equivocation.round, ROUND);
+				assert_eq!(equivocation.first.id, equivocation.offender);
+				assert_eq!(equivocation.second.id, equivocation.offender);
+				assert_ne!(equivocation.first.precommit, equivocation.second.precommit);
+				(1..=5u8)
+					.find(|&seed| pair(seed).public() == equivocation.offender)
+					.unwrap()
+			})
+			.collect()
+	}
+}
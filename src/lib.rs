@@ -4,13 +4,17 @@ pub mod consts;
 #[cfg(feature = "crawl")]
 pub mod crawl_client;
 pub mod data;
+pub mod finality;
 pub mod light_client;
 pub mod network;
 pub mod proof;
+pub mod pruning;
 pub mod rpc;
 pub mod subscriptions;
 pub mod sync_client;
 pub mod sync_finality;
 pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
 pub mod types;
 pub mod utils;
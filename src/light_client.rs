@@ -7,50 +7,62 @@
 //! * Connect to the Avail node WebSocket stream and start listening to finalized headers
 //! * Generate random cells for random data sampling (8 cells currently)
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
-//! * Verify proof using the received cells
-//! * Calculate block confidence and store it in RocksDB
+//! * Sample fresh random cells from DHT, in case some of the cells are not available in DHT, before falling back to RPC
+//! * Verify proof using the received cells, cells from DHT which fail verification are fetched from RPC instead
+//! * Calculate block confidence, along with the confidence from DHT and RPC cells separately, and store it in the database
+//! * Store availability failure in the database, in case not enough cells are fetched or some cells fail proof verification
 //! * Insert cells to to DHT for remote fetch
 //! * Notify the consumer (app client) a new block has been verified
 //!
 //! # Notes
 //!
 //! In case delay is configured, block processing is delayed for configured time.
+//! Multiple blocks are processed concurrently, but the state is updated and the consumer is notified in block order.
+//! Availability failures are published in block order as well, and block with failure doesn't achieve confidence.
 //! In case RPC is disabled, RPC calls will be skipped.
+//! In case minimum DHT confidence is configured, block achieves confidence only if confidence from DHT cells is high enough.
 //! In case partition is configured, block partition is fetched and inserted into DHT.
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use avail_subxt::{avail, primitives::Header, utils::H256};
 use codec::Encode;
 use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
-use futures::future::join_all;
+use futures::{future::join_all, stream::FuturesOrdered, StreamExt};
 use kate_recovery::{
 	commitments, data,
 	matrix::{Dimensions, Position},
 };
 use kate_recovery::{data::Cell, matrix::RowIndex};
 use mockall::automock;
-use rocksdb::DB;
 use sp_core::blake2_256;
 use std::{
+	collections::HashSet,
 	sync::{Arc, Mutex},
 	time::Instant,
 };
-use tokio::sync::{broadcast, mpsc::Sender};
-use tracing::{error, info};
+use tokio::sync::{
+	broadcast::{self, error::RecvError},
+	mpsc::{Sender, UnboundedSender},
+};
+use tracing::{error, info, warn};
 
 use crate::{
-	data::{store_block_header_in_db, store_confidence_in_db},
+	data::{BlockBatch, Database},
 	network::Client,
 	proof, rpc,
 	telemetry::{MetricCounter, MetricValue, Metrics},
-	types::{self, BlockVerified, LightClientConfig, OptionBlockRange, State},
-	utils::{calculate_confidence, extract_kate},
+	types::{
+		self, AvailabilityFailure, BlockVerified, FailureReason, LightClientConfig,
+		OptionBlockRange, RetryConfig, SamplingRecord, State,
+	},
+	utils::{calculate_confidence, diff_positions, extract_kate, retry},
 };
 
 #[async_trait]
 #[automock]
 pub trait LightClient {
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Vec<Cell>;
 	async fn fetch_cells_from_dht(
 		&self,
 		positions: &[Position],
@@ -62,27 +74,33 @@ pub trait LightClient {
 	async fn shrink_kademlia_map(&self) -> Result<()>;
 	async fn get_multiaddress_and_ip(&self) -> Result<(String, String)>;
 	async fn count_dht_entries(&self) -> Result<usize>;
-	fn store_block_header_in_db(&self, header: &Header, block_number: u32) -> Result<()>;
-	fn store_confidence_in_db(&self, count: u32, block_number: u32) -> Result<()>;
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()>;
 }
 
 #[derive(Clone)]
-struct LightClientImpl {
-	db: Arc<DB>,
+struct LightClientImpl<T: Database> {
+	db: T,
 	network_client: Client,
 	rpc_client: avail::Client,
+	retry: RetryConfig,
 }
 
-pub fn new(db: Arc<DB>, network_client: Client, rpc_client: avail::Client) -> impl LightClient {
+pub fn new(
+	db: impl Database,
+	network_client: Client,
+	rpc_client: avail::Client,
+	retry: RetryConfig,
+) -> impl LightClient {
 	LightClientImpl {
 		db,
 		network_client,
 		rpc_client,
+		retry,
 	}
 }
 
 #[async_trait]
-impl LightClient for LightClientImpl {
+impl<T: Database> LightClient for LightClientImpl<T> {
 	async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> f32 {
 		self.network_client
 			.insert_cells_into_dht(block, cells)
@@ -94,6 +112,14 @@ impl LightClient for LightClientImpl {
 	async fn insert_rows_into_dht(&self, block: u32, rows: Vec<(RowIndex, Vec<u8>)>) -> f32 {
 		self.network_client.insert_rows_into_dht(block, rows).await
 	}
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Vec<Cell> {
+		self.db
+			.get_cached_cells(block_number, positions)
+			.unwrap_or_else(|error| {
+				warn!(block_number, "Cannot get cached cells: {error:#}");
+				vec![]
+			})
+	}
 	async fn fetch_cells_from_dht(
 		&self,
 		positions: &[Position],
@@ -104,7 +130,10 @@ impl LightClient for LightClientImpl {
 			.await
 	}
 	async fn get_kate_proof(&self, hash: H256, positions: &[Position]) -> Result<Vec<Cell>> {
-		rpc::get_kate_proof(&self.rpc_client, hash, positions).await
+		retry(&self.retry, rpc::is_transient, || {
+			rpc::get_kate_proof(&self.rpc_client, hash, positions)
+		})
+		.await
 	}
 	async fn get_multiaddress_and_ip(&self) -> Result<(String, String)> {
 		self.network_client.get_multiaddress_and_ip().await
@@ -112,16 +141,24 @@ impl LightClient for LightClientImpl {
 	async fn count_dht_entries(&self) -> Result<usize> {
 		self.network_client.count_dht_entries().await
 	}
-	fn store_confidence_in_db(&self, count: u32, block_number: u32) -> Result<()> {
-		store_confidence_in_db(self.db.clone(), block_number, count)
-			.context("Failed to store confidence in DB")
-	}
-	fn store_block_header_in_db(&self, header: &Header, block_number: u32) -> Result<()> {
-		store_block_header_in_db(self.db.clone(), block_number, header)
-			.context("Failed to store block header in DB")
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()> {
+		self.db
+			.store_block(block_number, block)
+			.context("Failed to store block in DB")
 	}
 }
 
+/// Outcome of the block processing
+#[derive(Debug, Default)]
+pub struct ProcessedBlock {
+	/// Block confidence, if achieved
+	pub confidence: Option<f64>,
+	/// Failed data availability verification, if any
+	pub failure: Option<AvailabilityFailure>,
+	/// Block writes, committed by the caller
+	pub block: BlockBatch,
+}
+
 pub async fn process_block(
 	light_client: &impl LightClient,
 	metrics: &Arc<impl Metrics>,
@@ -129,8 +166,7 @@ pub async fn process_block(
 	pp: Arc<PublicParameters>,
 	header: &Header,
 	received_at: Instant,
-	state: Arc<Mutex<State>>,
-) -> Result<Option<f64>> {
+) -> Result<ProcessedBlock> {
 	metrics.count(MetricCounter::SessionBlock).await;
 	metrics
 		.record(MetricValue::TotalBlockNumber(header.number))
@@ -152,12 +188,12 @@ pub async fn process_block(
 			block_number,
 			"Skipping block with invalid dimensions {rows}x{cols}",
 		);
-		return Ok(None);
+		return Ok(ProcessedBlock::default());
 	};
 
 	if dimensions.cols().get() <= 2 {
 		error!(block_number, "more than 2 columns is required");
-		return Ok(None);
+		return Ok(ProcessedBlock::default());
 	}
 
 	let commitments = commitments::from_slice(&commitment)?;
@@ -171,12 +207,45 @@ pub async fn process_block(
 		positions.len()
 	);
 
-	let (cells_fetched, unfetched) = light_client
-		.fetch_cells_from_dht(&positions, block_number)
+	// Cells verified earlier are not fetched again, and they are not counted as DHT cells
+	let cached = light_client.get_cached_cells(block_number, &positions);
+	let uncached = diff_positions(&positions, &cached);
+	let (mut cells_fetched, unfetched) = light_client
+		.fetch_cells_from_dht(&uncached, block_number)
 		.await;
+
+	// Sample fresh random cells from DHT instead of fetching unavailable cells from RPC right away
+	let mut sampled: HashSet<Position> = positions.iter().cloned().collect();
+	// All sampled positions in sampling order, recorded in the sampling record
+	let mut sampled_positions = positions.clone();
+	let mut rounds = 1;
+	while cached.len() + cells_fetched.len() < positions.len() && rounds < cfg.dht_sampling_rounds {
+		let missing = (positions.len() - cached.len() - cells_fetched.len()) as u32;
+		let additional = rpc::generate_additional_random_cells(dimensions, missing, &sampled);
+		if additional.is_empty() {
+			break;
+		}
+		sampled.extend(additional.iter().cloned());
+		sampled_positions.extend(additional.iter().cloned());
+		rounds += 1;
+
+		let (mut fetched, _) = light_client
+			.fetch_cells_from_dht(&additional, block_number)
+			.await;
+		info!(
+			block_number,
+			round = rounds,
+			"Number of additional cells fetched from DHT: {}/{}",
+			fetched.len(),
+			additional.len()
+		);
+		cells_fetched.append(&mut fetched);
+	}
+
 	info!(
 		block_number,
 		"cells_from_dht" = cells_fetched.len(),
+		"dht_sampling_rounds" = rounds,
 		"Number of cells fetched from DHT: {}",
 		cells_fetched.len()
 	);
@@ -190,7 +259,16 @@ pub async fn process_block(
 		))
 		.await?;
 
-	let mut rpc_fetched = if cfg.disable_rpc {
+	metrics
+		.record(MetricValue::DHTSamplingRounds(rounds as u32))
+		.await?;
+
+	let missing = positions
+		.len()
+		.saturating_sub(cached.len() + cells_fetched.len());
+	let unfetched = unfetched.into_iter().take(missing).collect::<Vec<_>>();
+
+	let mut rpc_fetched = if cfg.disable_rpc || unfetched.is_empty() {
 		vec![]
 	} else {
 		light_client
@@ -210,7 +288,8 @@ pub async fn process_block(
 		.await?;
 
 	let mut cells = vec![];
-	cells.extend(cells_fetched);
+	cells.extend(cached.clone());
+	cells.extend(cells_fetched.clone());
 	cells.extend(rpc_fetched.clone());
 
 	if positions.len() > cells.len() {
@@ -219,13 +298,40 @@ pub async fn process_block(
 			"Failed to fetch {} cells",
 			positions.len() - cells.len()
 		);
-		return Ok(None);
+		let failure = AvailabilityFailure {
+			reason: FailureReason::CellsUnavailable,
+			cells_requested: positions.len() as u32,
+			cells_fetched: cells.len() as u32,
+			cells_unverified: 0,
+		};
+		metrics.count(MetricCounter::AvailabilityFailure).await;
+		// Sampling record is stored, so unavailable cells can be inspected
+		let sampling = SamplingRecord::new(
+			&sampled_positions,
+			&cached,
+			&cells_fetched,
+			&rpc_fetched,
+			&[],
+		);
+		let block = BlockBatch {
+			header: Some(header.clone()),
+			failure: Some(failure),
+			sampling: Some(sampling),
+			..Default::default()
+		};
+		return Ok(ProcessedBlock {
+			confidence: None,
+			failure: Some(failure),
+			block,
+		});
 	}
 
+	let mut block = BlockBatch::default();
 	let mut confidence = None;
+	let mut failure = None;
 	if !cfg.disable_proof_verification {
-		let (verified, unverified) =
-			proof::verify(block_number, dimensions, &cells, &commitments, pp)?;
+		let (mut verified, unverified) =
+			proof::verify(block_number, dimensions, &cells, &commitments, pp.clone())?;
 		let count = verified.len().saturating_sub(unverified.len());
 		info!(
 			block_number,
@@ -233,12 +339,51 @@ pub async fn process_block(
 			"Completed {count} verification rounds",
 		);
 
-		// write confidence factor into on-disk database
-		light_client
-			.store_confidence_in_db(verified.len() as u32, block_number)
-			.context("Failed to store confidence in DB")?;
+		// DHT cells which failed proof verification are dropped and fetched from RPC instead
+		let dht_unverified = cells_fetched
+			.iter()
+			.map(|cell| cell.position)
+			.filter(|position| unverified.contains(position))
+			.collect::<Vec<_>>();
+		if !dht_unverified.is_empty() {
+			warn!(
+				block_number,
+				"{} cells fetched from DHT failed proof verification",
+				dht_unverified.len()
+			);
+			cells_fetched.retain(|cell| !dht_unverified.contains(&cell.position));
+			cells.retain(|cell| !dht_unverified.contains(&cell.position));
+			if !cfg.disable_rpc {
+				let refetched = light_client
+					.get_kate_proof(header_hash, &dht_unverified)
+					.await
+					.context("Failed to fetch cells from node RPC")?;
+				let (refetched_verified, _) =
+					proof::verify(block_number, dimensions, &refetched, &commitments, pp)?;
+				verified.extend(refetched_verified);
+				cells.extend(refetched.clone());
+				rpc_fetched.extend(refetched);
+			}
+		}
 
-		state.lock().unwrap().confidence_achieved.set(block_number);
+		let sampling = SamplingRecord::new(
+			&sampled_positions,
+			&cached,
+			&cells_fetched,
+			&rpc_fetched,
+			&verified,
+		);
+		let counts = sampling.confidence_counts();
+		block.confidence = Some(verified.len() as u32);
+		block.confidence_counts = Some(counts);
+		block.sampling = Some(sampling);
+		if cfg.cache {
+			block.cells = cells
+				.iter()
+				.filter(|cell| verified.contains(&cell.position))
+				.cloned()
+				.collect();
+		}
 
 		let conf = calculate_confidence(verified.len() as u32);
 		info!(
@@ -248,7 +393,59 @@ pub async fn process_block(
 			conf
 		);
 		metrics.record(MetricValue::BlockConfidence(conf)).await?;
-		confidence = Some(conf);
+
+		let dht_conf = calculate_confidence(counts.dht);
+		let rpc_conf = calculate_confidence(counts.rpc);
+		info!(
+			block_number,
+			"dht_confidence" = dht_conf,
+			"rpc_confidence" = rpc_conf,
+			"Confidence factor from DHT cells: {dht_conf}, from RPC cells: {rpc_conf}",
+		);
+		metrics.record(MetricValue::DHTConfidence(dht_conf)).await?;
+		metrics.record(MetricValue::RPCConfidence(rpc_conf)).await?;
+
+		match cfg.min_dht_confidence {
+			Some(min_dht_conf) if dht_conf < min_dht_conf => {
+				warn!(
+					block_number,
+					"Confidence from DHT cells {dht_conf} is below required {min_dht_conf}"
+				);
+				// Confidence is not stored, so it is never reported as achieved for the block
+				failure = Some(AvailabilityFailure {
+					reason: FailureReason::InsufficientDhtConfidence,
+					cells_requested: positions.len() as u32,
+					cells_fetched: cells.len() as u32,
+					cells_unverified: 0,
+				});
+				block.confidence = None;
+				block.failure = failure;
+			},
+			_ => confidence = Some(conf),
+		}
+
+		// Cells served by the node failed verification, or DHT cells couldn't be replaced
+		let cells_unverified = positions.len().saturating_sub(verified.len());
+		if cells_unverified > 0 {
+			error!(
+				block_number,
+				"{cells_unverified} sampled cells failed proof verification",
+			);
+			failure = Some(AvailabilityFailure {
+				reason: FailureReason::InvalidProofs,
+				cells_requested: positions.len() as u32,
+				cells_fetched: cells.len() as u32,
+				cells_unverified: cells_unverified as u32,
+			});
+			block.confidence = None;
+			block.confidence_counts = None;
+			block.failure = failure;
+			confidence = None;
+		}
+
+		if failure.is_some() {
+			metrics.count(MetricCounter::AvailabilityFailure).await;
+		}
 	}
 
 	// push latest mined block's header into column family specified
@@ -259,9 +456,10 @@ pub async fn process_block(
 	// another competing thread, which syncs all block headers
 	// in range [0, LATEST], where LATEST = latest block number
 	// when this process started
-	light_client
-		.store_block_header_in_db(header, block_number)
-		.context("Failed to store block header in DB")?;
+	//
+	// confidence factor and block header are written into on-disk database at once by the caller,
+	// and state is updated in block order, after the block is stored
+	block.header = Some(header.clone());
 
 	let mut begin = Instant::now();
 	if let Some(partition) = &cfg.block_matrix_partition {
@@ -403,17 +601,28 @@ pub async fn process_block(
 
 	metrics.record(MetricValue::HealthCheck()).await?;
 
-	Ok(confidence)
+	Ok(ProcessedBlock {
+		confidence,
+		failure,
+		block,
+	})
 }
 
 pub struct Channels {
 	pub block_sender: Option<broadcast::Sender<BlockVerified>>,
 	pub header_receiver: broadcast::Receiver<(Header, Instant)>,
 	pub error_sender: Sender<anyhow::Error>,
+	/// Channel used to publish failed data availability verifications
+	pub failure_sender: broadcast::Sender<(u32, AvailabilityFailure)>,
+	/// Channel used to schedule blocks skipped by the light client for processing by the sync client
+	pub missed_block_sender: UnboundedSender<u32>,
 }
 
 /// Runs light client.
 ///
+/// Up to `block_processing_parallel_tasks` blocks are processed concurrently,
+/// while the state is updated and verified blocks are sent in block order.
+///
 /// # Arguments
 ///
 /// * `light_client` - Light client implementation
@@ -432,53 +641,109 @@ pub async fn run(
 ) {
 	info!("Starting light client...");
 
+	let parallel_tasks = cfg.block_processing_parallel_tasks.max(1);
+	let mut pipeline = FuturesOrdered::new();
+	let mut last_received: Option<u32> = None;
+	let mut lagged: Option<u64> = None;
+
 	loop {
-		let (header, received_at) = match channels.header_receiver.recv().await {
-			Ok(value) => value,
-			Err(error) => {
-				error!("Cannot receive message: {error}");
-				return;
+		tokio::select! {
+			message = channels.header_receiver.recv(), if pipeline.len() < parallel_tasks => {
+				let (header, received_at) = match message {
+					Ok(value) => value,
+					// Skipped blocks are processed by the sync client, so light client keeps up with the latest blocks
+					Err(RecvError::Lagged(skipped)) => {
+						warn!("Light client lagged behind, {skipped} blocks are skipped");
+						lagged = Some(skipped);
+						continue;
+					},
+					Err(RecvError::Closed) => {
+						let error = anyhow!("Header channel is closed");
+						error!("Cannot receive message: {error}");
+						if let Err(error) = channels.error_sender.send(error).await {
+							error!("Cannot send error message: {error}");
+						}
+						return;
+					},
+				};
+
+				if let Some(skipped) = lagged.take() {
+					let first = last_received.map_or_else(
+						|| header.number.saturating_sub(skipped.try_into().unwrap_or(u32::MAX)),
+						|last_received| last_received + 1,
+					);
+					let mut state = state.lock().unwrap();
+					for block_number in first..header.number {
+						// Blocks missed by the header subscription are already scheduled
+						if state.gaps.iter().any(|gap| gap.contains(block_number)) {
+							continue;
+						}
+						state.add_missed_block(block_number);
+						if let Err(error) = channels.missed_block_sender.send(block_number) {
+							error!(block_number, "Cannot send missed block message: {error}");
+						}
+					}
+				}
+				last_received = Some(header.number);
+
+				let (light_client, metrics, cfg, pp) = (&light_client, &metrics, &cfg, pp.clone());
+				pipeline.push_back(async move {
+					if let Some(seconds) = cfg.block_processing_delay.sleep_duration(received_at) {
+						info!("Sleeping for {seconds:?} seconds");
+						tokio::time::sleep(seconds).await;
+					}
+
+					let result =
+						process_block(light_client, metrics, cfg, pp, &header, received_at).await;
+					(header, result)
+				});
 			},
-		};
+			Some((header, process_block_result)) = pipeline.next() => {
+				let ProcessedBlock { confidence, failure, block } = match process_block_result {
+					Ok(processed_block) => processed_block,
+					Err(error) => {
+						error!("Cannot process block: {error}");
+						if let Err(error) = channels.error_sender.send(error).await {
+							error!("Cannot send error message: {error}");
+						}
+						return;
+					},
+				};
 
-		if let Some(seconds) = cfg.block_processing_delay.sleep_duration(received_at) {
-			info!("Sleeping for {seconds:?} seconds");
-			tokio::time::sleep(seconds).await;
-		}
+				let block_number = header.number;
 
-		let process_block_result = process_block(
-			&light_client,
-			&metrics,
-			&cfg,
-			pp.clone(),
-			&header,
-			received_at,
-			state.clone(),
-		)
-		.await;
-		let confidence = match process_block_result {
-			Ok(confidence) => confidence,
-			Err(error) => {
-				error!("Cannot process block: {error}");
-				if let Err(error) = channels.error_sender.send(error).await {
-					error!("Cannot send error message: {error}");
+				if let Err(error) = light_client.store_block_in_db(block_number, block) {
+					error!(block_number, "Cannot store block: {error:#}");
+					if let Err(error) = channels.error_sender.send(error).await {
+						error!("Cannot send error message: {error}");
+					}
+					return;
 				}
-				return;
-			},
-		};
 
-		let Ok(client_msg) = types::BlockVerified::try_from((header, confidence)) else {
-			error!("Cannot create message from header");
-			continue;
-		};
+				if confidence.is_some() {
+					state.lock().unwrap().confidence_achieved.set(block_number);
+				}
 
-		// notify dht-based application client
-		// that newly mined block has been received
-		if let Some(ref channel) = channels.block_sender {
-			if let Err(error) = channel.send(client_msg) {
-				error!("Cannot send block verified message: {error}");
-				continue;
-			}
+				if let Some(failure) = failure {
+					state.lock().unwrap().availability_failed.insert(block_number);
+					if let Err(error) = channels.failure_sender.send((block_number, failure)) {
+						error!("Cannot send availability failure message: {error}");
+					}
+				}
+
+				let Ok(client_msg) = types::BlockVerified::try_from((header, confidence)) else {
+					error!("Cannot create message from header");
+					continue;
+				};
+
+				// notify dht-based application client
+				// that newly mined block has been received
+				if let Some(ref channel) = channels.block_sender {
+					if let Err(error) = channel.send(client_msg) {
+						error!("Cannot send block verified message: {error}");
+					}
+				}
+			},
 		}
 	}
 }
@@ -487,8 +752,9 @@ pub async fn run(
 mod tests {
 	use super::rpc::cell_count_for_confidence;
 	use super::*;
+	use crate::api::v2::types::{block_status, BlockStatus};
 	use crate::telemetry;
-	use crate::types::RuntimeConfig;
+	use crate::types::{BlockRange, CellSource, ConfidenceCounts, RuntimeConfig};
 	use avail_subxt::{
 		api::runtime_types::avail_core::{
 			data_lookup::compact::CompactDataLookup,
@@ -499,6 +765,9 @@ mod tests {
 	};
 	use hex_literal::hex;
 	use kate_recovery::testnet;
+	use std::collections::BTreeSet;
+	use test_case::test_case;
+	use tokio::sync::mpsc::unbounded_channel;
 
 	#[test]
 	fn test_cell_count_for_confidence() {
@@ -518,10 +787,16 @@ mod tests {
 		);
 	}
 
+	#[test_case(None, true ; "No minimum DHT confidence")]
+	#[test_case(Some(50.0), false ; "DHT confidence below minimum")]
 	#[tokio::test]
-	async fn test_process_block_with_rpc() {
+	async fn test_process_block_with_rpc(min_dht_confidence: Option<f64>, achieved: bool) {
 		let mut mock_client = MockLightClient::new();
-		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		let mut cfg = LightClientConfig::from(&RuntimeConfig::default());
+		cfg.min_dht_confidence = min_dht_confidence;
 		let pp = Arc::new(testnet::public_params(1024));
 		let cells_fetched: Vec<Cell> = vec![];
 		let cells_unfetched = [
@@ -531,86 +806,9 @@ mod tests {
 			Position { row: 0, col: 1 },
 		]
 		.to_vec();
-		let header = Header {
-			parent_hash: hex!("c454470d840bc2583fcf881be4fd8a0f6daeac3a20d83b9fd4865737e56c9739")
-				.into(),
-			number: 57,
-			state_root: hex!("7dae455e5305263f29310c60c0cc356f6f52263f9f434502121e8a40d5079c32")
-				.into(),
-			extrinsics_root: hex!(
-				"bf1c73d4d09fa6a437a411a935ad3ec56a67a35e7b21d7676a5459b55b397ad4"
-			)
-			.into(),
-			digest: Digest { logs: vec![] },
-			extension: V1(HeaderExtension {
-				commitment: KateCommitment {
-					rows: 1,
-					cols: 4,
-					data_root: hex!(
-						"0000000000000000000000000000000000000000000000000000000000000000"
-					)
-					.into(),
-					commitment: [
-						128, 34, 252, 194, 232, 229, 27, 124, 216, 33, 253, 23, 251, 126, 112, 244,
-						7, 231, 73, 242, 0, 20, 5, 116, 175, 104, 27, 50, 45, 111, 127, 123, 202,
-						255, 63, 192, 243, 236, 62, 75, 104, 86, 36, 198, 134, 27, 182, 224, 128,
-						34, 252, 194, 232, 229, 27, 124, 216, 33, 253, 23, 251, 126, 112, 244, 7,
-						231, 73, 242, 0, 20, 5, 116, 175, 104, 27, 50, 45, 111, 127, 123, 202, 255,
-						63, 192, 243, 236, 62, 75, 104, 86, 36, 198, 134, 27, 182, 224,
-					]
-					.to_vec(),
-				},
-				app_lookup: CompactDataLookup {
-					size: 1,
-					index: vec![],
-				},
-			}),
-		};
-		let state = Arc::new(Mutex::new(State::default()));
+		let header = header(57);
 		let recv = Instant::now();
-		let kate_proof = [
-			Cell {
-				position: Position { row: 0, col: 2 },
-				content: [
-					183, 215, 10, 175, 218, 48, 236, 18, 30, 163, 215, 125, 205, 130, 176, 227,
-					133, 157, 194, 35, 153, 144, 141, 7, 208, 133, 170, 79, 27, 176, 202, 22, 111,
-					63, 107, 147, 93, 44, 82, 137, 78, 32, 161, 175, 214, 152, 125, 50, 247, 52,
-					138, 161, 52, 83, 193, 255, 17, 235, 98, 10, 88, 241, 25, 186, 3, 174, 139,
-					200, 128, 117, 255, 213, 200, 4, 46, 244, 219, 5, 131, 0,
-				],
-			},
-			Cell {
-				position: Position { row: 1, col: 1 },
-				content: [
-					172, 213, 85, 167, 89, 247, 11, 125, 149, 170, 217, 222, 86, 157, 11, 20, 154,
-					21, 173, 247, 193, 99, 189, 7, 225, 80, 156, 94, 83, 213, 217, 185, 113, 187,
-					112, 20, 170, 120, 50, 171, 52, 178, 209, 244, 158, 24, 129, 236, 83, 4, 110,
-					41, 9, 29, 26, 180, 156, 219, 69, 155, 148, 49, 78, 25, 165, 147, 150, 253,
-					251, 174, 49, 215, 191, 142, 169, 70, 17, 86, 218, 0,
-				],
-			},
-			Cell {
-				position: Position { row: 0, col: 3 },
-				content: [
-					132, 180, 92, 81, 128, 83, 245, 59, 206, 224, 200, 137, 236, 113, 109, 216,
-					161, 248, 236, 252, 252, 22, 140, 107, 203, 161, 33, 18, 100, 189, 157, 58, 7,
-					183, 146, 75, 57, 220, 84, 106, 203, 33, 142, 10, 130, 99, 90, 38, 85, 166,
-					211, 97, 111, 105, 21, 241, 123, 211, 193, 6, 254, 125, 169, 108, 252, 85, 49,
-					31, 54, 53, 79, 196, 5, 122, 206, 127, 226, 224, 70, 0,
-				],
-			},
-			Cell {
-				position: Position { row: 1, col: 3 },
-				content: [
-					132, 180, 92, 81, 128, 83, 245, 59, 206, 224, 200, 137, 236, 113, 109, 216,
-					161, 248, 236, 252, 252, 22, 140, 107, 203, 161, 33, 18, 100, 189, 157, 58, 7,
-					183, 146, 75, 57, 220, 84, 106, 203, 33, 142, 10, 130, 99, 90, 38, 85, 166,
-					211, 97, 111, 105, 21, 241, 123, 211, 193, 6, 254, 125, 169, 108, 252, 85, 49,
-					31, 54, 53, 79, 196, 5, 122, 206, 127, 226, 224, 70, 0,
-				],
-			},
-		]
-		.to_vec();
+		let kate_proof = cells();
 		mock_client
 			.expect_fetch_cells_from_dht()
 			.returning(move |_, _| {
@@ -622,12 +820,6 @@ mod tests {
 			let kate_proof = kate_proof.clone();
 			Box::pin(async move { Ok(kate_proof) })
 		});
-		mock_client
-			.expect_store_confidence_in_db()
-			.returning(|_, _| Ok(()));
-		mock_client
-			.expect_store_block_header_in_db()
-			.returning(|_, _| Ok(()));
 		mock_client
 			.expect_insert_rows_into_dht()
 			.returning(|_, _| Box::pin(async move { 1f32 }));
@@ -649,30 +841,158 @@ mod tests {
 		mock_metrics.expect_record().returning(|_| Ok(()));
 		mock_metrics.expect_set_multiaddress().returning(|_| ());
 		mock_metrics.expect_set_ip().returning(|_| ());
-		process_block(
+		let processed_block = process_block(
 			&mock_client,
 			&Arc::new(mock_metrics),
 			&cfg,
 			pp,
 			&header,
 			recv,
-			state,
 		)
 		.await
 		.unwrap();
+		assert_eq!(processed_block.confidence.is_some(), achieved);
+		assert_eq!(
+			processed_block.failure.map(|failure| failure.reason),
+			(!achieved).then_some(FailureReason::InsufficientDhtConfidence)
+		);
+		let block = processed_block.block;
+		assert!(block.header.is_some());
+		assert_eq!(block.confidence.is_some(), achieved);
+		assert_eq!(block.failure.is_some(), !achieved);
+		assert_eq!(block.confidence_counts.map(|counts| counts.dht), Some(0));
+		assert!(block.sampling.is_some());
 	}
 
 	#[tokio::test]
 	async fn test_process_block_without_rpc() {
 		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
 		let mut cfg = LightClientConfig::from(&RuntimeConfig::default());
 		cfg.disable_rpc = true;
 		let pp = Arc::new(testnet::public_params(1024));
 		let cells_unfetched: Vec<Position> = vec![];
-		let header = Header {
+		let header = header(57);
+		let recv = Instant::now();
+		let cells_fetched = cells();
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.returning(move |_, _| {
+				let fetched = cells_fetched.clone();
+				let unfetched = cells_unfetched.clone();
+				Box::pin(async move { (fetched, unfetched) })
+			});
+		mock_client.expect_get_kate_proof().never();
+		mock_client
+			.expect_insert_rows_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
+		mock_client
+			.expect_insert_cells_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
+		mock_client
+			.expect_shrink_kademlia_map()
+			.returning(|| Box::pin(async move { Ok(()) }));
+		mock_client.expect_get_multiaddress_and_ip().returning(|| {
+			Box::pin(async move { Ok(("multiaddress".to_string(), "ip".to_string())) })
+		});
+		mock_client
+			.expect_count_dht_entries()
+			.returning(|| Box::pin(async move { Ok(1) }));
+
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+		mock_metrics.expect_set_multiaddress().returning(|_| ());
+		mock_metrics.expect_set_ip().returning(|_| ());
+		let block = process_block(
+			&mock_client,
+			&Arc::new(mock_metrics),
+			&cfg,
+			pp,
+			&header,
+			recv,
+		)
+		.await
+		.unwrap()
+		.block;
+		assert!(block.header.is_some());
+		assert!(block.confidence.is_some());
+		assert!(block.sampling.is_some());
+	}
+
+	#[tokio::test]
+	async fn test_process_block_with_additional_dht_sampling() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let pp = Arc::new(testnet::public_params(1024));
+		let header = header(57);
+		let recv = Instant::now();
+		let first_round = cells()[..2].to_vec();
+		let second_round = cells()[2..].to_vec();
+		let mut sequence = mockall::Sequence::new();
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.withf(|positions, _| positions.len() == 4)
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(move |positions, _| {
+				let fetched = first_round.clone();
+				let unfetched = positions[2..].to_vec();
+				Box::pin(async move { (fetched, unfetched) })
+			});
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.withf(|positions, _| positions.len() == 2)
+			.times(1)
+			.in_sequence(&mut sequence)
+			.returning(move |_, _| {
+				let fetched = second_round.clone();
+				Box::pin(async move { (fetched, vec![]) })
+			});
+		mock_client.expect_get_kate_proof().never();
+		mock_client
+			.expect_insert_cells_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
+		mock_client
+			.expect_shrink_kademlia_map()
+			.returning(|| Box::pin(async move { Ok(()) }));
+		mock_client.expect_get_multiaddress_and_ip().returning(|| {
+			Box::pin(async move { Ok(("multiaddress".to_string(), "ip".to_string())) })
+		});
+		mock_client
+			.expect_count_dht_entries()
+			.returning(|| Box::pin(async move { Ok(1) }));
+
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+		mock_metrics.expect_set_multiaddress().returning(|_| ());
+		mock_metrics.expect_set_ip().returning(|_| ());
+		let block = process_block(
+			&mock_client,
+			&Arc::new(mock_metrics),
+			&cfg,
+			pp,
+			&header,
+			recv,
+		)
+		.await
+		.unwrap()
+		.block;
+		assert!(block.confidence.is_some());
+		assert!(block.sampling.is_some());
+	}
+
+	fn header(number: u32) -> Header {
+		Header {
 			parent_hash: hex!("c454470d840bc2583fcf881be4fd8a0f6daeac3a20d83b9fd4865737e56c9739")
 				.into(),
-			number: 57,
+			number,
 			state_root: hex!("7dae455e5305263f29310c60c0cc356f6f52263f9f434502121e8a40d5079c32")
 				.into(),
 			extrinsics_root: hex!(
@@ -703,10 +1023,11 @@ mod tests {
 					index: vec![],
 				},
 			}),
-		};
-		let state = Arc::new(Mutex::new(State::default()));
-		let recv = Instant::now();
-		let cells_fetched = [
+		}
+	}
+
+	fn cells() -> Vec<Cell> {
+		vec![
 			Cell {
 				position: Position { row: 0, col: 2 },
 				content: [
@@ -748,26 +1069,86 @@ mod tests {
 				],
 			},
 		]
-		.to_vec();
+	}
+
+	#[tokio::test]
+	async fn test_process_block_with_cached_cells() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| cells()[..2].to_vec());
 		mock_client
 			.expect_fetch_cells_from_dht()
-			.returning(move |_, _| {
-				let fetched = cells_fetched.clone();
-				let unfetched = cells_unfetched.clone();
-				Box::pin(async move { (fetched, unfetched) })
-			});
+			.returning(|_, _| Box::pin(async move { (cells()[2..].to_vec(), vec![]) }));
 		mock_client.expect_get_kate_proof().never();
 		mock_client
-			.expect_store_confidence_in_db()
-			.returning(|_, _| Ok(()));
+			.expect_insert_cells_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
 		mock_client
-			.expect_store_block_header_in_db()
-			.returning(|_, _| Ok(()));
+			.expect_shrink_kademlia_map()
+			.returning(|| Box::pin(async move { Ok(()) }));
+		mock_client.expect_get_multiaddress_and_ip().returning(|| {
+			Box::pin(async move { Ok(("multiaddress".to_string(), "ip".to_string())) })
+		});
 		mock_client
-			.expect_insert_rows_into_dht()
-			.returning(|_, _| Box::pin(async move { 1f32 }));
+			.expect_count_dht_entries()
+			.returning(|| Box::pin(async move { Ok(1) }));
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+		mock_metrics.expect_set_multiaddress().returning(|_| ());
+		mock_metrics.expect_set_ip().returning(|_| ());
+
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let pp = Arc::new(testnet::public_params(1024));
+		let processed_block = process_block(
+			&mock_client,
+			&Arc::new(mock_metrics),
+			&cfg,
+			pp,
+			&header(1),
+			Instant::now(),
+		)
+		.await
+		.unwrap();
+		assert!(processed_block.confidence.is_some());
+		let block = processed_block.block;
+		let sources = block.sampling.as_ref().map(|sampling| {
+			let count = |source| {
+				let cells = sampling.cells.iter();
+				cells.filter(|cell| cell.source == source).count()
+			};
+			(count(CellSource::Cache), count(CellSource::Dht))
+		});
+		assert_eq!(block.confidence, Some(4));
+		assert_eq!(
+			block.confidence_counts,
+			Some(ConfidenceCounts { dht: 2, rpc: 0 })
+		);
+		assert_eq!(sources, Some((2, 2)));
+	}
+
+	#[tokio::test]
+	async fn test_process_block_with_invalid_dht_cell() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		mock_client.expect_fetch_cells_from_dht().returning(|_, _| {
+			let mut dht_cells = cells();
+			// Content of another cell doesn't match the proof of the sampled position
+			dht_cells[0].content = dht_cells[2].content;
+			Box::pin(async move { (dht_cells, vec![]) })
+		});
+		// Cell which failed verification is fetched from RPC instead
+		mock_client
+			.expect_get_kate_proof()
+			.withf(|_, positions| *positions == [Position { row: 0, col: 2 }])
+			.times(1)
+			.returning(|_, _| Box::pin(async move { Ok(cells()[..1].to_vec()) }));
 		mock_client
 			.expect_insert_cells_into_dht()
+			.withf(|_, rpc_cells| rpc_cells.len() == 1)
 			.returning(|_, _| Box::pin(async move { 1f32 }));
 		mock_client
 			.expect_shrink_kademlia_map()
@@ -778,22 +1159,346 @@ mod tests {
 		mock_client
 			.expect_count_dht_entries()
 			.returning(|| Box::pin(async move { Ok(1) }));
-
 		let mut mock_metrics = telemetry::MockMetrics::new();
 		mock_metrics.expect_count().returning(|_| ());
 		mock_metrics.expect_record().returning(|_| Ok(()));
 		mock_metrics.expect_set_multiaddress().returning(|_| ());
 		mock_metrics.expect_set_ip().returning(|_| ());
-		process_block(
+
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let pp = Arc::new(testnet::public_params(1024));
+		let processed_block = process_block(
 			&mock_client,
 			&Arc::new(mock_metrics),
 			&cfg,
 			pp,
-			&header,
-			recv,
-			state,
+			&header(1),
+			Instant::now(),
 		)
 		.await
 		.unwrap();
+		assert!(processed_block.confidence.is_some());
+		assert!(processed_block.failure.is_none());
+		let block = processed_block.block;
+		assert_eq!(block.confidence, Some(4));
+		assert_eq!(
+			block.confidence_counts,
+			Some(ConfidenceCounts { dht: 3, rpc: 1 })
+		);
+	}
+
+	#[tokio::test]
+	async fn test_run_dht_confidence_below_minimum() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		// Cells of the second block are fetched from RPC, and cells of other blocks from DHT
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.returning(|positions, block_number| {
+				let result = if block_number == 2 {
+					(vec![], positions.to_vec())
+				} else {
+					(cells(), vec![])
+				};
+				Box::pin(async move { result })
+			});
+		mock_client
+			.expect_get_kate_proof()
+			.returning(|_, _| Box::pin(async move { Ok(cells()) }));
+		mock_client
+			.expect_store_block_in_db()
+			.withf(|block_number, block| {
+				let failure = block.failure.map(|failure| failure.reason);
+				match block_number {
+					2 => {
+						block.confidence.is_none()
+							&& failure == Some(FailureReason::InsufficientDhtConfidence)
+					},
+					_ => block.confidence.is_some() && failure.is_none(),
+				}
+			})
+			.times(3)
+			.returning(|_, _| Ok(()));
+		mock_client
+			.expect_insert_cells_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
+		mock_client
+			.expect_shrink_kademlia_map()
+			.returning(|| Box::pin(async move { Ok(()) }));
+		mock_client.expect_get_multiaddress_and_ip().returning(|| {
+			Box::pin(async move { Ok(("multiaddress".to_string(), "ip".to_string())) })
+		});
+		mock_client
+			.expect_count_dht_entries()
+			.returning(|| Box::pin(async move { Ok(1) }));
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+		mock_metrics.expect_set_multiaddress().returning(|_| ());
+		mock_metrics.expect_set_ip().returning(|_| ());
+
+		let mut cfg = LightClientConfig::from(&RuntimeConfig::default());
+		cfg.min_dht_confidence = Some(50.0);
+		let pp = Arc::new(testnet::public_params(1024));
+		let state = Arc::new(Mutex::new(State::default()));
+
+		let (header_sender, header_receiver) = broadcast::channel(10);
+		let (block_sender, mut block_receiver) = broadcast::channel(10);
+		let (error_sender, _error_receiver) = tokio::sync::mpsc::channel(1);
+		let (failure_sender, mut failure_receiver) = broadcast::channel(10);
+		let (missed_block_sender, _missed_block_receiver) = unbounded_channel();
+		let channels = Channels {
+			block_sender: Some(block_sender),
+			header_receiver,
+			error_sender,
+			failure_sender,
+			missed_block_sender,
+		};
+
+		let handle = tokio::spawn(run(
+			mock_client,
+			cfg,
+			pp,
+			Arc::new(mock_metrics),
+			state.clone(),
+			channels,
+		));
+
+		for number in [1, 2, 3] {
+			header_sender
+				.send((header(number), Instant::now()))
+				.unwrap();
+		}
+		for (number, achieved) in [(1, true), (2, false), (3, true)] {
+			let timeout = std::time::Duration::from_secs(5);
+			let block = tokio::time::timeout(timeout, block_receiver.recv())
+				.await
+				.unwrap()
+				.unwrap();
+			assert_eq!(block.block_num, number);
+			assert_eq!(block.confidence.is_some(), achieved);
+		}
+		let (block_number, failure) = failure_receiver.recv().await.unwrap();
+		assert_eq!(block_number, 2);
+		assert_eq!(failure.reason, FailureReason::InsufficientDhtConfidence);
+		handle.abort();
+
+		let mut state = state.lock().unwrap();
+		assert_eq!(state.availability_failed, BTreeSet::from([2]));
+		state.latest = 3;
+		state.header_verified = Some(BlockRange { first: 1, last: 3 });
+		// Confidence achieved range spans the gated block, but it is still reported as failed
+		assert!(state.confidence_achieved.contains(2));
+		assert_eq!(block_status(&None, &state, 2), Some(BlockStatus::Failed));
+		assert_eq!(
+			block_status(&None, &state, 3),
+			Some(BlockStatus::VerifyingData)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_run_lagged() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.returning(|_, _| Box::pin(async move { (cells(), vec![]) }));
+		mock_client
+			.expect_get_kate_proof()
+			.returning(|_, _| Box::pin(async move { Ok(cells()) }));
+		mock_client
+			.expect_store_block_in_db()
+			.withf(|block_number, _| *block_number == 3)
+			.times(1)
+			.returning(|_, _| Ok(()));
+		mock_client
+			.expect_insert_cells_into_dht()
+			.returning(|_, _| Box::pin(async move { 1f32 }));
+		mock_client
+			.expect_shrink_kademlia_map()
+			.returning(|| Box::pin(async move { Ok(()) }));
+		mock_client.expect_get_multiaddress_and_ip().returning(|| {
+			Box::pin(async move { Ok(("multiaddress".to_string(), "ip".to_string())) })
+		});
+		mock_client
+			.expect_count_dht_entries()
+			.returning(|| Box::pin(async move { Ok(1) }));
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+		mock_metrics.expect_set_multiaddress().returning(|_| ());
+		mock_metrics.expect_set_ip().returning(|_| ());
+
+		let cfg = LightClientConfig::from(&RuntimeConfig::default());
+		let pp = Arc::new(testnet::public_params(1024));
+		let state = Arc::new(Mutex::new(State::default()));
+
+		let (header_sender, header_receiver) = broadcast::channel(1);
+		let (block_sender, mut block_receiver) = broadcast::channel(10);
+		let (error_sender, _error_receiver) = tokio::sync::mpsc::channel(1);
+		let (failure_sender, _failure_receiver) = broadcast::channel(10);
+		let (missed_block_sender, mut missed_block_receiver) = unbounded_channel();
+		let channels = Channels {
+			block_sender: Some(block_sender),
+			header_receiver,
+			error_sender,
+			failure_sender,
+			missed_block_sender,
+		};
+
+		for number in [1, 2, 3] {
+			header_sender
+				.send((header(number), Instant::now()))
+				.unwrap();
+		}
+
+		let handle = tokio::spawn(run(
+			mock_client,
+			cfg,
+			pp,
+			Arc::new(mock_metrics),
+			state.clone(),
+			channels,
+		));
+
+		let timeout = std::time::Duration::from_secs(5);
+		let block = tokio::time::timeout(timeout, block_receiver.recv())
+			.await
+			.unwrap()
+			.unwrap();
+		// Only the last block is processed, skipped blocks are scheduled for the sync client
+		assert_eq!(block.block_num, 3);
+		assert_eq!(missed_block_receiver.try_recv().unwrap(), 1);
+		assert_eq!(missed_block_receiver.try_recv().unwrap(), 2);
+		assert!(missed_block_receiver.try_recv().is_err());
+		handle.abort();
+
+		let state = state.lock().unwrap();
+		let gaps: Vec<_> = state.gaps.iter().map(|gap| (gap.first, gap.last)).collect();
+		assert_eq!(gaps, vec![(1, 2)]);
+	}
+
+	#[tokio::test]
+	async fn test_run_closed() {
+		let (header_sender, header_receiver) = broadcast::channel(1);
+		let (error_sender, mut error_receiver) = tokio::sync::mpsc::channel(1);
+		let (failure_sender, _failure_receiver) = broadcast::channel(10);
+		let (missed_block_sender, _missed_block_receiver) = unbounded_channel();
+		let channels = Channels {
+			block_sender: None,
+			header_receiver,
+			error_sender,
+			failure_sender,
+			missed_block_sender,
+		};
+		drop(header_sender);
+
+		run(
+			MockLightClient::new(),
+			LightClientConfig::from(&RuntimeConfig::default()),
+			Arc::new(testnet::public_params(1024)),
+			Arc::new(telemetry::MockMetrics::new()),
+			Arc::new(Mutex::new(State::default())),
+			channels,
+		)
+		.await;
+
+		assert!(error_receiver.recv().await.is_some());
+	}
+
+	#[tokio::test]
+	async fn test_run_in_block_order() {
+		let mut mock_client = MockLightClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		mock_client
+			.expect_fetch_cells_from_dht()
+			.returning(|positions, block_number| {
+				let unfetched = positions.to_vec();
+				Box::pin(async move {
+					// First block is processed slower than the following one
+					if block_number == 1 {
+						tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+					}
+					(vec![], unfetched)
+				})
+			});
+		mock_client
+			.expect_store_block_in_db()
+			.withf(|_, block| {
+				// All sampled cells are recorded as unavailable
+				let unavailable = block.sampling.as_ref().map(|sampling| {
+					!sampling.cells.is_empty()
+						&& sampling
+							.cells
+							.iter()
+							.all(|cell| cell.source == CellSource::Unavailable)
+				});
+				block.header.is_some()
+					&& block.confidence.is_none()
+					&& block.failure.map(|failure| failure.reason)
+						== Some(FailureReason::CellsUnavailable)
+					&& unavailable == Some(true)
+			})
+			.times(2)
+			.returning(|_, _| Ok(()));
+		let mut mock_metrics = telemetry::MockMetrics::new();
+		mock_metrics.expect_count().returning(|_| ());
+		mock_metrics.expect_record().returning(|_| Ok(()));
+
+		let mut cfg = LightClientConfig::from(&RuntimeConfig::default());
+		cfg.disable_rpc = true;
+		let pp = Arc::new(testnet::public_params(1024));
+		let state = Arc::new(Mutex::new(State::default()));
+
+		let (header_sender, header_receiver) = broadcast::channel(10);
+		let (block_sender, mut block_receiver) = broadcast::channel(10);
+		let (error_sender, _error_receiver) = tokio::sync::mpsc::channel(1);
+		let (failure_sender, mut failure_receiver) = broadcast::channel(10);
+		let (missed_block_sender, _missed_block_receiver) = unbounded_channel();
+		let channels = Channels {
+			block_sender: Some(block_sender),
+			header_receiver,
+			error_sender,
+			failure_sender,
+			missed_block_sender,
+		};
+
+		let handle = tokio::spawn(run(
+			mock_client,
+			cfg,
+			pp,
+			Arc::new(mock_metrics),
+			state.clone(),
+			channels,
+		));
+
+		for number in [1, 2] {
+			header_sender
+				.send((header(number), Instant::now()))
+				.unwrap();
+		}
+		for number in [1, 2] {
+			let timeout = std::time::Duration::from_secs(5);
+			let block = tokio::time::timeout(timeout, block_receiver.recv())
+				.await
+				.unwrap()
+				.unwrap();
+			assert_eq!(block.block_num, number);
+			assert!(block.confidence.is_none());
+
+			let (block_number, failure) = failure_receiver.recv().await.unwrap();
+			assert_eq!(block_number, number);
+			assert_eq!(failure.reason, FailureReason::CellsUnavailable);
+		}
+		handle.abort();
+		let state = state.lock().unwrap();
+		assert_eq!(state.availability_failed, BTreeSet::from([1, 2]));
+		assert!(state.confidence_achieved.is_none());
 	}
 }
@@ -6,7 +6,7 @@ use kate_recovery::{
 	matrix::{Dimensions, Position, RowIndex},
 };
 use libp2p::{
-	kad::{record::Key, PeerRecord, Quorum, Record},
+	kad::{record::Key, GetRecordError, PeerRecord, Quorum, Record},
 	multiaddr::Protocol,
 	Multiaddr, PeerId,
 };
@@ -17,6 +17,17 @@ use std::{
 use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, trace};
 
+use crate::{types::RetryConfig, utils::retry};
+
+/// Returns true if DHT query failed without reaching enough peers, and can be retried.
+/// Records not found in the DHT are not retried.
+fn is_transient(error: &anyhow::Error) -> bool {
+	matches!(
+		error.downcast_ref::<GetRecordError>(),
+		Some(GetRecordError::QuorumFailed { .. } | GetRecordError::Timeout { .. })
+	)
+}
+
 #[derive(Clone)]
 pub struct Client {
 	command_sender: mpsc::Sender<Command>,
@@ -26,6 +37,8 @@ pub struct Client {
 	ttl: u64,
 	/// Number of records to be put in DHT simultaneously
 	put_batch_size: usize,
+	/// Retry policy for fetching records from DHT
+	retry: RetryConfig,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -73,12 +86,14 @@ impl Client {
 		dht_parallelization_limit: usize,
 		ttl: u64,
 		put_batch_size: usize,
+		retry: RetryConfig,
 	) -> Self {
 		Self {
 			command_sender: sender,
 			dht_parallelization_limit,
 			ttl,
 			put_batch_size,
+			retry,
 		}
 	}
 
@@ -198,7 +213,11 @@ impl Client {
 
 		trace!("Getting DHT record for reference {}", reference);
 
-		match self.get_kad_record(record_key).await {
+		match retry(&self.retry, is_transient, || {
+			self.get_kad_record(record_key.clone())
+		})
+		.await
+		{
 			Ok(peer_record) => {
 				debug!("Fetched cell {reference} from the DHT");
 
@@ -230,7 +249,11 @@ impl Client {
 
 		trace!("Getting DHT record for reference {}", reference);
 
-		match self.get_kad_record(record_key).await {
+		match retry(&self.retry, is_transient, || {
+			self.get_kad_record(record_key.clone())
+		})
+		.await
+		{
 			Ok(peer_record) => Some((row_index.0, peer_record.record.value)),
 			Err(error) => {
 				debug!("Row {reference} not found in the DHT: {error}");
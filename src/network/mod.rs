@@ -29,7 +29,7 @@ mod mem_store;
 pub mod network_analyzer;
 pub use client::Client;
 
-use crate::types::{LibP2PConfig, SecretKey};
+use crate::types::{LibP2PConfig, RetryConfig, SecretKey};
 
 #[derive(NetworkBehaviour)]
 #[behaviour(event_process = false)]
@@ -48,6 +48,7 @@ pub fn init(
 	dht_parallelization_limit: usize,
 	ttl: u64,
 	put_batch_size: usize,
+	retry: RetryConfig,
 	is_fat_client: bool,
 	id_keys: libp2p::identity::Keypair,
 ) -> Result<(Client, EventLoop)> {
@@ -151,6 +152,7 @@ pub fn init(
 			dht_parallelization_limit,
 			ttl,
 			put_batch_size,
+			retry,
 		),
 		EventLoop::new(
 			swarm,
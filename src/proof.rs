@@ -1,25 +1,248 @@
-//! Parallelized proof verification
+//! Parallelized proof verification, loading of public parameters, and verification of data root inclusion proofs
 
-use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use anyhow::{anyhow, Context};
+use dusk_bytes::Serializable;
+use dusk_plonk::{
+	bls12_381::{G1Affine, G1Projective},
+	commitment_scheme::kzg10::{Commitment, Proof, PublicParameters},
+	fft::EvaluationDomain,
+	prelude::BlsScalar,
+};
 use itertools::{Either, Itertools};
 use kate_recovery::{
 	data::Cell,
 	matrix::{Dimensions, Position},
 	proof,
 };
-use std::sync::{mpsc::channel, Arc};
-use tracing::error;
+use once_cell::sync::Lazy;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use sp_core::{keccak_256, H256};
+use std::{
+	fs,
+	sync::{mpsc::channel, Arc, Mutex},
+};
+use threadpool::ThreadPool;
+use tracing::{error, info};
+
+use crate::types::DataProof;
+
+/// Embedded public parameters of the network trusted setup
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PublicParamsPreset {
+	/// Public parameters of the testnet trusted setup, used by local and devnet networks
+	#[default]
+	Testnet,
+}
+
+impl PublicParamsPreset {
+	pub fn public_params(&self) -> PublicParameters {
+		match self {
+			PublicParamsPreset::Testnet => kate_recovery::testnet::public_params(1024),
+		}
+	}
+}
+
+/// Returns hex encoded Blake2-256 hash of public parameters
+pub fn public_params_hash(public_parameters: &PublicParameters) -> String {
+	hex::encode(sp_core::blake2_256(&public_parameters.to_raw_var_bytes()))
+}
+
+/// Loads public parameters from the file, or embedded preset public parameters if path is not set.
+/// Fails if loaded public parameters hash doesn't match the expected hash. If expected hash is not set,
+/// public parameters loaded from the file are expected to match the preset public parameters.
+pub fn load_public_params(
+	path: Option<&str>,
+	preset: PublicParamsPreset,
+	expected_hash: Option<&str>,
+) -> anyhow::Result<PublicParameters> {
+	let (public_parameters, expected_hash, source) = match path {
+		Some(path) => {
+			let bytes = fs::read(path)
+				.with_context(|| format!("Failed to read public parameters from {path}"))?;
+			let public_parameters = PublicParameters::from_slice(&bytes)
+				.map_err(|error| anyhow!("Failed to decode public parameters: {error:?}"))?;
+			let expected_hash = expected_hash
+				.map(|hash| hash.to_string())
+				.unwrap_or_else(|| public_params_hash(&preset.public_params()));
+			(
+				public_parameters,
+				Some(expected_hash),
+				format!("file {path}"),
+			)
+		},
+		None => (
+			preset.public_params(),
+			expected_hash.map(|hash| hash.to_string()),
+			format!("{preset:?} preset"),
+		),
+	};
+
+	let hash = public_params_hash(&public_parameters);
+	if let Some(expected_hash) = expected_hash {
+		if !hash.eq_ignore_ascii_case(expected_hash.trim_start_matches("0x")) {
+			return Err(anyhow!(
+				"Public parameters hash {hash} doesn't match expected hash {expected_hash}"
+			));
+		}
+	}
+
+	info!("Loaded public parameters from {source} with hash {hash}");
+	Ok(public_parameters)
+}
+
+/// Maximum number of cells verified in a single thread pool job
+const MAX_JOB_SIZE: usize = 64;
+
+/// Thread pool shared by all proof verifications, with one thread per CPU
+static VERIFICATION_POOL: Lazy<Mutex<ThreadPool>> =
+	Lazy::new(|| Mutex::new(ThreadPool::new(num_cpus::get())));
+
+fn verification_pool() -> ThreadPool {
+	VERIFICATION_POOL
+		.lock()
+		.expect("Verification pool lock should be acquired")
+		.clone()
+}
+
+fn partition_verified(
+	results: impl IntoIterator<Item = (Position, bool)>,
+) -> (Vec<Position>, Vec<Position>) {
+	results
+		.into_iter()
+		.partition_map(|(position, is_verified)| match is_verified {
+			true => Either::Left(position),
+			false => Either::Right(position),
+		})
+}
+
+/// Checks proofs of cells from the same row against the row commitment with a single pairing check.
+/// Each cell equation `e(C - y·G + z·W, H) = e(W, τH)` is multiplied by a random scalar and the equations
+/// are summed, so the aggregated equation holds for an invalid proof only with negligible probability.
+/// Returns `None` if any cell cannot be decoded.
+fn verify_row_batch(
+	public_parameters: &PublicParameters,
+	dimensions: Dimensions,
+	commitment: &[u8; 48],
+	cells: &[Cell],
+) -> Option<bool> {
+	let commitment = G1Affine::from_bytes(commitment).ok()?;
+	let points = EvaluationDomain::new(dimensions.width())
+		.ok()?
+		.elements()
+		.collect::<Vec<_>>();
 
-/// Verifies proofs for given block, cells and commitments
+	let mut witness = G1Projective::identity();
+	let mut polynomial = G1Projective::identity();
+	let mut evaluation = BlsScalar::zero();
+
+	for cell in cells {
+		let proof: [u8; 48] = cell.content[..48].try_into().ok()?;
+		let data: [u8; 32] = cell.content[48..].try_into().ok()?;
+		let cell_witness = G1Affine::from_bytes(&proof).ok()?;
+		let cell_evaluation = BlsScalar::from_bytes(&data).ok()?;
+		let point = points.get(cell.position.col as usize)?;
+
+		let random = BlsScalar::random(&mut OsRng);
+		witness += cell_witness * random;
+		polynomial += commitment * random + cell_witness * (random * point);
+		evaluation += cell_evaluation * random;
+	}
+
+	// Aggregated equation `e(ΣrC - Σry·G + ΣrzW, H) = e(ΣrW, τH)` is an opening at zero
+	let proof = Proof {
+		commitment_to_witness: Commitment::from(G1Affine::from(witness)),
+		evaluated_point: evaluation,
+		commitment_to_polynomial: Commitment::from(G1Affine::from(polynomial)),
+	};
+	Some(
+		public_parameters
+			.opening_key()
+			.check(BlsScalar::zero(), proof),
+	)
+}
+
+/// Verifies cells from the same row against the row commitment.
+/// All cell proofs are checked at once with an aggregated pairing check, and only if the aggregated
+/// check fails, each cell proof is verified separately to find the invalid cells.
+pub fn verify_row_cells(
+	public_parameters: &PublicParameters,
+	dimensions: Dimensions,
+	commitment: &[u8; 48],
+	cells: &[Cell],
+) -> Result<Vec<(Position, bool)>, proof::Error> {
+	if cells.is_empty() {
+		return Ok(vec![]);
+	}
+
+	if verify_row_batch(public_parameters, dimensions, commitment, cells) == Some(true) {
+		return Ok(cells.iter().map(|cell| (cell.position, true)).collect());
+	}
+
+	cells
+		.iter()
+		.map(|cell| {
+			proof::verify(public_parameters, dimensions, commitment, cell)
+				.map(|is_verified| (cell.position, is_verified))
+		})
+		.collect()
+}
+
+/// Verifies proofs for given block, cells and commitments.
+/// Cells are grouped into jobs per row commitment, which are verified on the shared thread pool
+/// with a single aggregated pairing check per job (see [`verify_row_cells`]).
 pub fn verify(
 	block_num: u32,
 	dimensions: Dimensions,
 	cells: &[Cell],
 	commitments: &[[u8; 48]],
 	public_parameters: Arc<PublicParameters>,
+) -> Result<(Vec<Position>, Vec<Position>), proof::Error> {
+	let pool = verification_pool();
+	let (tx, rx) = channel::<Result<Vec<(Position, bool)>, proof::Error>>();
+
+	let rows = cells
+		.iter()
+		.cloned()
+		.into_group_map_by(|cell| cell.position.row);
+	let mut jobs_count = 0;
+
+	for (row, row_cells) in rows {
+		let commitment = commitments[row as usize];
+
+		for job_cells in row_cells.chunks(MAX_JOB_SIZE) {
+			let tx = tx.clone();
+			let job_cells = job_cells.to_vec();
+			let public_parameters = public_parameters.clone();
+			jobs_count += 1;
+
+			pool.execute(move || {
+				let result =
+					verify_row_cells(&public_parameters, dimensions, &commitment, &job_cells);
+				if let Err(error) = tx.send(result) {
+					error!(block_num, "Failed to send proofs verified message: {error}");
+				}
+			});
+		}
+	}
+
+	let results = rx.iter().take(jobs_count).collect::<Result<Vec<_>, _>>()?;
+
+	Ok(partition_verified(results.into_iter().flatten()))
+}
+
+/// Verifies proofs for given block, cells and commitments, each cell in a separate job
+/// on the newly created thread pool. Used as a baseline in verification benchmarks.
+pub fn verify_unbatched(
+	block_num: u32,
+	dimensions: Dimensions,
+	cells: &[Cell],
+	commitments: &[[u8; 48]],
+	public_parameters: Arc<PublicParameters>,
 ) -> Result<(Vec<Position>, Vec<Position>), proof::Error> {
 	let cpus = num_cpus::get();
-	let pool = threadpool::ThreadPool::new(cpus);
+	let pool = ThreadPool::new(cpus);
 	let (tx, rx) = channel::<(Position, Result<bool, proof::Error>)>();
 
 	for cell in cells {
@@ -37,16 +260,247 @@ pub fn verify(
 		});
 	}
 
-	let (verified, unverified): (Vec<_>, Vec<_>) = rx
+	let results = rx
 		.iter()
 		.take(cells.len())
 		.map(|(position, result)| result.map(|is_verified| (position, is_verified)))
-		.collect::<Result<Vec<(Position, bool)>, _>>()?
-		.into_iter()
-		.partition_map(|(position, is_verified)| match is_verified {
-			true => Either::Left(position),
-			false => Either::Right(position),
-		});
+		.collect::<Result<Vec<(Position, bool)>, _>>()?;
+
+	Ok(partition_verified(results))
+}
+
+/// Verifies that the data proof leaf is committed under the given data root.
+/// Merkle tree is built by the node from Keccak-256 hashes of data transactions,
+/// where the last node on the level without a sibling is promoted to the next level.
+pub fn verify_data_proof(data_root: H256, data_proof: &DataProof) -> bool {
+	let DataProof {
+		root,
+		proof,
+		number_of_leaves,
+		leaf_index,
+		leaf,
+	} = data_proof;
+
+	if *root != data_root || leaf_index >= number_of_leaves {
+		return false;
+	}
+
+	let mut position = *leaf_index;
+	let mut width = *number_of_leaves;
+	let computed = proof.iter().fold(*leaf, |node, sibling| {
+		let hash = if position % 2 == 1 || position + 1 == width {
+			keccak_256(&[sibling.as_bytes(), node.as_bytes()].concat())
+		} else {
+			keccak_256(&[node.as_bytes(), sibling.as_bytes()].concat())
+		};
+		position /= 2;
+		width = (width - 1) / 2 + 1;
+		hash.into()
+	});
+
+	computed == data_root
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		load_public_params, public_params_hash, verify, verify_data_proof, verify_row_batch,
+		verify_unbatched, PublicParamsPreset, MAX_JOB_SIZE,
+	};
+	use crate::types::DataProof;
+	use kate_recovery::{
+		data::Cell,
+		matrix::{Dimensions, Position},
+		testnet,
+	};
+	use sp_core::{keccak_256, H256};
+	use std::{io::Write, sync::Arc};
+	use tempfile::NamedTempFile;
+	use test_case::test_case;
+
+	const COMMITMENT: [u8; 48] = [
+		181, 10, 104, 251, 33, 171, 87, 192, 13, 195, 93, 127, 215, 78, 114, 192, 95, 92, 167, 10,
+		49, 17, 20, 204, 222, 102, 70, 218, 173, 18, 30, 49, 232, 10, 137, 187, 186, 216, 97, 140,
+		16, 33, 52, 56, 170, 208, 118, 242,
+	];
+
+	/// Contents of cells in columns 0 and 3 of the first row
+	const CELL_CONTENTS: [[u8; 80]; 2] = [
+		[
+			183, 56, 112, 134, 157, 186, 15, 255, 245, 173, 188, 37, 165, 224, 226, 80, 196, 137,
+			235, 233, 154, 4, 110, 142, 26, 95, 150, 132, 61, 23, 202, 212, 101, 6, 235, 6, 102,
+			188, 206, 147, 36, 121, 128, 63, 240, 37, 200, 236, 4, 44, 40, 4, 3, 0, 11, 35, 249,
+			222, 81, 135, 1, 128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+		],
+		[
+			150, 6, 83, 12, 56, 17, 0, 225, 186, 238, 151, 181, 116, 1, 34, 240, 174, 192, 98, 201,
+			60, 208, 50, 215, 90, 231, 2, 27, 17, 204, 140, 30, 213, 253, 200, 176, 72, 98, 121,
+			25, 239, 76, 230, 154, 121, 246, 142, 37, 85, 184, 201, 218, 107, 88, 0, 87, 199, 169,
+			98, 172, 4, 140, 151, 65, 162, 162, 190, 205, 20, 95, 67, 114, 73, 59, 170, 52, 243,
+			140, 237, 0,
+		],
+	];
+
+	fn sorted(positions: Vec<Position>) -> Vec<(u32, u16)> {
+		let mut positions: Vec<_> = positions
+			.into_iter()
+			.map(|position| (position.row, position.col))
+			.collect();
+		positions.sort();
+		positions
+	}
+
+	/// Writes public parameters into the unique temporary file, removed when the file is dropped
+	fn public_params_file(size: usize) -> NamedTempFile {
+		let mut file = NamedTempFile::new().unwrap();
+		file.write_all(&testnet::public_params(size).to_var_bytes())
+			.unwrap();
+		file
+	}
+
+	#[test]
+	fn load_public_params_from_file() {
+		let preset = PublicParamsPreset::Testnet;
+		let hash = public_params_hash(&testnet::public_params(1024));
+		let file = public_params_file(1024);
+		let path = file.path().to_str().unwrap();
+
+		let loaded = load_public_params(Some(path), preset, Some(&hash)).unwrap();
+		assert_eq!(public_params_hash(&loaded), hash);
+		// Preset public parameters hash is expected by default
+		assert!(load_public_params(Some(path), preset, None).is_ok());
+		assert!(load_public_params(Some(path), preset, Some("00")).is_err());
+		assert!(load_public_params(Some("missing_public_params.bin"), preset, None).is_err());
+	}
 
-	Ok((verified, unverified))
+	#[test]
+	fn load_public_params_from_file_not_matching_preset() {
+		let preset = PublicParamsPreset::Testnet;
+		let file = public_params_file(256);
+		let path = file.path().to_str().unwrap();
+		let hash = public_params_hash(&testnet::public_params(256));
+
+		assert!(load_public_params(Some(path), preset, None).is_err());
+		assert!(load_public_params(Some(path), preset, Some(&hash)).is_ok());
+	}
+
+	#[test]
+	fn load_embedded_public_params() {
+		let preset = PublicParamsPreset::Testnet;
+		let hash = public_params_hash(&testnet::public_params(1024));
+		assert!(load_public_params(None, preset, None).is_ok());
+		assert!(load_public_params(None, preset, Some(&format!("0x{hash}"))).is_ok());
+		assert!(load_public_params(None, preset, Some("00")).is_err());
+	}
+
+	#[test]
+	fn verify_matches_unbatched() {
+		let public_parameters = Arc::new(testnet::public_params(1024));
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		// Swapped cell contents are not verified against the commitment
+		let cells = (0..MAX_JOB_SIZE + 1)
+			.flat_map(|_| [(0, 0, 0), (0, 3, 1), (1, 0, 0), (1, 3, 0)])
+			.map(|(row, col, content_index)| Cell {
+				position: Position { row, col },
+				content: CELL_CONTENTS[content_index],
+			})
+			.collect::<Vec<_>>();
+		let commitments = [COMMITMENT, COMMITMENT];
+
+		let (verified, unverified) = verify(
+			1,
+			dimensions,
+			&cells,
+			&commitments,
+			public_parameters.clone(),
+		)
+		.unwrap();
+		let (expected_verified, expected_unverified) =
+			verify_unbatched(1, dimensions, &cells, &commitments, public_parameters).unwrap();
+
+		assert_eq!(verified.len() + unverified.len(), cells.len());
+		assert_eq!(sorted(verified), sorted(expected_verified));
+		assert_eq!(sorted(unverified), sorted(expected_unverified));
+	}
+
+	#[test]
+	fn verify_row_batch_fails_on_invalid_cell() {
+		let public_parameters = testnet::public_params(1024);
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let cell = |col, content_index: usize| Cell {
+			position: Position { row: 0, col },
+			content: CELL_CONTENTS[content_index],
+		};
+
+		let valid = [cell(0, 0), cell(3, 1)];
+		let verified = verify_row_batch(&public_parameters, dimensions, &COMMITMENT, &valid);
+		assert_eq!(verified, Some(true));
+
+		let invalid = [cell(0, 0), cell(3, 1), cell(3, 0)];
+		let verified = verify_row_batch(&public_parameters, dimensions, &COMMITMENT, &invalid);
+		assert_eq!(verified, Some(false));
+
+		let mut undecodable = cell(0, 0);
+		undecodable.content[..48].fill(u8::MAX);
+		let verified =
+			verify_row_batch(&public_parameters, dimensions, &COMMITMENT, &[undecodable]);
+		assert_eq!(verified, None);
+	}
+
+	fn hash(left: H256, right: H256) -> H256 {
+		keccak_256(&[left.as_bytes(), right.as_bytes()].concat()).into()
+	}
+
+	fn leaves() -> Vec<H256> {
+		[b"a", b"b", b"c"]
+			.iter()
+			.map(|data| keccak_256(*data).into())
+			.collect()
+	}
+
+	fn data_proof(leaf_index: u32, proof: Vec<H256>) -> DataProof {
+		let leaves = leaves();
+		DataProof {
+			root: hash(hash(leaves[0], leaves[1]), leaves[2]),
+			proof,
+			number_of_leaves: 3,
+			leaf_index,
+			leaf: leaves[leaf_index as usize],
+		}
+	}
+
+	#[test_case(0 ; "first leaf")]
+	#[test_case(1 ; "second leaf")]
+	#[test_case(2 ; "promoted leaf")]
+	fn verify_data_proof_ok(leaf_index: u32) {
+		let leaves = leaves();
+		let proof = match leaf_index {
+			0 => vec![leaves[1], leaves[2]],
+			1 => vec![leaves[0], leaves[2]],
+			_ => vec![hash(leaves[0], leaves[1])],
+		};
+		let data_proof = data_proof(leaf_index, proof);
+		assert!(verify_data_proof(data_proof.root, &data_proof));
+	}
+
+	#[test]
+	fn verify_data_proof_fails() {
+		let leaves = leaves();
+		let data_proof = data_proof(0, vec![leaves[1], leaves[2]]);
+		let root = data_proof.root;
+
+		assert!(!verify_data_proof(H256::repeat_byte(1), &data_proof));
+
+		let mut invalid = data_proof.clone();
+		invalid.leaf = leaves[1];
+		assert!(!verify_data_proof(root, &invalid));
+
+		let mut invalid = data_proof.clone();
+		invalid.proof.swap(0, 1);
+		assert!(!verify_data_proof(root, &invalid));
+
+		let mut invalid = data_proof;
+		invalid.leaf_index = 3;
+		assert!(!verify_data_proof(root, &invalid));
+	}
 }
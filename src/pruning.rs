@@ -0,0 +1,235 @@
+//! Pruning of the stored block data, according to configured retention policies.
+//!
+//! Periodically deletes block headers, confidence factors, application data and cached cells and
+//! rows which are older than retention allows, and updates pruned ranges in [`State`] accordingly.
+//! Pruned ranges are persisted before deleting, so they are restored on restart.
+
+use anyhow::{Context, Result};
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, Mutex},
+};
+use tracing::{debug, error, info};
+
+use crate::{
+	data::Database,
+	types::{Pruned, PruningConfig, State},
+};
+
+/// Returns first block to keep, if there are blocks to prune
+fn prune_below(latest: u32, retention: u32) -> Option<u32> {
+	let first_retained = latest.saturating_add(1).saturating_sub(retention);
+	(first_retained > 0).then_some(first_retained)
+}
+
+/// Returns first block to keep, so that the cache of kept blocks doesn't exceed the maximum size
+fn cache_prune_below(sizes: &BTreeMap<u32, u64>, max_size: u64) -> Option<u32> {
+	let mut total_size = 0u64;
+	for (&block_number, &size) in sizes.iter().rev() {
+		total_size = total_size.saturating_add(size);
+		if total_size > max_size {
+			return Some(block_number.saturating_add(1));
+		}
+	}
+	None
+}
+
+/// Stores the pruned bound, and updates the pruned range in the state once the bound is stored.
+/// Returns `false` if block data is already pruned below the given block.
+fn store_pruned_below(
+	db: &impl Database,
+	state: &Mutex<State>,
+	pruned: Pruned,
+	block_number: u32,
+) -> Result<bool> {
+	let pruned_below = *state.lock().unwrap().pruned_below_mut(pruned);
+	if pruned_below.map_or(false, |pruned_below| block_number <= pruned_below) {
+		return Ok(false);
+	}
+	db.store_pruned_below(pruned, block_number)?;
+	// Pruned range is updated before deleting, so deleted blocks are never reported as available
+	*state.lock().unwrap().pruned_below_mut(pruned) = Some(block_number);
+	Ok(true)
+}
+
+/// Prunes cache of the oldest blocks, if cache size exceeds the maximum
+fn prune_cache_size(db: &impl Database, state: &Mutex<State>, max_size: Option<u64>) -> Result<()> {
+	let Some(max_size) = max_size else {
+		return Ok(());
+	};
+
+	let sizes = db.get_cache_sizes().context("Failed to get cache sizes")?;
+	let Some(block_number) = cache_prune_below(&sizes, max_size) else {
+		return Ok(());
+	};
+
+	store_pruned_below(db, state, Pruned::Cache, block_number)
+		.context("Failed to store pruned cache bound")?;
+
+	db.prune_cache(block_number)
+		.context("Failed to prune cache exceeding maximum size")?;
+	debug!("Pruned cache below block {block_number} to fit maximum size");
+	Ok(())
+}
+
+fn prune(
+	db: &impl Database,
+	state: &Mutex<State>,
+	name: &str,
+	retention: Option<u32>,
+	pruned: Pruned,
+	prune_stored: impl Fn(u32) -> Result<()>,
+) -> Result<()> {
+	let Some(retention) = retention else {
+		return Ok(());
+	};
+
+	let latest = state.lock().unwrap().latest;
+	let Some(block_number) = prune_below(latest, retention) else {
+		return Ok(());
+	};
+
+	if !store_pruned_below(db, state, pruned, block_number)
+		.context(format!("Failed to store pruned {name} bound"))?
+	{
+		return Ok(());
+	}
+
+	prune_stored(block_number).context(format!("Failed to prune {name}"))?;
+	debug!("Pruned {name} below block {block_number}");
+	Ok(())
+}
+
+/// Runs pruning of the stored block data in configured interval.
+///
+/// # Arguments
+///
+/// * `db` - Database to prune
+/// * `cfg` - Pruning configuration
+/// * `state` - Processed blocks state
+pub async fn run(db: impl Database, cfg: PruningConfig, state: Arc<Mutex<State>>) {
+	info!("Starting pruning...");
+
+	let mut interval = tokio::time::interval(cfg.interval);
+
+	loop {
+		interval.tick().await;
+
+		if let Err(error) = prune(
+			&db,
+			&state,
+			"block headers",
+			cfg.block_header_retention,
+			Pruned::Headers,
+			|block_number| db.prune_headers(block_number),
+		) {
+			error!("{error:#}");
+		}
+
+		if let Err(error) = prune(
+			&db,
+			&state,
+			"confidence factors",
+			cfg.confidence_retention,
+			Pruned::Confidence,
+			|block_number| {
+				db.prune_confidence(block_number)?;
+				let mut state = state.lock().unwrap();
+				state.availability_failed = state.availability_failed.split_off(&block_number);
+				Ok(())
+			},
+		) {
+			error!("{error:#}");
+		}
+
+		if let Err(error) = prune(
+			&db,
+			&state,
+			"app data",
+			cfg.app_data_retention,
+			Pruned::AppData,
+			|block_number| db.prune_data(block_number),
+		) {
+			error!("{error:#}");
+		}
+
+		if let Err(error) = prune(
+			&db,
+			&state,
+			"cache",
+			cfg.cache_retention,
+			Pruned::Cache,
+			|block_number| db.prune_cache(block_number),
+		) {
+			error!("{error:#}");
+		}
+
+		if let Err(error) = prune_cache_size(&db, &state, cfg.cache_max_size) {
+			error!("{error:#}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{cache_prune_below, prune, prune_below};
+	use crate::{
+		data::{Database, MemoryDB},
+		types::{Pruned, State},
+	};
+	use std::{collections::BTreeMap, sync::Mutex};
+	use test_case::test_case;
+
+	#[test_case(10, 20 => None ; "retention longer than chain")]
+	#[test_case(10, 11 => None ; "retention equal to chain")]
+	#[test_case(10, 10 => Some(1) ; "prune genesis")]
+	#[test_case(100, 1 => Some(100) ; "keep latest only")]
+	#[test_case(100, 30 => Some(71) ; "keep last blocks")]
+	#[test_case(u32::MAX, 1 => Some(u32::MAX) ; "latest block at maximum")]
+	fn prune_below_block(latest: u32, retention: u32) -> Option<u32> {
+		prune_below(latest, retention)
+	}
+
+	#[test]
+	fn prune_stores_pruned_below() {
+		let db = MemoryDB::default();
+		let state = Mutex::new(State {
+			latest: 100,
+			..Default::default()
+		});
+		let prune_headers = |block_number| db.prune_headers(block_number);
+
+		prune(
+			&db,
+			&state,
+			"headers",
+			Some(30),
+			Pruned::Headers,
+			prune_headers,
+		)
+		.unwrap();
+		assert_eq!(state.lock().unwrap().header_pruned_below, Some(71));
+		assert_eq!(db.get_pruned_below(Pruned::Headers).unwrap(), Some(71));
+		assert_eq!(db.get_pruned_below(Pruned::Confidence).unwrap(), None);
+
+		// Pruned bound is not moved backwards
+		prune(
+			&db,
+			&state,
+			"headers",
+			Some(50),
+			Pruned::Headers,
+			prune_headers,
+		)
+		.unwrap();
+		assert_eq!(db.get_pruned_below(Pruned::Headers).unwrap(), Some(71));
+	}
+
+	#[test_case(&[], 100 => None ; "empty cache")]
+	#[test_case(&[(1, 30), (2, 30), (3, 40)], 100 => None ; "cache size equal to maximum")]
+	#[test_case(&[(1, 30), (2, 30), (3, 50)], 100 => Some(2) ; "prune oldest block")]
+	#[test_case(&[(1, 30), (2, 30), (3, 150)], 100 => Some(4) ; "latest block exceeds maximum")]
+	fn cache_prune_below_size(sizes: &[(u32, u64)], max_size: u64) -> Option<u32> {
+		cache_prune_below(&BTreeMap::from_iter(sizes.iter().copied()), max_size)
+	}
+}
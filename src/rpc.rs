@@ -34,25 +34,23 @@ pub async fn get_header_by_hash(client: &avail::Client, hash: H256) -> Result<Da
 		.ok_or_else(|| anyhow!("Header with hash {hash:?} not found"))
 }
 
+/// Gets GRANDPA authorities with their voting weights
 pub async fn get_valset_by_hash(
 	client: &avail::Client,
 	hash: H256,
-) -> Result<Vec<ed25519::Public>> {
-	let grandpa_valset = client
+) -> Result<Vec<(ed25519::Public, u64)>> {
+	client
 		.runtime_api()
 		.at(hash)
 		.call_raw::<Vec<(ed25519::Public, u64)>>("GrandpaApi_grandpa_authorities", None)
 		.await
-		.unwrap();
-
-	// Drop weights, as they are not currently used.
-	Ok(grandpa_valset.iter().map(|e| e.0).collect())
+		.context(format!("Failed to get GRANDPA authorities at {hash:?}"))
 }
 
 pub async fn get_valset_by_block_number(
 	client: &avail::Client,
 	block: u32,
-) -> Result<Vec<ed25519::Public>> {
+) -> Result<Vec<(ed25519::Public, u64)>> {
 	let hash = get_block_hash(client, block).await?;
 	get_valset_by_hash(client, hash).await
 }
@@ -120,6 +118,31 @@ pub fn generate_random_cells(dimensions: Dimensions, cell_count: u32) -> Vec<Pos
 	indices.into_iter().collect::<Vec<_>>()
 }
 
+/// Generates random cells which are not in the set of already sampled positions.
+/// Number of generated cells is limited by the number of remaining positions in the extended matrix.
+pub fn generate_additional_random_cells(
+	dimensions: Dimensions,
+	cell_count: u32,
+	sampled: &HashSet<Position>,
+) -> Vec<Position> {
+	let remaining = dimensions
+		.extended_size()
+		.saturating_sub(sampled.len() as u32);
+	let count = cell_count.min(remaining) as usize;
+	let mut rng = thread_rng();
+	let mut indices = HashSet::new();
+	while indices.len() < count {
+		let col = rng.gen_range(0..dimensions.cols().into());
+		let row = rng.gen_range(0..dimensions.extended_rows());
+		let position = Position { row, col };
+		if !sampled.contains(&position) {
+			indices.insert(position);
+		}
+	}
+
+	indices.into_iter().collect::<Vec<_>>()
+}
+
 #[instrument(skip_all, level = "trace")]
 pub async fn get_kate_rows(
 	client: &avail::Client,
@@ -135,6 +158,14 @@ pub async fn get_kate_rows(
 		.context("Failed to get Kate rows")
 }
 
+/// Returns true if RPC request failed because of connection or transport error, and can be retried
+pub fn is_transient(error: &anyhow::Error) -> bool {
+	matches!(
+		error.downcast_ref::<subxt::Error>(),
+		Some(subxt::Error::Io(_) | subxt::Error::Rpc(_))
+	)
+}
+
 /// RPC to get proofs for given positions of block
 pub async fn get_kate_proof(
 	client: &avail::Client,
@@ -160,6 +191,21 @@ pub async fn get_kate_proof(
 		.collect::<Vec<_>>())
 }
 
+/// RPC to get Merkle inclusion proof of the data transaction under the block data root
+pub async fn get_data_proof(
+	client: &avail::Client,
+	transaction_index: u32,
+	block_hash: H256,
+) -> Result<DataProof> {
+	let mut params = RpcParams::new();
+	params.push(transaction_index)?;
+	params.push(block_hash)?;
+	let t = client.rpc().deref();
+	t.request("kate_queryDataProof", params)
+		.await
+		.context("Failed to get data proof")
+}
+
 // RPC to check connection to substrate node
 pub async fn get_system_version(client: &avail::Client) -> Result<String> {
 	client
@@ -308,7 +354,8 @@ pub fn cell_count_for_confidence(confidence: f64) -> u32 {
 
 #[cfg(test)]
 mod tests {
-	use crate::rpc::{shuffle_full_nodes, ExpectedVersion};
+	use crate::rpc::{generate_additional_random_cells, shuffle_full_nodes, ExpectedVersion};
+	use kate_recovery::matrix::{Dimensions, Position};
 	use proptest::{
 		prelude::any_with,
 		prop_assert, prop_assert_eq, proptest,
@@ -316,6 +363,7 @@ mod tests {
 		strategy::{BoxedStrategy, Strategy},
 	};
 	use rand::{seq::SliceRandom, thread_rng};
+	use std::collections::HashSet;
 	use test_case::test_case;
 
 	fn full_nodes() -> BoxedStrategy<(Vec<String>, Option<String>)> {
@@ -347,6 +395,21 @@ mod tests {
 		assert_eq!(expected.matches(version, spec_name), matches);
 	}
 
+	#[test_case(4, 0, 4 ; "No cells sampled")]
+	#[test_case(4, 4, 4 ; "Some cells sampled")]
+	#[test_case(4, 6, 2 ; "Not enough cells remaining")]
+	#[test_case(4, 8, 0 ; "All cells sampled")]
+	fn test_generate_additional_random_cells(cell_count: u32, sampled: usize, expected: usize) {
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let sampled: HashSet<Position> = (0..dimensions.extended_rows())
+			.flat_map(|row| (0..4).map(move |col| Position { row, col }))
+			.take(sampled)
+			.collect();
+		let cells = generate_additional_random_cells(dimensions, cell_count, &sampled);
+		assert_eq!(cells.len(), expected);
+		assert!(cells.iter().all(|position| !sampled.contains(position)));
+	}
+
 	proptest! {
 		#[test]
 		fn shuffle_without_last((full_nodes, _) in full_nodes()) {
@@ -3,37 +3,53 @@ use avail_subxt::{
 	avail::Client,
 	primitives::{grandpa::AuthorityId, Header},
 	rpc::rpc_params,
+	utils::H256,
 };
 use codec::Encode;
-use rocksdb::DB;
-use sp_core::{blake2_256, ed25519, Pair};
+use sp_core::{blake2_256, ed25519};
 use std::{
 	sync::{Arc, Mutex},
 	time::Instant,
 };
 use tokio::sync::{
 	broadcast,
-	mpsc::{unbounded_channel, Sender},
+	mpsc::{unbounded_channel, Sender, UnboundedSender},
 };
-use tracing::{error, info, trace};
+use tracing::{error, info, trace, warn};
 
 use crate::{
-	data::store_finality_sync_checkpoint,
-	rpc,
-	types::{FinalitySyncCheckpoint, GrandpaJustification, OptionBlockRange, SignerMessage, State},
+	data::Database,
+	finality, rpc,
+	telemetry::{MetricCounter, Metrics},
+	types::{
+		ChainViolation, ChainViolationKind, Equivocation, FinalityJustification,
+		FinalitySyncCheckpoint, GrandpaJustification, OptionBlockRange, State,
+	},
 	utils,
 };
 
+pub struct Channels {
+	pub message_tx: broadcast::Sender<(Header, Instant)>,
+	/// Channel used to publish finalized header chain violations
+	pub violation_tx: broadcast::Sender<ChainViolation>,
+	/// Channel used to publish equivocations detected in justifications, with the finalized block number
+	pub equivocation_tx: broadcast::Sender<(u32, Equivocation)>,
+	/// Channel used to send numbers of blocks missed by the header subscription to the sync client
+	pub missed_block_tx: UnboundedSender<u32>,
+	pub error_sender: Sender<anyhow::Error>,
+}
+
 pub async fn finalized_headers(
 	rpc_client: Client,
-	message_tx: broadcast::Sender<(Header, Instant)>,
-	error_sender: Sender<anyhow::Error>,
+	channels: Channels,
+	metrics: Arc<impl Metrics>,
 	state: Arc<Mutex<State>>,
-	db: Arc<DB>,
+	db: impl Database,
 ) {
-	if let Err(error) = subscribe_check_and_process(rpc_client, message_tx, state, db).await {
+	if let Err(error) = subscribe_check_and_process(rpc_client, &channels, metrics, state, db).await
+	{
 		error!("{error}");
-		if let Err(error) = error_sender.send(error).await {
+		if let Err(error) = channels.error_sender.send(error).await {
 			error!("Cannot send error to error channel: {error}");
 		}
 	}
@@ -42,18 +58,111 @@ pub async fn finalized_headers(
 #[derive(Clone, Debug)]
 enum Messages {
 	Justification(GrandpaJustification),
-	ValidatorSetChange((Vec<ed25519::Public>, u64)),
+	ValidatorSetChange((Vec<(ed25519::Public, u64)>, u64)),
 	NewHeader(Header, Instant),
 }
 
+/// Checks that finalized header links to the previous finalized header, and that it doesn't conflict
+/// with the header already finalized at the same height. Previous header is the last sent header if
+/// it directly precedes given header, otherwise it is taken from the database, if stored.
+fn verify_chain(
+	db: &impl Database,
+	header: &Header,
+	last_sent_header: Option<(u32, H256)>,
+) -> Result<Option<ChainViolation>> {
+	let hash: H256 = Encode::using_encoded(header, blake2_256).into();
+
+	if let Some(stored) = db.get_header(header.number)? {
+		let stored_hash: H256 = Encode::using_encoded(&stored, blake2_256).into();
+		if stored_hash != hash {
+			return Ok(Some(ChainViolation {
+				block_number: header.number,
+				kind: ChainViolationKind::ConflictingHeaders,
+				expected_hash: stored_hash,
+				received_hash: hash,
+			}));
+		}
+	}
+
+	let Some(parent_number) = header.number.checked_sub(1) else {
+		return Ok(None);
+	};
+
+	let parent_hash = match last_sent_header {
+		Some((number, hash)) if number == parent_number => Some(hash),
+		_ => db
+			.get_header(parent_number)?
+			.map(|parent| Encode::using_encoded(&parent, blake2_256).into()),
+	};
+
+	Ok(parent_hash
+		.filter(|&parent_hash| parent_hash != header.parent_hash)
+		.map(|parent_hash| ChainViolation {
+			block_number: header.number,
+			kind: ChainViolationKind::BrokenLink,
+			expected_hash: parent_hash,
+			received_hash: header.parent_hash,
+		}))
+}
+
+/// Verifies the header chain and returns hash of the header.
+/// If chain violation is detected, it is published and the error is returned, halting header processing.
+fn check_chain(
+	db: &impl Database,
+	header: &Header,
+	last_sent_header: Option<(u32, H256)>,
+	violation_tx: &broadcast::Sender<ChainViolation>,
+) -> Result<H256> {
+	if let Some(violation) = verify_chain(db, header, last_sent_header)? {
+		// Sending fails only if there are no subscribers, which is not relevant here
+		_ = violation_tx.send(violation.clone());
+		return Err(anyhow!("Chain safety violation: {violation}"));
+	}
+	Ok(Encode::using_encoded(header, blake2_256).into())
+}
+
+/// Stores, publishes and counts equivocations detected in the verified justification of the block.
+async fn report_equivocations(
+	db: &impl Database,
+	metrics: &impl Metrics,
+	block_number: u32,
+	equivocations: Vec<Equivocation>,
+	equivocation_tx: &broadcast::Sender<(u32, Equivocation)>,
+) -> Result<()> {
+	if equivocations.is_empty() {
+		return Ok(());
+	}
+
+	db.store_equivocations(block_number, &equivocations)?;
+	for equivocation in equivocations {
+		warn!(
+			"Validator {:?} equivocated in round {} of set {} at block {block_number}",
+			equivocation.offender, equivocation.round, equivocation.set_id
+		);
+		metrics.count(MetricCounter::Equivocation).await;
+		// Sending fails only if there are no subscribers, which is not relevant here
+		_ = equivocation_tx.send((block_number, equivocation));
+	}
+	Ok(())
+}
+
 // Subscribes to finalized headers, justifications and monitors the changes in validator set.
-// Verifies the justifications. Then sends the header off to be processed by LC.
+// Verifies the justifications and the header chain. Then sends the header off to be processed by LC.
 async fn subscribe_check_and_process(
 	subxt_client: Client,
-	message_tx: broadcast::Sender<(Header, Instant)>,
+	channels: &Channels,
+	metrics: Arc<impl Metrics>,
 	state: Arc<Mutex<State>>,
-	db: Arc<DB>,
+	db: impl Database,
 ) -> Result<()> {
+	let Channels {
+		message_tx,
+		violation_tx,
+		equivocation_tx,
+		missed_block_tx,
+		..
+	} = channels;
+
 	let mut header_subscription = subxt_client
 		.rpc()
 		.subscribe_finalized_block_headers()
@@ -68,9 +177,20 @@ async fn subscribe_check_and_process(
 	// Fetch the set ID from storage at current height
 	let mut set_id = rpc::get_set_id_by_hash(&subxt_client, last_finalized_block_hash).await?;
 
-	// Get last (implicitly trusted) finalized block number
-	let mut last_finalized_block_header =
-		rpc::get_header_by_hash(&subxt_client, last_finalized_block_hash).await?;
+	// Get last (implicitly trusted) finalized block number, from database if already stored
+	let last_finalized_block_header = match db.get_header_by_hash(last_finalized_block_hash)? {
+		Some(header) => header,
+		None => rpc::get_header_by_hash(&subxt_client, last_finalized_block_hash).await?,
+	};
+
+	// Blocks up to the latest block on startup are synced by the sync client, so blocks
+	// finalized since then are sent as skipped blocks, even if missed by the subscription
+	let startup_latest_block = state.lock().unwrap().latest;
+	let mut last_sent_block_number = last_finalized_block_header
+		.number
+		.min(startup_latest_block.saturating_sub(1));
+	// Number and hash of the last header sent to the light client, used to verify the header chain
+	let mut last_sent_header: Option<(u32, H256)> = None;
 
 	info!("Current set: {:?}", (validator_set.clone(), set_id));
 
@@ -101,7 +221,7 @@ async fn subscribe_check_and_process(
 					let auths: Vec<(AuthorityId, u64)> = new_auths.pop().unwrap();
 					let new_valset = auths
 						.into_iter()
-						.map(|(a, _)| ed25519::Public::from_raw(a.0 .0 .0))
+						.map(|(a, weight)| (ed25519::Public::from_raw(a.0 .0 .0), weight))
 						.collect();
 
 					// Increment set_id
@@ -174,52 +294,30 @@ async fn subscribe_check_and_process(
 			{
 				// Basically, pop it out of the collection.
 				let (header, received_at) = unverified_headers.swap_remove(pos);
-				// Form a message which is signed in the justification, it's a triplet of a Precommit, round number and set_id (taken from Substrate code).
-				let signed_message = Encode::encode(&(
-					&SignerMessage::PrecommitMessage(
-						justification.commit.precommits[0].clone().precommit,
-					),
-					&justification.round,
-					&set_id, // Set ID is needed here.
-				));
-
-				// Verify all the signatures of the justification signs the hash of the block and extract all the signer addreses.
-				let signer_addresses = justification
-					.commit
-					.precommits
-					.iter()
-					.map(|precommit| {
-						let is_ok = <ed25519::Pair as Pair>::verify(
-							&precommit.signature,
-							&signed_message,
-							&precommit.id,
-						);
-						is_ok
-							.then(|| precommit.clone().id)
-							.ok_or_else(|| anyhow!("Not signed by this signature!"))
-					})
-					.collect::<Result<Vec<_>>>();
-				let Ok(signer_addresses) = signer_addresses else {
-					break 'mainloop Err(signer_addresses.unwrap_err());
-				};
-
-				// Match all the signer addresses to the current validator set.
-				let num_matched_addresses = signer_addresses
-					.iter()
-					.filter(|x| validator_set.iter().any(|e| e.0.eq(&x.0)))
-					.count();
+				// Verify that the justification is signed by the supermajority of the current validator set by weight.
+				let signed_weight =
+					match finality::verify_justification(&justification, set_id, &validator_set) {
+						Ok(signed_weight) => signed_weight,
+						Err(error) => break 'mainloop Err(error),
+					};
 
 				info!(
-					"Number of matching signatures: {num_matched_addresses}/{} for block {}",
-					validator_set.len(),
+					"Signed weight: {signed_weight}/{} for block {}",
+					validator_set.iter().map(|(_, weight)| weight).sum::<u64>(),
 					header.number
 				);
 
-				if num_matched_addresses < (validator_set.len() * 2 / 3) {
-					break 'mainloop Err(anyhow!(
-						"Not signed by the supermajority of the validator set."
-					));
-				}
+				// Record validators which signed conflicting precommits, signatures are verified already
+				let equivocations =
+					finality::find_equivocations(&justification, set_id, &validator_set);
+				report_equivocations(
+					&db,
+					metrics.as_ref(),
+					header.number,
+					equivocations,
+					equivocation_tx,
+				)
+				.await?;
 
 				// Store finality checkpoint if finality is synced
 				if !finality_synced {
@@ -227,30 +325,32 @@ async fn subscribe_check_and_process(
 				}
 				if finality_synced {
 					info!("Storing finality checkpoint at block {}", header.number);
-					store_finality_sync_checkpoint(
-						db.clone(),
-						FinalitySyncCheckpoint {
-							set_id,
-							number: header.number,
-							validator_set: validator_set.clone(),
-						},
-					)?;
+					db.store_finality_sync_checkpoint(FinalitySyncCheckpoint {
+						set_id,
+						number: header.number,
+						validator_set: validator_set.clone(),
+					})?;
 				}
 
 				// Get all the skipped blocks, if they exist
-				for bl_num in (last_finalized_block_header.number + 1)..header.number {
+				for bl_num in (last_sent_block_number + 1)..header.number {
 					info!("Sending skipped block {bl_num}");
 
-					let (header, received_at) = match unverified_headers
+					let position = unverified_headers
 						.iter()
-						.position(|(h, _)| h.number == bl_num)
-					{
+						.position(|(h, _)| h.number == bl_num);
+					// Blocks up to the startup latest block are expected to be fetched from RPC
+					let is_missed = position.is_none() && bl_num > startup_latest_block;
+					let (header, received_at) = match position {
 						Some(pos) => {
 							info!("Fetching header from unverified headers");
 							unverified_headers.swap_remove(pos)
 						},
 						None => {
-							info!("Fetching header from RPC");
+							if is_missed {
+								warn!("Block {bl_num} is missed by the header subscription, fetching header from RPC");
+								state.lock().unwrap().add_missed_block(bl_num);
+							}
 							(
 								rpc::get_header_by_block_number(&subxt_client, bl_num)
 									.await?
@@ -260,13 +360,33 @@ async fn subscribe_check_and_process(
 						},
 					};
 
+					let hash = check_chain(&db, &header, last_sent_header, violation_tx)?;
+					last_sent_header = Some((header.number, hash));
+
 					state.lock().unwrap().header_verified.set(header.number);
-					message_tx.send((header, received_at))?;
+					if is_missed {
+						// Missed blocks are processed by the sync client, which clears their gaps
+						missed_block_tx.send(bl_num)?;
+					} else {
+						message_tx.send((header, received_at))?;
+					}
 				}
 
+				let hash = check_chain(&db, &header, last_sent_header, violation_tx)?;
+				last_sent_header = Some((header.number, hash));
+
+				// Store verified justification, so it can be served to the downstream verifiers
+				db.store_justification(
+					header.number,
+					&FinalityJustification {
+						set_id,
+						justification,
+					},
+				)?;
+
 				info!("Sending finalized block {}", header.number);
-				// Reset last finalized block
-				last_finalized_block_header = header.clone();
+				// Reset last sent block
+				last_sent_block_number = header.number;
 
 				// Finally, send the verified block (header)
 				state.lock().unwrap().header_verified.set(header.number);
@@ -280,3 +400,112 @@ async fn subscribe_check_and_process(
 	};
 	res
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{check_chain, verify_chain};
+	use crate::{
+		data::{Database, MemoryDB},
+		test_utils::{header, header_hash},
+		types::{ChainViolation, ChainViolationKind},
+	};
+	use avail_subxt::utils::H256;
+	use test_case::test_case;
+	use tokio::sync::broadcast;
+
+	#[test_case(false, false => None ; "no previous header")]
+	#[test_case(true, false => None ; "linked to last sent header")]
+	#[test_case(false, true => None ; "linked to stored header")]
+	#[test_case(true, true => None ; "linked to last sent and stored header")]
+	fn verify_chain_linked(last_sent: bool, stored: bool) -> Option<ChainViolation> {
+		let db = MemoryDB::default();
+		let parent = header(4, H256::repeat_byte(1));
+		if stored {
+			db.store_header(4, &parent).unwrap();
+		}
+		let last_sent_header = last_sent.then(|| (4, header_hash(&parent)));
+		verify_chain(&db, &header(5, header_hash(&parent)), last_sent_header).unwrap()
+	}
+
+	#[test_case(true, false ; "not linked to last sent header")]
+	#[test_case(false, true ; "not linked to stored header")]
+	fn verify_chain_broken_link(last_sent: bool, stored: bool) {
+		let db = MemoryDB::default();
+		let parent = header(4, H256::repeat_byte(1));
+		if stored {
+			db.store_header(4, &parent).unwrap();
+		}
+		let last_sent_header = last_sent.then(|| (4, header_hash(&parent)));
+		let violation = verify_chain(&db, &header(5, H256::repeat_byte(2)), last_sent_header)
+			.unwrap()
+			.unwrap();
+		assert_eq!(
+			violation,
+			ChainViolation {
+				block_number: 5,
+				kind: ChainViolationKind::BrokenLink,
+				expected_hash: header_hash(&parent),
+				received_hash: H256::repeat_byte(2),
+			}
+		);
+	}
+
+	#[test]
+	fn verify_chain_last_sent_not_parent() {
+		let db = MemoryDB::default();
+		let last_sent_header = Some((3, H256::repeat_byte(3)));
+		let result = verify_chain(&db, &header(5, H256::repeat_byte(2)), last_sent_header);
+		assert_eq!(result.unwrap(), None);
+	}
+
+	#[test]
+	fn verify_chain_conflicting_headers() {
+		let db = MemoryDB::default();
+		let stored = header(5, H256::repeat_byte(1));
+		db.store_header(5, &stored).unwrap();
+		assert_eq!(verify_chain(&db, &stored, None).unwrap(), None);
+
+		let conflicting = header(5, H256::repeat_byte(2));
+		let violation = verify_chain(&db, &conflicting, None).unwrap().unwrap();
+		assert_eq!(
+			violation,
+			ChainViolation {
+				block_number: 5,
+				kind: ChainViolationKind::ConflictingHeaders,
+				expected_hash: header_hash(&stored),
+				received_hash: header_hash(&conflicting),
+			}
+		);
+	}
+
+	#[test]
+	fn check_chain_linked() {
+		let db = MemoryDB::default();
+		let (violation_tx, mut violation_rx) = broadcast::channel(1);
+		let parent = header(4, H256::repeat_byte(1));
+		let last_sent_header = Some((4, header_hash(&parent)));
+		let child = header(5, header_hash(&parent));
+		let hash = check_chain(&db, &child, last_sent_header, &violation_tx).unwrap();
+		assert_eq!(hash, header_hash(&child));
+		assert!(violation_rx.try_recv().is_err());
+	}
+
+	#[test]
+	fn check_chain_violation() {
+		let db = MemoryDB::default();
+		let (violation_tx, mut violation_rx) = broadcast::channel(1);
+		let parent = header(4, H256::repeat_byte(1));
+		let last_sent_header = Some((4, header_hash(&parent)));
+		let child = header(5, H256::repeat_byte(2));
+		assert!(check_chain(&db, &child, last_sent_header, &violation_tx).is_err());
+		assert_eq!(
+			violation_rx.try_recv().unwrap(),
+			ChainViolation {
+				block_number: 5,
+				kind: ChainViolationKind::BrokenLink,
+				expected_hash: header_hash(&parent),
+				received_hash: H256::repeat_byte(2),
+			}
+		);
+	}
+}
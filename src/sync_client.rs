@@ -7,8 +7,10 @@
 //! * For each block, fetches block header from RPC and stores it into database
 //! * Generate random cells for random data sampling
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
-//! * Verify proof using the received cells
-//! * Calculate block confidence and store it in RocksDB
+//! * Verify proof using the received cells, cells from DHT which fail verification are fetched from RPC instead
+//! * Calculate block confidence and store it in the database
+//! * Store availability failure in the database, in case not enough cells are fetched, some cells fail proof verification,
+//!   or confidence from DHT cells is below the configured minimum
 //! * Insert cells to to DHT for remote fetch
 //!
 //! # Notes
@@ -16,40 +18,38 @@
 //! In case RPC is disabled, RPC calls will be skipped.
 
 use crate::{
-	data::{
-		is_block_header_in_db, is_confidence_in_db, store_block_header_in_db,
-		store_confidence_in_db,
-	},
+	data::{BlockBatch, Database},
 	network::Client,
 	proof, rpc,
-	types::{BlockVerified, OptionBlockRange, State, SyncClientConfig},
-	utils::{calculate_confidence, extract_app_lookup, extract_kate},
+	types::{
+		AvailabilityFailure, BlockRange, BlockVerified, FailureReason, OptionBlockRange,
+		RetryConfig, SamplingRecord, State, SyncClientConfig,
+	},
+	utils::{calculate_confidence, diff_positions, extract_app_lookup, extract_kate, retry},
 };
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use avail_subxt::{avail, primitives::Header as DaHeader, utils::H256};
 use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
 use kate_recovery::{commitments, matrix::Dimensions};
 use kate_recovery::{data::Cell, matrix::Position};
 use mockall::automock;
-use rocksdb::DB;
 use std::{
 	sync::{Arc, Mutex},
 	time::Instant,
 };
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver};
 use tracing::{error, info, warn};
 
 #[async_trait]
 #[automock]
 pub trait SyncClient {
-	fn block_header_in_db(&self, block_number: u32) -> Result<bool>;
 	async fn get_header_by_block_number(&self, block_number: u32) -> Result<(DaHeader, H256)>;
-	fn store_block_header_in_db(&self, header: DaHeader, block_number: u32) -> Result<()>;
 	fn is_confidence_in_db(&self, block_number: u32) -> Result<bool>;
-	fn store_confidence_in_db(&self, count: u32, block_number: u32) -> Result<()>;
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()>;
 	async fn get_kate_proof(&self, hash: H256, positions: &[Position]) -> Result<Vec<Cell>>;
 	async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> f32;
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Vec<Cell>;
 	async fn fetch_cells_from_dht(
 		&self,
 		positions: &[Position],
@@ -58,50 +58,52 @@ pub trait SyncClient {
 	fn get_client(&self) -> avail::Client;
 }
 #[derive(Clone)]
-struct SyncClientImpl {
-	db: Arc<DB>,
+struct SyncClientImpl<T: Database> {
+	db: T,
 	network_client: Client,
 	rpc_client: avail::Client,
+	retry: RetryConfig,
 }
 
-pub fn new(db: Arc<DB>, network_client: Client, rpc_client: avail::Client) -> impl SyncClient {
+pub fn new(
+	db: impl Database,
+	network_client: Client,
+	rpc_client: avail::Client,
+	retry: RetryConfig,
+) -> impl SyncClient {
 	SyncClientImpl {
 		db,
 		network_client,
 		rpc_client,
+		retry,
 	}
 }
 
 #[async_trait]
-impl SyncClient for SyncClientImpl {
-	fn block_header_in_db(&self, block_number: u32) -> Result<bool> {
-		is_block_header_in_db(self.db.clone(), block_number)
-			.context("Failed to check if block header is in DB")
-	}
-
+impl<T: Database> SyncClient for SyncClientImpl<T> {
 	async fn get_header_by_block_number(&self, block_number: u32) -> Result<(DaHeader, H256)> {
 		rpc::get_header_by_block_number(&self.rpc_client, block_number)
 			.await
 			.with_context(|| format!("Failed to get block {block_number} by block number"))
 	}
 
-	fn store_block_header_in_db(&self, header: DaHeader, block_number: u32) -> Result<()> {
-		store_block_header_in_db(self.db.clone(), block_number, &header)
-			.context("Failed to store block header in DB")
-	}
-
 	fn is_confidence_in_db(&self, block_number: u32) -> Result<bool> {
-		is_confidence_in_db(self.db.clone(), block_number)
+		self.db
+			.is_confidence_stored(block_number)
 			.context("Failed to check if confidence is in DB")
 	}
 
-	fn store_confidence_in_db(&self, count: u32, block_number: u32) -> Result<()> {
-		store_confidence_in_db(self.db.clone(), block_number, count)
-			.context("Failed to store confidence in DB")
+	fn store_block_in_db(&self, block_number: u32, block: BlockBatch) -> Result<()> {
+		self.db
+			.store_block(block_number, block)
+			.context("Failed to store block in DB")
 	}
 
 	async fn get_kate_proof(&self, hash: H256, positions: &[Position]) -> Result<Vec<Cell>> {
-		rpc::get_kate_proof(&self.rpc_client, hash, positions).await
+		retry(&self.retry, rpc::is_transient, || {
+			rpc::get_kate_proof(&self.rpc_client, hash, positions)
+		})
+		.await
 	}
 
 	async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> f32 {
@@ -109,6 +111,14 @@ impl SyncClient for SyncClientImpl {
 			.insert_cells_into_dht(block, cells)
 			.await
 	}
+	fn get_cached_cells(&self, block_number: u32, positions: &[Position]) -> Vec<Cell> {
+		self.db
+			.get_cached_cells(block_number, positions)
+			.unwrap_or_else(|error| {
+				warn!(block_number, "Cannot get cached cells: {error:#}");
+				vec![]
+			})
+	}
 	async fn fetch_cells_from_dht(
 		&self,
 		positions: &[Position],
@@ -123,25 +133,34 @@ impl SyncClient for SyncClientImpl {
 	}
 }
 
+/// Outcome of the block writes
+#[derive(Debug, PartialEq)]
+enum BlockWrites {
+	/// Block writes are committed, or block is already stored
+	Committed,
+	/// Block failed data availability verification, and the failure is committed
+	Failed,
+}
+
 async fn process_block(
 	sync_client: &impl SyncClient,
 	block_number: u32,
 	cfg: &SyncClientConfig,
 	pp: Arc<PublicParameters>,
 	block_verified_sender: Option<broadcast::Sender<BlockVerified>>,
-) -> Result<()> {
+) -> Result<BlockWrites> {
+	// Block header and confidence are stored together, so block
+	// with stored confidence is already synced and verified
 	if sync_client
-		.block_header_in_db(block_number)
-		.context("Failed to check if block header is in DB")?
+		.is_confidence_in_db(block_number)
+		.context("Failed to check if confidence is in DB")?
 	{
-		// TODO: If block header storing fails, that block will be skipped upon restart
-		// Better option would be to check for confidence
-		return Ok(());
+		return Ok(BlockWrites::Committed);
 	};
 
-	// if block header look up fails, only then comes here for
-	// fetching and storing block header as part of (light weight)
-	// syncing process
+	// if confidence look up fails, only then comes here for
+	// fetching block header and verifying the block as part
+	// of (light weight) syncing process
 	let begin = Instant::now();
 
 	let (header, header_hash) = sync_client.get_header_by_block_number(block_number).await?;
@@ -149,22 +168,8 @@ async fn process_block(
 	let app_lookup = extract_app_lookup(&header.extension);
 
 	info!(block_number, "App index {:?}", app_lookup);
-
-	sync_client
-		.store_block_header_in_db(header.clone(), block_number)
-		.context("Failed to store block header in DB")?;
-
 	info!(block_number, elapsed = ?begin.elapsed(), "Synced block header");
 
-	// If it's found that this certain block is not verified
-	// then it'll be verified now
-	if sync_client
-		.is_confidence_in_db(block_number)
-		.context("Failed to check if confidence is in DB")?
-	{
-		return Ok(());
-	};
-
 	let begin = Instant::now();
 
 	let (rows, cols, _, commitment) = extract_kate(&header.extension);
@@ -176,8 +181,11 @@ async fn process_block(
 	let cell_count = rpc::cell_count_for_confidence(cfg.confidence);
 	let positions = rpc::generate_random_cells(dimensions, cell_count);
 
-	let (dht_fetched, unfetched) = sync_client
-		.fetch_cells_from_dht(&positions, block_number)
+	// Cells verified earlier are not fetched again, and they are not counted as DHT cells
+	let cached = sync_client.get_cached_cells(block_number, &positions);
+	let uncached = diff_positions(&positions, &cached);
+	let (mut dht_fetched, unfetched) = sync_client
+		.fetch_cells_from_dht(&uncached, block_number)
 		.await;
 
 	info!(
@@ -186,7 +194,7 @@ async fn process_block(
 		dht_fetched.len()
 	);
 
-	let rpc_fetched = if cfg.disable_rpc {
+	let mut rpc_fetched = if cfg.disable_rpc {
 		vec![]
 	} else {
 		sync_client.get_kate_proof(header_hash, &unfetched).await?
@@ -199,19 +207,39 @@ async fn process_block(
 	);
 
 	let mut cells = vec![];
-	cells.extend(dht_fetched);
+	cells.extend(cached.clone());
+	cells.extend(dht_fetched.clone());
 	cells.extend(rpc_fetched.clone());
 	if positions.len() > cells.len() {
-		return Err(anyhow!(
+		error!(
+			block_number,
 			"Failed to fetch {} cells",
 			positions.len() - cells.len()
-		));
+		);
+		// Sampling record is stored, so unavailable cells can be inspected
+		let sampling = SamplingRecord::new(&positions, &cached, &dht_fetched, &rpc_fetched, &[]);
+		let block = BlockBatch {
+			header: Some(header),
+			failure: Some(AvailabilityFailure {
+				reason: FailureReason::CellsUnavailable,
+				cells_requested: positions.len() as u32,
+				cells_fetched: cells.len() as u32,
+				cells_unverified: 0,
+			}),
+			sampling: Some(sampling),
+			..Default::default()
+		};
+		sync_client
+			.store_block_in_db(block_number, block)
+			.context("Failed to store block in DB")?;
+		return Ok(BlockWrites::Failed);
 	}
 
 	let cells_len = cells.len();
 	info!(block_number, "Fetched {cells_len} cells for verification");
 
-	let (verified, _) = proof::verify(block_number, dimensions, &cells, &commitments, pp)?;
+	let (mut verified, unverified) =
+		proof::verify(block_number, dimensions, &cells, &commitments, pp.clone())?;
 
 	info!(
 		block_number,
@@ -219,27 +247,130 @@ async fn process_block(
 		"Completed {cells_len} verification rounds",
 	);
 
-	// write confidence factor into on-disk database
+	// DHT cells which failed proof verification are dropped and fetched from RPC instead
+	let dht_unverified = dht_fetched
+		.iter()
+		.map(|cell| cell.position)
+		.filter(|position| unverified.contains(position))
+		.collect::<Vec<_>>();
+	if !dht_unverified.is_empty() {
+		warn!(
+			block_number,
+			"{} cells fetched from DHT failed proof verification",
+			dht_unverified.len()
+		);
+		dht_fetched.retain(|cell| !dht_unverified.contains(&cell.position));
+		cells.retain(|cell| !dht_unverified.contains(&cell.position));
+		if !cfg.disable_rpc {
+			let refetched = sync_client
+				.get_kate_proof(header_hash, &dht_unverified)
+				.await?;
+			let (refetched_verified, _) =
+				proof::verify(block_number, dimensions, &refetched, &commitments, pp)?;
+			verified.extend(refetched_verified);
+			cells.extend(refetched.clone());
+			rpc_fetched.extend(refetched);
+		}
+	}
+
+	// block header, confidence factor and sampling record are written into on-disk database at once
+	let sampling = SamplingRecord::new(&positions, &cached, &dht_fetched, &rpc_fetched, &verified);
+	let counts = sampling.confidence_counts();
+	let mut block = BlockBatch {
+		header: Some(header.clone()),
+		confidence: Some(verified.len().try_into()?),
+		confidence_counts: Some(counts),
+		sampling: Some(sampling),
+		..Default::default()
+	};
+	if cfg.cache {
+		block.cells = cells
+			.into_iter()
+			.filter(|cell| verified.contains(&cell.position))
+			.collect();
+	}
+
+	let dht_conf = calculate_confidence(counts.dht);
+	if let Some(min_dht_conf) = cfg.min_dht_confidence.filter(|&min| dht_conf < min) {
+		warn!(
+			block_number,
+			"Confidence from DHT cells {dht_conf} is below required {min_dht_conf}"
+		);
+		// Confidence is not stored, so it is never reported as achieved for the block
+		block.confidence = None;
+		block.failure = Some(AvailabilityFailure {
+			reason: FailureReason::InsufficientDhtConfidence,
+			cells_requested: positions.len() as u32,
+			cells_fetched: cells_len as u32,
+			cells_unverified: 0,
+		});
+	}
+
+	// Cells served by the node failed verification, or DHT cells couldn't be replaced
+	let cells_unverified = positions.len().saturating_sub(verified.len());
+	if cells_unverified > 0 {
+		error!(
+			block_number,
+			"{cells_unverified} sampled cells failed proof verification",
+		);
+		block.confidence = None;
+		block.confidence_counts = None;
+		block.failure = Some(AvailabilityFailure {
+			reason: FailureReason::InvalidProofs,
+			cells_requested: positions.len() as u32,
+			cells_fetched: cells_len as u32,
+			cells_unverified: cells_unverified as u32,
+		});
+	}
+
+	let confidence = Some(calculate_confidence(verified.len() as u32));
+	let client_msg =
+		BlockVerified::try_from((header, confidence)).context("converting to message failed")?;
+
+	let block_writes = if block.failure.is_some() {
+		BlockWrites::Failed
+	} else {
+		BlockWrites::Committed
+	};
 	sync_client
-		.store_confidence_in_db(verified.len().try_into()?, block_number)
-		.context("Failed to store confidence in DB")?;
+		.store_block_in_db(block_number, block)
+		.context("Failed to store block in DB")?;
 
 	let inserted_cells = sync_client
 		.insert_cells_into_dht(block_number, rpc_fetched)
 		.await;
 	info!(block_number, "Cells inserted into DHT: {inserted_cells}");
 
-	let confidence = Some(calculate_confidence(verified.len() as u32));
-	let client_msg =
-		BlockVerified::try_from((header, confidence)).context("converting to message failed")?;
-
+	// Data of the block which failed verification is not fetched by the application client
 	if let Some(ref channel) = block_verified_sender {
-		if let Err(error) = channel.send(client_msg) {
-			error!("Cannot send block verified message: {error}");
+		if block_writes != BlockWrites::Failed {
+			if let Err(error) = channel.send(client_msg) {
+				error!("Cannot send block verified message: {error}");
+			}
 		}
 	}
 
-	Ok(())
+	Ok(block_writes)
+}
+
+/// Returns range of blocks between the last processed block and the sync end block,
+/// missed while the light client was not running and not covered by the configured sync
+///
+/// # Arguments
+///
+/// * `last_processed_block` - Last block with stored confidence, if any
+/// * `sync_start_block` - Configured sync start block
+/// * `sync_end_block` - Sync end block
+pub fn restart_gap(
+	last_processed_block: Option<u32>,
+	sync_start_block: Option<u32>,
+	sync_end_block: u32,
+) -> Option<BlockRange> {
+	let first = last_processed_block? + 1;
+	let last = sync_start_block.map_or(sync_end_block, |sync_start_block| {
+		sync_start_block.saturating_sub(1).min(sync_end_block)
+	});
+	(first <= last).then_some(BlockRange { first, last })
 }
 
 /// Runs sync client.
@@ -283,13 +414,21 @@ pub async fn run(
 		// TODO: Should we handle unprocessed blocks differently?
 		let block_verified_sender = block_verified_sender.clone();
 		let pp = pp.clone();
-		if let Err(error) =
-			process_block(&sync_client, block_number, &cfg, pp, block_verified_sender).await
-		{
-			error!(block_number, "Cannot process block: {error:#}");
-		} else {
-			let mut state = state.lock().unwrap();
-			state.sync_confidence_achieved.set(block_number);
+		let result =
+			process_block(&sync_client, block_number, &cfg, pp, block_verified_sender).await;
+		let mut state = state.lock().unwrap();
+		match result {
+			Ok(BlockWrites::Committed) => {
+				// Blocks missed since the last run are synced here
+				state.remove_missed_block(block_number);
+				state.sync_confidence_achieved.set(block_number);
+			},
+			Ok(BlockWrites::Failed) => {
+				state.remove_missed_block(block_number);
+				state.availability_failed.insert(block_number);
+			},
+			// Missed block stays in the gap, so it is not reported as processed
+			Err(error) => error!(block_number, "Cannot process block: {error:#}"),
 		}
 	}
 
@@ -298,11 +437,54 @@ pub async fn run(
 	}
 }
 
+/// Processes blocks missed by the header subscription, in the order they are received.
+///
+/// # Arguments
+///
+/// * `cfg` - Sync client configuration
+/// * `pp` - Public parameters (i.e. SRS) needed for proof verification
+/// * `block_verified_sender` - Optional channel to send verified blocks
+/// * `state` - Processed blocks state, missed blocks are removed from gaps once stored, and kept on failure
+/// * `missed_block_receiver` - Channel used to receive numbers of missed blocks
+pub async fn run_missed_blocks(
+	sync_client: impl SyncClient,
+	cfg: SyncClientConfig,
+	pp: Arc<PublicParameters>,
+	block_verified_sender: Option<broadcast::Sender<BlockVerified>>,
+	state: Arc<Mutex<State>>,
+	mut missed_block_receiver: UnboundedReceiver<u32>,
+) {
+	info!("Starting processing of missed blocks...");
+
+	while let Some(block_number) = missed_block_receiver.recv().await {
+		info!(block_number, "Processing missed block");
+		let block_verified_sender = block_verified_sender.clone();
+		let result = process_block(
+			&sync_client,
+			block_number,
+			&cfg,
+			pp.clone(),
+			block_verified_sender,
+		)
+		.await;
+		match result {
+			Ok(BlockWrites::Committed) => state.lock().unwrap().remove_missed_block(block_number),
+			Ok(BlockWrites::Failed) => {
+				let mut state = state.lock().unwrap();
+				state.remove_missed_block(block_number);
+				state.availability_failed.insert(block_number);
+			},
+			Err(error) => error!(block_number, "Cannot process missed block: {error:#}"),
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 
 	use super::*;
 	use crate::types::{self, RuntimeConfig};
+	use anyhow::anyhow;
 	use avail_subxt::{
 		api::runtime_types::avail_core::{
 			data_lookup::compact::CompactDataLookup,
@@ -314,14 +496,34 @@ mod tests {
 	use hex_literal::hex;
 	use kate_recovery::testnet;
 	use mockall::predicate::eq;
+	use test_case::test_case;
+
+	#[test_case(None, None, 99 => None ; "nothing processed before")]
+	#[test_case(Some(99), None, 99 => None ; "no missed blocks")]
+	#[test_case(Some(80), None, 99 => Some((81, 99)) ; "missed blocks")]
+	#[test_case(Some(80), Some(90), 99 => Some((81, 89)) ; "missed blocks before sync")]
+	#[test_case(Some(80), Some(50), 99 => None ; "missed blocks covered by sync")]
+	fn test_restart_gap(
+		last_processed_block: Option<u32>,
+		sync_start_block: Option<u32>,
+		sync_end_block: u32,
+	) -> Option<(u32, u32)> {
+		restart_gap(last_processed_block, sync_start_block, sync_end_block)
+			.map(|gap| (gap.first, gap.last))
+	}
 
+	#[test_case(None, true ; "confidence achieved")]
+	#[test_case(Some(100.0), false ; "insufficient DHT confidence")]
 	#[tokio::test]
-	pub async fn test_process_blocks_without_rpc() {
-		let (block_tx, _) = broadcast::channel::<types::BlockVerified>(10);
+	pub async fn test_process_blocks_without_rpc(min_dht_confidence: Option<f64>, achieved: bool) {
 		let pp = Arc::new(testnet::public_params(1024));
 		let mut cfg = SyncClientConfig::from(&RuntimeConfig::default());
 		cfg.disable_rpc = true;
+		cfg.min_dht_confidence = min_dht_confidence;
 		let mut mock_client = MockSyncClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
 		let header: DaHeader = DaHeader {
 			parent_hash: hex!("2a75ea712b4b2c360cb7c0cdd806de4e9363ff7e37ce30788d487a258604dba3")
 				.into(),
@@ -359,7 +561,7 @@ mod tests {
 		let header_hash: H256 =
 			hex!("3767f8955d6f7306b1e55701b6316fa1163daa8d4cffdb05c3b25db5f5da1723").into();
 		mock_client
-			.expect_block_header_in_db()
+			.expect_is_confidence_in_db()
 			.with(eq(42))
 			.returning(|_| Ok(false));
 
@@ -371,14 +573,6 @@ mod tests {
 
 				Box::pin(async move { Ok((header, header_hash)) })
 			});
-		mock_client
-			.expect_store_block_header_in_db()
-			.withf(|_, x| *x == 42)
-			.returning(|_, _| Ok(()));
-		mock_client
-			.expect_is_confidence_in_db()
-			.with(eq(42))
-			.returning(|_| Ok(true));
 		mock_client
 			.expect_fetch_cells_from_dht()
 			.withf(|_, x: &u32| *x == 42)
@@ -435,25 +629,41 @@ mod tests {
 			mock_client.expect_get_kate_proof().never();
 		}
 		mock_client
-			.expect_is_confidence_in_db()
-			.with(eq(42))
-			.returning(|_| Ok(true));
-
+			.expect_store_block_in_db()
+			.withf(move |block_number, block| {
+				let failure = block.failure.map(|failure| failure.reason);
+				*block_number == 42
+					&& block.header.is_some()
+					&& block.confidence.is_some() == achieved
+					&& failure == (!achieved).then_some(FailureReason::InsufficientDhtConfidence)
+					&& block.sampling.is_some()
+			})
+			.times(1)
+			.returning(|_, _| Ok(()));
 		mock_client
 			.expect_insert_cells_into_dht()
 			.withf(move |x, _| *x == 42)
 			.returning(move |_, _| Box::pin(async move { 1f32 }));
-		process_block(&mock_client, 42, &cfg, pp, Some(block_tx))
+		let block_writes = process_block(&mock_client, 42, &cfg, pp, None)
 			.await
 			.unwrap();
+		let expected = if achieved {
+			BlockWrites::Committed
+		} else {
+			BlockWrites::Failed
+		};
+		assert_eq!(block_writes, expected);
 	}
 
 	#[tokio::test]
 	pub async fn test_process_blocks_with_rpc() {
-		let (block_tx, _) = broadcast::channel::<types::BlockVerified>(10);
+		let (block_tx, mut block_rx) = broadcast::channel::<types::BlockVerified>(10);
 		let pp = Arc::new(testnet::public_params(1024));
 		let cfg = SyncClientConfig::from(&RuntimeConfig::default());
 		let mut mock_client = MockSyncClient::new();
+		mock_client
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
 		let header: DaHeader = DaHeader {
 			parent_hash: hex!("2a75ea712b4b2c360cb7c0cdd806de4e9363ff7e37ce30788d487a258604dba3")
 				.into(),
@@ -501,7 +711,7 @@ mod tests {
 			],
 		}];
 		mock_client
-			.expect_block_header_in_db()
+			.expect_is_confidence_in_db()
 			.with(eq(42))
 			.returning(|_| Ok(false));
 
@@ -513,14 +723,6 @@ mod tests {
 
 				Box::pin(async move { Ok((header, header_hash)) })
 			});
-		mock_client
-			.expect_store_block_header_in_db()
-			.withf(|_, x| *x == 42)
-			.returning(|_, _| Ok(()));
-		mock_client
-			.expect_is_confidence_in_db()
-			.with(eq(42))
-			.returning(|_| Ok(true));
 		mock_client
 			.expect_fetch_cells_from_dht()
 			.withf(|_, x: &u32| *x == 42)
@@ -571,29 +773,72 @@ mod tests {
 			});
 		}
 		mock_client
-			.expect_is_confidence_in_db()
-			.with(eq(42))
-			.returning(|_| Ok(true));
+			.expect_store_block_in_db()
+			.withf(|block_number, block| {
+				*block_number == 42
+					&& block.header.is_some()
+					&& block.confidence.is_some()
+					&& block.sampling.is_some()
+			})
+			.times(1)
+			.returning(|_, _| Ok(()));
 		mock_client
 			.expect_insert_cells_into_dht()
 			.withf(move |x, _| *x == 42)
 			.returning(move |_, _| Box::pin(async move { 1f32 }));
-		process_block(&mock_client, 42, &cfg, pp, Some(block_tx))
+		let block_writes = process_block(&mock_client, 42, &cfg, pp, Some(block_tx))
 			.await
 			.unwrap();
+		assert_eq!(block_writes, BlockWrites::Committed);
+		assert_eq!(block_rx.try_recv().unwrap().block_num, 42);
 	}
 	#[tokio::test]
-	pub async fn test_header_in_dbstore() {
+	async fn test_run_missed_blocks() {
+		let pp = Arc::new(testnet::public_params(1024));
+		let cfg = SyncClientConfig::from(&RuntimeConfig::default());
+		let mut mock_client = MockSyncClient::new();
+		mock_client
+			.expect_is_confidence_in_db()
+			.times(2)
+			.returning(|block_number| match block_number {
+				7 => Err(anyhow!("Database is not available")),
+				_ => Ok(true),
+			});
+		let state = Arc::new(Mutex::new(State::default()));
+		{
+			let mut state = state.lock().unwrap();
+			for block_number in [5, 6, 7] {
+				state.add_missed_block(block_number);
+			}
+		}
+
+		let (missed_block_tx, missed_block_rx) = tokio::sync::mpsc::unbounded_channel();
+		missed_block_tx.send(5).unwrap();
+		missed_block_tx.send(7).unwrap();
+		drop(missed_block_tx);
+		run_missed_blocks(mock_client, cfg, pp, None, state.clone(), missed_block_rx).await;
+
+		let state = state.lock().unwrap();
+		let gaps: Vec<_> = state.gaps.iter().map(|gap| (gap.first, gap.last)).collect();
+		// Block 7 failed to process, so it is still missed
+		assert_eq!(gaps, vec![(6, 7)]);
+	}
+
+	#[tokio::test]
+	pub async fn test_confidence_in_dbstore() {
 		let (block_tx, _) = broadcast::channel::<types::BlockVerified>(10);
 		let pp = Arc::new(testnet::public_params(1024));
 		let cfg = SyncClientConfig::from(&RuntimeConfig::default());
 		let mut mock_client = MockSyncClient::new();
 		mock_client
-			.expect_block_header_in_db()
+			.expect_get_cached_cells()
+			.returning(|_, _| vec![]);
+		mock_client
+			.expect_is_confidence_in_db()
 			.withf(|block: &u32| *block == 42)
 			.returning(|_| Ok(true));
 		mock_client.expect_get_header_by_block_number().never();
-		mock_client.block_header_in_db(42).unwrap();
+		mock_client.expect_store_block_in_db().never();
 		process_block(&mock_client, 42, &cfg, pp, Some(block_tx))
 			.await
 			.unwrap();
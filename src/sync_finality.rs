@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, Context, Result};
 use avail_subxt::{
 	api::{self, runtime_types::sp_core::crypto::KeyTypeId},
 	avail::{self, Client},
@@ -9,22 +9,22 @@ use avail_subxt::{
 };
 use codec::{Decode, Encode};
 use futures::future::join_all;
-use rocksdb::DB;
 use serde::de::{self};
 use serde::Deserialize;
 use sp_core::{
 	blake2_256,
 	bytes::from_hex,
 	ed25519::{self},
-	twox_128, Pair, H256,
+	twox_128, H256,
 };
 // use subxt::rpc_params;
 use tokio::sync::mpsc::Sender;
 use tracing::{error, info, trace};
 
 use crate::{
-	data::{get_finality_sync_checkpoint, store_finality_sync_checkpoint},
-	types::{FinalitySyncCheckpoint, GrandpaJustification, SignerMessage, State},
+	data::Database,
+	finality, rpc,
+	types::{FinalitySyncCheckpoint, GrandpaJustification, State, TrustedCheckpoint},
 	utils::filter_auth_set_changes,
 };
 
@@ -65,35 +65,55 @@ impl<'de> Deserialize<'de> for WrappedProof {
 
 pub trait SyncFinality {
 	fn get_client(&self) -> avail::Client;
-	fn get_db(&self) -> Arc<DB>;
+	fn get_finality_sync_checkpoint(&self) -> Result<Option<FinalitySyncCheckpoint>>;
+	fn store_finality_sync_checkpoint(&self, checkpoint: FinalitySyncCheckpoint) -> Result<()>;
+	fn get_trusted_checkpoint(&self) -> Option<TrustedCheckpoint>;
 }
 
-pub struct SyncFinalityImpl {
-	db: Arc<DB>,
+pub struct SyncFinalityImpl<T: Database> {
+	db: T,
 	rpc_client: avail::Client,
+	trusted_checkpoint: Option<TrustedCheckpoint>,
 }
 
-impl SyncFinality for SyncFinalityImpl {
+impl<T: Database> SyncFinality for SyncFinalityImpl<T> {
 	fn get_client(&self) -> avail::Client {
 		self.rpc_client.clone()
 	}
 
-	fn get_db(&self) -> Arc<DB> {
-		self.db.clone()
+	fn get_finality_sync_checkpoint(&self) -> Result<Option<FinalitySyncCheckpoint>> {
+		self.db.get_finality_sync_checkpoint()
+	}
+
+	fn store_finality_sync_checkpoint(&self, checkpoint: FinalitySyncCheckpoint) -> Result<()> {
+		self.db.store_finality_sync_checkpoint(checkpoint)
+	}
+
+	fn get_trusted_checkpoint(&self) -> Option<TrustedCheckpoint> {
+		self.trusted_checkpoint.clone()
 	}
 }
 
-pub fn new(db: Arc<DB>, rpc_client: avail::Client) -> impl SyncFinality {
-	SyncFinalityImpl { db, rpc_client }
+pub fn new(
+	db: impl Database,
+	rpc_client: avail::Client,
+	trusted_checkpoint: Option<TrustedCheckpoint>,
+) -> impl SyncFinality {
+	SyncFinalityImpl {
+		db,
+		rpc_client,
+		trusted_checkpoint,
+	}
 }
 
 const GRANDPA_KEY_ID: [u8; 4] = *b"gran";
 const GRANDPA_KEY_LEN: usize = 32;
 
+/// Gets GRANDPA authorities at genesis, with equal voting weights
 async fn get_valset_at_genesis(
 	rpc_client: Client,
 	genesis_hash: H256,
-) -> Result<Vec<ed25519::Public>> {
+) -> Result<Vec<(ed25519::Public, u64)>> {
 	let mut k1 = twox_128("Session".as_bytes()).to_vec();
 	let mut k2 = twox_128("KeyOwner".as_bytes()).to_vec();
 	k1.append(&mut k2);
@@ -151,7 +171,7 @@ async fn get_valset_at_genesis(
 	let validator_set = grandpa_keys_and_account
 		.into_iter()
 		.filter(|(_, parent_acc)| validator_set_pre.iter().any(|e| e.0 == parent_acc.0))
-		.map(|(grandpa_key, _)| grandpa_key)
+		.map(|(grandpa_key, _)| (grandpa_key, 1))
 		.collect::<Vec<_>>();
 	Ok(validator_set)
 }
@@ -169,6 +189,51 @@ pub async fn run(
 	};
 }
 
+/// Validates trusted checkpoint against the node and converts it into the finality sync checkpoint
+async fn trusted_finality_sync_checkpoint(
+	rpc_client: &Client,
+	trusted_checkpoint: &TrustedCheckpoint,
+) -> Result<FinalitySyncCheckpoint> {
+	let checkpoint = FinalitySyncCheckpoint::try_from(trusted_checkpoint)?;
+	let block_number = trusted_checkpoint.block_number;
+	let block_hash = rpc::get_block_hash(rpc_client, block_number)
+		.await
+		.context(format!(
+			"Couldn't get hash for trusted checkpoint block no. {block_number}"
+		))?;
+	if block_hash != trusted_checkpoint.block_hash {
+		return Err(anyhow!(
+			"Trusted checkpoint block no. {block_number} hash {:?} doesn't match node block hash {block_hash:?}, node is on a different chain",
+			trusted_checkpoint.block_hash
+		));
+	}
+
+	// Validator set which finalizes blocks after the trusted block is stored in the trusted block state
+	let set_id = rpc::get_set_id_by_hash(rpc_client, block_hash)
+		.await
+		.context(format!(
+			"Couldn't get set ID for trusted checkpoint block no. {block_number}"
+		))?;
+	if set_id != checkpoint.set_id {
+		return Err(anyhow!(
+			"Trusted checkpoint set ID {} doesn't match node set ID {set_id} at block no. {block_number}",
+			checkpoint.set_id
+		));
+	}
+	let validator_set = rpc::get_valset_by_hash(rpc_client, block_hash)
+		.await
+		.context(format!(
+			"Couldn't get validator set for trusted checkpoint block no. {block_number}"
+		))?;
+	if validator_set != checkpoint.validator_set {
+		return Err(anyhow!(
+			"Trusted checkpoint validator set doesn't match node validator set at block no. {block_number}"
+		));
+	}
+
+	Ok(checkpoint)
+}
+
 pub async fn sync_finality(
 	sync_finality: impl SyncFinality,
 	state: Arc<Mutex<State>>,
@@ -176,12 +241,30 @@ pub async fn sync_finality(
 	let rpc_client = sync_finality.get_client();
 	let gen_hash = rpc_client.genesis_hash();
 
-	let checkpoint = get_finality_sync_checkpoint(sync_finality.get_db())?;
+	let mut checkpoint = sync_finality.get_finality_sync_checkpoint()?;
+
+	// Trusted checkpoint is validated and stored once, unless finality is already synced past it
+	if let Some(trusted_checkpoint) = sync_finality.get_trusted_checkpoint() {
+		if checkpoint
+			.as_ref()
+			.map_or(true, |ch| ch.number <= trusted_checkpoint.block_number)
+		{
+			info!(
+				"Starting from trusted checkpoint at block no {}",
+				trusted_checkpoint.block_number
+			);
+			let trusted = trusted_finality_sync_checkpoint(&rpc_client, &trusted_checkpoint)
+				.await
+				.context("Failed to validate trusted checkpoint")?;
+			sync_finality.store_finality_sync_checkpoint(trusted.clone())?;
+			checkpoint = Some(trusted);
+		}
+	}
 
 	info!("Starting finality validation sync.");
 	let mut set_id: u64;
 	let mut curr_block_num = 1u32;
-	let mut validator_set: Vec<ed25519::Public>;
+	let mut validator_set: Vec<(ed25519::Public, u64)>;
 	if let Some(ch) = checkpoint {
 		info!("Continuing from block no {}", ch.number);
 		set_id = ch.set_id;
@@ -267,68 +350,30 @@ pub async fn sync_finality(
 			.context(format!("Couldn't get header for {}", proof_block_hash))?
 			.context(format!("Header for hash {} not found!", proof_block_hash))?;
 
-		let signed_message = Encode::encode(&(
-			&SignerMessage::PrecommitMessage(
-				proof.0.justification.0.commit.precommits[0]
-					.clone()
-					.precommit,
-			),
-			&proof.0.justification.0.round,
-			&set_id,
-		));
-		// Verify all the signatures of the justification signs the hash of the block
-		let signer_addresses = proof
-			.0
-			.justification
-			.0
-			.commit
-			.precommits
-			.iter()
-			.map(|precommit| {
-				let is_ok = <ed25519::Pair as Pair>::verify(
-					&precommit.signature,
-					&signed_message,
-					&precommit.id,
-				);
-				is_ok
-					.then(|| precommit.clone().id)
-					.ok_or_else(|| anyhow!("Not signed by this signature!"))
-			})
-			.collect::<Result<Vec<_>>>();
-
-		let Ok(signer_addresses) = signer_addresses else {
-			error!("Verification failed!");
-			bail!("Some of the signatures don't match");
-		};
-
-		let num_matched_addresses = signer_addresses
-			.iter()
-			.filter(|x| validator_set.iter().any(|e| e.0.eq(&x.0)))
-			.count();
+		// Verify that the justification is signed by the supermajority of the validator set by weight
+		let signed_weight =
+			finality::verify_justification(&proof.0.justification.0, set_id, &validator_set)
+				.context(format!(
+					"Failed to verify justification for block no. {curr_block_num}"
+				))?;
 		info!(
-			"Number of matching signatures for block {curr_block_num}: {num_matched_addresses}/{}",
-			validator_set.len()
+			"Signed weight for block {curr_block_num}: {signed_weight}/{}",
+			validator_set.iter().map(|(_, weight)| weight).sum::<u64>()
 		);
-		if num_matched_addresses < (validator_set.len() * 2 / 3) {
-			bail!("Not signed by the supermajority of the validator set.");
-		} else {
-			trace!("Proof in block: {}", p_h.number);
-			curr_block_num += 1;
 
-			validator_set = next_validator_set[0]
-				.iter()
-				.map(|a| ed25519::Public::from_raw(a.0 .0 .0 .0))
-				.collect();
-			set_id += 1;
-			store_finality_sync_checkpoint(
-				sync_finality.get_db(),
-				FinalitySyncCheckpoint {
-					number: curr_block_num,
-					set_id,
-					validator_set: validator_set.clone(),
-				},
-			)?;
-		}
+		trace!("Proof in block: {}", p_h.number);
+		curr_block_num += 1;
+
+		validator_set = next_validator_set[0]
+			.iter()
+			.map(|(a, weight)| (ed25519::Public::from_raw(a.0 .0 .0), *weight))
+			.collect();
+		set_id += 1;
+		sync_finality.store_finality_sync_checkpoint(FinalitySyncCheckpoint {
+			number: curr_block_num,
+			set_id,
+			validator_set: validator_set.clone(),
+		})?;
 	}
 	state.lock().unwrap().finality_synced = true;
 	info!("Finality is fully synced.");
@@ -6,14 +6,19 @@ pub mod otlp;
 
 pub enum MetricCounter {
 	SessionBlock,
+	AvailabilityFailure,
+	Equivocation,
 }
 
 pub enum MetricValue {
 	TotalBlockNumber(u32),
 	DHTFetched(f64),
 	DHTFetchedPercentage(f64),
+	DHTSamplingRounds(u32),
 	NodeRPCFetched(f64),
 	BlockConfidence(f64),
+	DHTConfidence(f64),
+	RPCConfidence(f64),
 	RPCCallDuration(f64),
 	DHTPutDuration(f64),
 	DHTPutSuccess(f64),
@@ -13,6 +13,8 @@ use tokio::sync::RwLock;
 pub struct Metrics {
 	meter: Meter,
 	session_block_counter: Counter<u64>,
+	availability_failure_counter: Counter<u64>,
+	equivocation_counter: Counter<u64>,
 	peer_id: String,
 	multiaddress: RwLock<String>,
 	ip: RwLock<String>,
@@ -68,6 +70,13 @@ impl super::Metrics for Metrics {
 			super::MetricCounter::SessionBlock => {
 				self.session_block_counter.add(1, &self.attributes().await);
 			},
+			super::MetricCounter::AvailabilityFailure => {
+				self.availability_failure_counter
+					.add(1, &self.attributes().await);
+			},
+			super::MetricCounter::Equivocation => {
+				self.equivocation_counter.add(1, &self.attributes().await);
+			},
 		}
 	}
 
@@ -82,12 +91,22 @@ impl super::Metrics for Metrics {
 			super::MetricValue::DHTFetchedPercentage(number) => {
 				self.record_f64("dht_fetched_percentage", number).await?;
 			},
+			super::MetricValue::DHTSamplingRounds(number) => {
+				self.record_u64("dht_sampling_rounds", number.into())
+					.await?;
+			},
 			super::MetricValue::NodeRPCFetched(number) => {
 				self.record_f64("node_rpc_fetched", number).await?;
 			},
 			super::MetricValue::BlockConfidence(number) => {
 				self.record_f64("block_confidence", number).await?;
 			},
+			super::MetricValue::DHTConfidence(number) => {
+				self.record_f64("dht_confidence", number).await?;
+			},
+			super::MetricValue::RPCConfidence(number) => {
+				self.record_f64("rpc_confidence", number).await?;
+			},
 			super::MetricValue::RPCCallDuration(number) => {
 				self.record_f64("rpc_call_duration", number).await?;
 			},
@@ -151,11 +170,14 @@ pub fn initialize(endpoint: String, peer_id: String, role: String) -> Result<Met
 	global::set_meter_provider(provider);
 	let meter = global::meter("avail_light_client");
 	// Initialize counters - they need to persist unlike Gauges that are recreated on every record
-	// TODO - move to a separate func once there's more than 1 counter
 	let session_block_counter = meter.u64_counter("session_block_counter").init();
+	let availability_failure_counter = meter.u64_counter("availability_failure_counter").init();
+	let equivocation_counter = meter.u64_counter("equivocation_counter").init();
 	Ok(Metrics {
 		meter,
 		session_block_counter,
+		availability_failure_counter,
+		equivocation_counter,
 		peer_id,
 		multiaddress: RwLock::new("".to_string()), // Default value is empty until first processed block triggers an update
 		ip: RwLock::new("".to_string()),
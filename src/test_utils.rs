@@ -0,0 +1,37 @@
+//! Fixtures shared by unit tests of multiple modules.
+
+use avail_subxt::{
+	api::runtime_types::avail_core::{
+		data_lookup::compact::CompactDataLookup,
+		header::extension::{v2, HeaderExtension},
+		kate_commitment::v2::KateCommitment,
+	},
+	primitives::Header as DaHeader,
+	utils::H256,
+};
+use codec::Encode;
+use sp_core::blake2_256;
+use subxt::config::substrate::Digest;
+
+/// Returns header of the empty block with given number and parent hash
+pub fn header(number: u32, parent_hash: H256) -> DaHeader {
+	DaHeader {
+		parent_hash,
+		number,
+		state_root: H256::default(),
+		extrinsics_root: H256::default(),
+		extension: HeaderExtension::V2(v2::HeaderExtension {
+			commitment: KateCommitment::default(),
+			app_lookup: CompactDataLookup {
+				size: 0,
+				index: vec![],
+			},
+		}),
+		digest: Digest { logs: vec![] },
+	}
+}
+
+/// Returns block hash of the given header
+pub fn header_hash(header: &DaHeader) -> H256 {
+	Encode::using_encoded(header, blake2_256).into()
+}
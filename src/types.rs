@@ -1,5 +1,7 @@
 //! Shared light client structs and enums.
 
+use crate::consts::BLOCK_TIME_SECS;
+use crate::proof::PublicParamsPreset;
 use crate::utils::{extract_app_lookup, extract_kate};
 use anyhow::anyhow;
 use anyhow::{Context, Result};
@@ -8,12 +10,16 @@ use avail_subxt::{primitives::Header as DaHeader, utils::H256};
 use codec::{Decode, Encode};
 use kate_recovery::{
 	commitments,
-	matrix::{Dimensions, Partition},
+	data::Cell,
+	matrix::{Dimensions, Partition, Position},
 };
 use libp2p::{Multiaddr, PeerId};
 use serde::{de::Error, Deserialize, Serialize};
 use sp_core::{blake2_256, bytes, ed25519};
-use std::str::FromStr;
+use std::{
+	collections::{BTreeSet, HashMap},
+	str::FromStr,
+};
 
 use clap::Parser;
 use std::num::NonZeroUsize;
@@ -47,6 +53,12 @@ pub struct CliOpts {
 	/// Log level
 	#[arg(long)]
 	pub verbosity: Option<LogLevel>,
+	/// Export snapshot of the stored light client state into the file, and exit
+	#[arg(long, value_name = "FILE")]
+	pub export_snapshot: Option<String>,
+	/// Import snapshot of the light client state from the file on startup
+	#[arg(long, value_name = "FILE")]
+	pub import_snapshot: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -61,6 +73,20 @@ pub struct RuntimeVersionResult {
 	transaction_version: u32,
 }
 
+/// Merkle inclusion proof of the data transaction, committed under the block data root
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataProof {
+	/// Root of the Merkle tree, expected to match the data root in the block header
+	pub root: H256,
+	/// Proof items, ordered from the leaf towards the root
+	pub proof: Vec<H256>,
+	pub number_of_leaves: u32,
+	pub leaf_index: u32,
+	/// Keccak-256 hash of the transaction data
+	pub leaf: H256,
+}
+
 /// Light to app client channel message struct
 #[derive(Clone, Debug)]
 pub struct BlockVerified {
@@ -72,6 +98,14 @@ pub struct BlockVerified {
 	pub confidence: Option<f64>,
 }
 
+/// Request to fetch and verify application data for the range of already verified blocks
+#[derive(Clone, Debug)]
+pub struct AppBackfill {
+	pub app_id: u32,
+	pub first: u32,
+	pub last: u32,
+}
+
 impl TryFrom<(DaHeader, Option<f64>)> for BlockVerified {
 	type Error = anyhow::Error;
 	fn try_from((header, confidence): (DaHeader, Option<f64>)) -> Result<Self, Self::Error> {
@@ -236,10 +270,18 @@ pub struct RuntimeConfig {
 	pub full_node_ws: Vec<String>,
 	/// ID of application used to start application client. If app_id is not set, or set to 0, application client is not started (default: 0).
 	pub app_id: Option<u32>,
+	/// IDs of additional applications tracked by the application client, along with the `app_id` (default: empty).
+	pub app_ids: Vec<u32>,
 	/// Confidence threshold, used to calculate how many cells need to be sampled to achieve desired confidence (default: 92.0).
 	pub confidence: f64,
+	/// Minimum confidence calculated only from cells fetched from DHT peers, required for the block to achieve confidence.
+	/// Blocks below the minimum fail data availability verification, and their confidence is not stored.
+	/// If not set, confidence calculated from cells fetched from both DHT and RPC is used (default: None).
+	pub min_dht_confidence: Option<f64>,
 	/// File system path where RocksDB used by light client, stores its data.
 	pub avail_path: String,
+	/// If set to true, data is stored in memory instead of RocksDB, and it is lost on restart (default: false).
+	pub in_memory_db: bool,
 	/// Log level, default is `INFO`. See `<https://docs.rs/log/0.4.14/log/enum.LevelFilter.html>` for possible log level values. (default: `INFO`).
 	pub log_level: String,
 	/// If set to true, logs are displayed in JSON format, which is used for structured logging. Otherwise, plain text format is used (default: false).
@@ -250,6 +292,13 @@ pub struct RuntimeConfig {
 	pub disable_rpc: bool,
 	/// Disables proof verification in general, if set to true, otherwise proof verification is performed. (default: false).
 	pub disable_proof_verification: bool,
+	/// Path to the file with KZG public parameters. If not set, embedded preset public parameters are used (default: None).
+	pub public_params_path: Option<String>,
+	/// Embedded KZG public parameters preset. If `--network` flag is used, network preset is used if not set (default: testnet).
+	pub public_params_preset: Option<PublicParamsPreset>,
+	/// Expected hex encoded Blake2-256 hash of KZG public parameters. Light client refuses to start on mismatch.
+	/// If not set, public parameters loaded from the file are expected to match the preset (default: None).
+	pub public_params_hash: Option<String>,
 	/// Maximum number of parallel tasks spawned for GET and PUT operations on DHT (default: 20).
 	pub dht_parallelization_limit: usize,
 	/// Number of records to be inserted into DHT simultaneously (default: 1000)
@@ -258,6 +307,8 @@ pub struct RuntimeConfig {
 	pub query_proof_rpc_parallel_tasks: usize,
 	/// Number of seconds to postpone block processing after block finalized message arrives (default: 0).
 	pub block_processing_delay: Option<u32>,
+	/// Maximum number of blocks processed concurrently by the light client (default: 4).
+	pub block_processing_parallel_tasks: usize,
 	/// Fraction and number of the block matrix part to fetch (e.g. 2/20 means second 1/20 part of a matrix) (default: None)
 	#[serde(with = "block_matrix_partition_format")]
 	pub block_matrix_partition: Option<Partition>,
@@ -267,8 +318,36 @@ pub struct RuntimeConfig {
 	pub sync_finality_enable: bool,
 	/// Maximum number of cells per request for proof queries (default: 30).
 	pub max_cells_per_rpc: Option<usize>,
+	/// Maximum number of DHT sampling rounds. If some of the sampled cells are not available in DHT,
+	/// fresh random cells are sampled from DHT in the next round, before falling back to RPC (default: 3).
+	pub dht_sampling_rounds: usize,
+	/// Maximum number of attempts for fetching cells and rows from DHT or RPC (default: 3).
+	pub retry_attempts: u32,
+	/// Delay in milliseconds before the first retry, doubled on every subsequent retry (default: 500).
+	pub retry_backoff: u64,
+	/// Timeout in seconds for a single RPC fetch attempt (default: 60).
+	pub retry_timeout: u32,
+	/// Timeout in seconds for a single DHT fetch attempt (default: 10).
+	pub dht_retry_timeout: u32,
 	/// Threshold for the number of cells fetched via DHT for the app client (default: 5000)
 	pub threshold: usize,
+	/// If set to true, application client reconstructs entire block matrix from DHT cells for every block,
+	/// and stores data of all applications. Application client is started even if no app ID is configured (default: false).
+	pub full_block_reconstruction: bool,
+	/// Retention policy for block headers and justifications stored in database. Set to number of last blocks to keep (e.g. "1000"),
+	/// or to duration of time for which blocks are kept (e.g. "30m", "24h", "7d"). If not set, headers are never pruned (default: None).
+	pub block_header_retention: Option<Retention>,
+	/// Retention policy for confidence factors stored in database, in the same format as `block_header_retention` (default: None).
+	pub confidence_retention: Option<Retention>,
+	/// Retention policy for application data stored in database, in the same format as `block_header_retention` (default: None).
+	pub app_data_retention: Option<Retention>,
+	/// Retention policy for locally cached verified cells and rows, in the same format as `block_header_retention`.
+	/// Cached cells and rows are served instead of fetching them again. If not set, caching is disabled (default: None).
+	pub cache_retention: Option<Retention>,
+	/// Maximum size of locally cached cells and rows, in megabytes. If exceeded, cache of the oldest blocks is pruned (default: 1024).
+	pub cache_max_size: u64,
+	/// Interval in which the pruning of stored data is performed, in seconds (default: 300 sec).
+	pub pruning_interval: u64,
 	/// Kademlia configuration - WARNING: Changing the default values might cause the peer to suffer poor performance!
 	/// Default Kademlia config values have been copied from rust-libp2p Kademila defaults
 	///
@@ -310,6 +389,9 @@ pub struct RuntimeConfig {
 	/// Avail account secret key. (default: None)
 	#[serde(skip_serializing)]
 	pub avail_secret_key: Option<AvailSecretKey>,
+	/// Trusted finality checkpoint to start finality sync from, instead of genesis (default: None).
+	/// If `--network` flag is used, network default is used if set.
+	pub trusted_checkpoint: Option<TrustedCheckpoint>,
 	#[cfg(feature = "crawl")]
 	#[serde(flatten)]
 	pub crawl: crate::crawl_client::CrawlConfig,
@@ -337,20 +419,126 @@ impl TryFrom<String> for AvailSecretKey {
 	}
 }
 
+/// Retention policy for the data stored in database
+///
+/// * `Blocks` - keeps given number of last blocks
+/// * `Duration` - keeps blocks newer than given duration, assuming expected block time
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Retention {
+	Blocks(u32),
+	Duration(Duration),
+}
+
+impl Retention {
+	/// Number of last blocks to keep (at least one)
+	pub fn blocks(&self) -> u32 {
+		match self {
+			Retention::Blocks(blocks) => (*blocks).max(1),
+			Retention::Duration(duration) => {
+				let blocks = (duration.as_secs() + BLOCK_TIME_SECS - 1) / BLOCK_TIME_SECS;
+				u32::try_from(blocks).unwrap_or(u32::MAX).max(1)
+			},
+		}
+	}
+}
+
+impl TryFrom<String> for Retention {
+	type Error = anyhow::Error;
+
+	fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+		let value = value.trim();
+		let Some(unit) = value.chars().last() else {
+			return Err(anyhow!("Retention value is empty"));
+		};
+		if unit.is_ascii_digit() {
+			let blocks = value
+				.parse::<u32>()
+				.context(format!("Invalid number of blocks {value}"))?;
+			return Ok(Retention::Blocks(blocks));
+		}
+		let multiplier = match unit {
+			's' => 1,
+			'm' => 60,
+			'h' => 60 * 60,
+			'd' => 24 * 60 * 60,
+			_ => {
+				return Err(anyhow!(
+					"Invalid retention unit {unit}, valid units are s, m, h and d"
+				))
+			},
+		};
+		let amount = value[..value.len() - 1]
+			.parse::<u64>()
+			.context(format!("Invalid retention duration {value}"))?;
+		let seconds = amount
+			.checked_mul(multiplier)
+			.ok_or_else(|| anyhow!("Retention duration {value} is too large"))?;
+		Ok(Retention::Duration(Duration::from_secs(seconds)))
+	}
+}
+
+impl From<Retention> for String {
+	fn from(value: Retention) -> Self {
+		match value {
+			Retention::Blocks(blocks) => blocks.to_string(),
+			Retention::Duration(duration) => format!("{}s", duration.as_secs()),
+		}
+	}
+}
+
 pub struct Delay(pub Option<Duration>);
 
+/// Retry policy for DHT and RPC retrieval (see [RuntimeConfig] for details)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryConfig {
+	pub attempts: u32,
+	pub backoff: Duration,
+	pub timeout: Duration,
+}
+
+impl RetryConfig {
+	/// Returns delay before the retry following given (1-based) failed attempt
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		self.backoff
+			.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+	}
+
+	/// Returns retry policy for DHT retrieval, with DHT specific attempt timeout
+	pub fn dht(val: &RuntimeConfig) -> Self {
+		RetryConfig {
+			timeout: Duration::from_secs(val.dht_retry_timeout.into()),
+			..val.into()
+		}
+	}
+}
+
+impl From<&RuntimeConfig> for RetryConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		RetryConfig {
+			attempts: val.retry_attempts.max(1),
+			backoff: Duration::from_millis(val.retry_backoff),
+			timeout: Duration::from_secs(val.retry_timeout.into()),
+		}
+	}
+}
+
 /// Light client configuration (see [RuntimeConfig] for details)
 pub struct LightClientConfig {
 	pub full_node_ws: Vec<String>,
 	pub confidence: f64,
+	pub min_dht_confidence: Option<f64>,
 	pub disable_rpc: bool,
 	pub dht_parallelization_limit: usize,
 	pub query_proof_rpc_parallel_tasks: usize,
 	pub block_processing_delay: Delay,
+	pub block_processing_parallel_tasks: usize,
 	pub block_matrix_partition: Option<Partition>,
 	pub disable_proof_verification: bool,
 	pub max_cells_per_rpc: usize,
+	pub dht_sampling_rounds: usize,
 	pub ttl: u64,
+	pub cache: bool,
 }
 
 impl Delay {
@@ -370,14 +558,18 @@ impl From<&RuntimeConfig> for LightClientConfig {
 		LightClientConfig {
 			full_node_ws: val.full_node_ws.clone(),
 			confidence: val.confidence,
+			min_dht_confidence: val.min_dht_confidence,
 			disable_rpc: val.disable_rpc,
 			dht_parallelization_limit: val.dht_parallelization_limit,
 			query_proof_rpc_parallel_tasks: val.query_proof_rpc_parallel_tasks,
 			block_processing_delay: Delay(block_processing_delay),
+			block_processing_parallel_tasks: val.block_processing_parallel_tasks,
 			block_matrix_partition: val.block_matrix_partition,
 			disable_proof_verification: val.disable_proof_verification,
 			max_cells_per_rpc: val.max_cells_per_rpc.unwrap_or(30),
+			dht_sampling_rounds: val.dht_sampling_rounds,
 			ttl: val.kad_record_ttl,
+			cache: val.cache_retention.is_some(),
 		}
 	}
 }
@@ -482,27 +674,35 @@ impl From<&RuntimeConfig> for IdentifyConfig {
 #[derive(Clone)]
 pub struct SyncClientConfig {
 	pub confidence: f64,
+	pub min_dht_confidence: Option<f64>,
 	pub disable_rpc: bool,
 	pub dht_parallelization_limit: usize,
 	pub ttl: u64,
+	pub cache: bool,
 }
 
 impl From<&RuntimeConfig> for SyncClientConfig {
 	fn from(val: &RuntimeConfig) -> Self {
 		SyncClientConfig {
 			confidence: val.confidence,
+			min_dht_confidence: val.min_dht_confidence,
 			disable_rpc: val.disable_rpc,
 			dht_parallelization_limit: val.dht_parallelization_limit,
 			ttl: val.kad_record_ttl,
+			cache: val.cache_retention.is_some(),
 		}
 	}
 }
 
 /// App client configuration (see [RuntimeConfig] for details)
+#[derive(Clone)]
 pub struct AppClientConfig {
 	pub dht_parallelization_limit: usize,
 	pub disable_rpc: bool,
 	pub threshold: usize,
+	pub cache: bool,
+	pub full_block_reconstruction: bool,
+	pub retry: RetryConfig,
 }
 
 impl From<&RuntimeConfig> for AppClientConfig {
@@ -511,9 +711,49 @@ impl From<&RuntimeConfig> for AppClientConfig {
 			dht_parallelization_limit: val.dht_parallelization_limit,
 			disable_rpc: val.disable_rpc,
 			threshold: val.threshold,
+			cache: val.cache_retention.is_some(),
+			full_block_reconstruction: val.full_block_reconstruction,
+			retry: val.into(),
+		}
+	}
+}
+/// Pruning configuration (see [RuntimeConfig] for details)
+///
+/// Retention policies are converted to the number of last blocks to keep.
+pub struct PruningConfig {
+	pub interval: Duration,
+	pub block_header_retention: Option<u32>,
+	pub confidence_retention: Option<u32>,
+	pub app_data_retention: Option<u32>,
+	pub cache_retention: Option<u32>,
+	/// Maximum cache size in bytes, set if caching is enabled
+	pub cache_max_size: Option<u64>,
+}
+
+impl PruningConfig {
+	pub fn is_enabled(&self) -> bool {
+		self.block_header_retention.is_some()
+			|| self.confidence_retention.is_some()
+			|| self.app_data_retention.is_some()
+			|| self.cache_retention.is_some()
+	}
+}
+
+impl From<&RuntimeConfig> for PruningConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		PruningConfig {
+			interval: Duration::from_secs(val.pruning_interval),
+			block_header_retention: val.block_header_retention.map(|r| r.blocks()),
+			confidence_retention: val.confidence_retention.map(|r| r.blocks()),
+			app_data_retention: val.app_data_retention.map(|r| r.blocks()),
+			cache_retention: val.cache_retention.map(|r| r.blocks()),
+			cache_max_size: val
+				.cache_retention
+				.map(|_| val.cache_max_size.saturating_mul(1024 * 1024)),
 		}
 	}
 }
+
 impl Default for RuntimeConfig {
 	fn default() -> Self {
 		RuntimeConfig {
@@ -534,23 +774,42 @@ impl Default for RuntimeConfig {
 			relays: vec![],
 			full_node_ws: vec!["ws://127.0.0.1:9944".to_owned()],
 			app_id: None,
+			app_ids: vec![],
 			confidence: 92.0,
+			min_dht_confidence: None,
 			avail_path: "avail_path".to_owned(),
+			in_memory_db: false,
 			log_level: "INFO".to_owned(),
 			log_format_json: false,
 			ot_collector_endpoint: "http://otelcollector.avail.tools:4317".to_string(),
 			disable_rpc: false,
 			disable_proof_verification: false,
+			public_params_path: None,
+			public_params_preset: None,
+			public_params_hash: None,
 			dht_parallelization_limit: 20,
 			put_batch_size: 1000,
 			query_proof_rpc_parallel_tasks: 8,
 			block_processing_delay: None,
+			block_processing_parallel_tasks: 4,
 			block_matrix_partition: None,
 			sync_start_block: None,
 			sync_finality_enable: true,
 			max_cells_per_rpc: Some(30),
+			dht_sampling_rounds: 3,
+			retry_attempts: 3,
+			retry_backoff: 500,
+			retry_timeout: 60,
+			dht_retry_timeout: 10,
 			kad_record_ttl: 24 * 60 * 60,
 			threshold: 5000,
+			full_block_reconstruction: false,
+			block_header_retention: None,
+			confidence_retention: None,
+			app_data_retention: None,
+			cache_retention: None,
+			cache_max_size: 1024,
+			pruning_interval: 300,
 			replication_factor: 20,
 			publication_interval: 12 * 60 * 60,
 			replication_interval: 3 * 60 * 60,
@@ -563,6 +822,7 @@ impl Default for RuntimeConfig {
 			max_kad_record_size: 8192,
 			max_kad_provided_keys: 1024,
 			avail_secret_key: None,
+			trusted_checkpoint: None,
 			#[cfg(feature = "crawl")]
 			crawl: crate::crawl_client::CrawlConfig::default(),
 		}
@@ -610,6 +870,24 @@ impl Network {
 			Network::Biryani => "wss://biryani-devnet.avail.tools:443/ws",
 		}
 	}
+
+	/// Embedded KZG public parameters preset of the network trusted setup
+	fn public_params_preset(&self) -> PublicParamsPreset {
+		match self {
+			Network::Local => PublicParamsPreset::Testnet,
+			Network::Biryani => PublicParamsPreset::Testnet,
+		}
+	}
+
+	/// Default trusted finality checkpoint of the network, if any
+	fn trusted_checkpoint(&self) -> Option<TrustedCheckpoint> {
+		match self {
+			// Local network is started from genesis, so finality is synced from genesis
+			Network::Local => None,
+			// Devnet is frequently reset, so there is no stable checkpoint to trust yet
+			Network::Biryani => None,
+		}
+	}
 }
 
 #[derive(Clone)]
@@ -649,6 +927,20 @@ impl ToString for LogLevel {
 }
 
 impl RuntimeConfig {
+	/// Returns sorted IDs of all applications tracked by the application client, ignoring ID 0
+	pub fn app_ids(&self) -> Vec<u32> {
+		let mut app_ids = self
+			.app_id
+			.iter()
+			.chain(self.app_ids.iter())
+			.copied()
+			.filter(|&app_id| app_id != 0)
+			.collect::<Vec<_>>();
+		app_ids.sort_unstable();
+		app_ids.dedup();
+		app_ids
+	}
+
 	pub fn load_runtime_config(&mut self, opts: &CliOpts) -> Result<()> {
 		if let Some(config_path) = &opts.config {
 			fs::metadata(config_path)
@@ -668,6 +960,12 @@ impl RuntimeConfig {
 			);
 			self.full_node_ws = vec![network.full_node_ws().to_string()];
 			self.bootstraps = vec![MultiaddrConfig::PeerIdAndMultiaddr(bootstrap)];
+			if self.trusted_checkpoint.is_none() {
+				self.trusted_checkpoint = network.trusted_checkpoint();
+			}
+			if self.public_params_preset.is_none() {
+				self.public_params_preset = Some(network.public_params_preset());
+			}
 		}
 
 		if let Some(loglvl) = &opts.verbosity {
@@ -701,6 +999,24 @@ impl BlockRange {
 	}
 }
 
+/// Stored block data which is pruned by the block range
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pruned {
+	Headers,
+	Confidence,
+	AppData,
+	Cache,
+}
+
+impl Pruned {
+	pub const ALL: [Pruned; 4] = [
+		Pruned::Headers,
+		Pruned::Confidence,
+		Pruned::AppData,
+		Pruned::Cache,
+	];
+}
+
 #[derive(Default)]
 pub struct State {
 	pub synced: Option<bool>,
@@ -713,6 +1029,93 @@ pub struct State {
 	pub sync_confidence_achieved: Option<BlockRange>,
 	pub sync_data_verified: Option<BlockRange>,
 	pub finality_synced: bool,
+	pub header_pruned_below: Option<u32>,
+	pub confidence_pruned_below: Option<u32>,
+	pub data_pruned_below: Option<u32>,
+	pub cache_pruned_below: Option<u32>,
+	/// IDs of applications currently tracked by the application client
+	pub app_ids: BTreeSet<u32>,
+	/// Verified data ranges per application
+	pub apps: HashMap<u32, AppState>,
+	/// Ranges of blocks missed by the light client, which are scheduled for backfill
+	pub gaps: Vec<BlockRange>,
+	/// Blocks for which data availability verification failed
+	pub availability_failed: BTreeSet<u32>,
+}
+
+#[derive(Clone, Default)]
+pub struct AppState {
+	pub data_verified: Option<BlockRange>,
+	pub sync_data_verified: Option<BlockRange>,
+	/// Range of already verified blocks with app data fetched after the app was added
+	pub backfilled: Option<BlockRange>,
+}
+
+impl AppState {
+	pub fn contains(&self, block_number: u32) -> bool {
+		self.data_verified.contains(block_number)
+			|| self.sync_data_verified.contains(block_number)
+			|| self.backfilled.contains(block_number)
+	}
+}
+
+impl State {
+	pub fn is_header_pruned(&self, block_number: u32) -> bool {
+		is_pruned(self.header_pruned_below, block_number)
+	}
+
+	pub fn is_confidence_pruned(&self, block_number: u32) -> bool {
+		is_pruned(self.confidence_pruned_below, block_number)
+	}
+
+	pub fn is_data_pruned(&self, block_number: u32) -> bool {
+		is_pruned(self.data_pruned_below, block_number)
+	}
+
+	/// Returns block number below which given block data is pruned
+	pub fn pruned_below_mut(&mut self, pruned: Pruned) -> &mut Option<u32> {
+		match pruned {
+			Pruned::Headers => &mut self.header_pruned_below,
+			Pruned::Confidence => &mut self.confidence_pruned_below,
+			Pruned::AppData => &mut self.data_pruned_below,
+			Pruned::Cache => &mut self.cache_pruned_below,
+		}
+	}
+
+	/// Records missed block, extending the last gap if block directly follows it
+	pub fn add_missed_block(&mut self, block_number: u32) {
+		match self.gaps.last_mut() {
+			Some(gap) if gap.last + 1 == block_number => gap.last = block_number,
+			_ => self.gaps.push(BlockRange::init(block_number)),
+		}
+	}
+
+	/// Removes processed block from the gap containing it, splitting the gap if needed
+	pub fn remove_missed_block(&mut self, block_number: u32) {
+		let Some(index) = self.gaps.iter().position(|gap| gap.contains(block_number)) else {
+			return;
+		};
+		let BlockRange { first, last } = self.gaps.remove(index);
+		let remaining = [
+			(first < block_number).then(|| BlockRange {
+				first,
+				last: block_number - 1,
+			}),
+			(block_number < last).then(|| BlockRange {
+				first: block_number + 1,
+				last,
+			}),
+		];
+		for (offset, gap) in remaining.into_iter().flatten().enumerate() {
+			self.gaps.insert(index + offset, gap);
+		}
+	}
+}
+
+fn is_pruned(pruned_below: Option<u32>, block_number: u32) -> bool {
+	pruned_below
+		.map(|pruned_below| block_number < pruned_below)
+		.unwrap_or(false)
 }
 
 pub trait OptionBlockRange {
@@ -720,6 +1123,8 @@ pub trait OptionBlockRange {
 	fn first(&self) -> Option<u32>;
 	fn last(&self) -> Option<u32>;
 	fn contains(&self, block_number: u32) -> bool;
+	/// Returns part of the range which is not pruned, if any
+	fn retained(&self, pruned_below: Option<u32>) -> Option<BlockRange>;
 }
 
 impl OptionBlockRange for Option<BlockRange> {
@@ -743,13 +1148,239 @@ impl OptionBlockRange for Option<BlockRange> {
 			.map(|range| range.contains(block_number))
 			.unwrap_or(false)
 	}
+
+	fn retained(&self, pruned_below: Option<u32>) -> Option<BlockRange> {
+		let range = self.as_ref()?;
+		let Some(pruned_below) = pruned_below else {
+			return Some(range.clone());
+		};
+		(pruned_below <= range.last).then(|| BlockRange {
+			first: range.first.max(pruned_below),
+			last: range.last,
+		})
+	}
+}
+
+/// Source the sampled cell is fetched from
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
+pub enum CellSource {
+	Dht,
+	Rpc,
+	/// Cell verified earlier and stored in the local cache
+	Cache,
+	/// Cell is sampled, but not fetched from any source
+	Unavailable,
+}
+
+/// Sampled cell position, source and verification outcome
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+pub struct SampledCell {
+	pub row: u32,
+	pub col: u16,
+	pub source: CellSource,
+	pub verified: bool,
+}
+
+/// Cells sampled from the block in order to achieve the confidence
+#[derive(Clone, Debug, Default, Decode, Encode, PartialEq, Eq)]
+pub struct SamplingRecord {
+	pub cells: Vec<SampledCell>,
+}
+
+impl SamplingRecord {
+	/// Creates sampling record from the cached cells, cells fetched from DHT and RPC, and verified positions.
+	/// Sampled positions which are not fetched from any source are recorded as unavailable.
+	pub fn new(
+		sampled: &[Position],
+		cached: &[Cell],
+		dht_fetched: &[Cell],
+		rpc_fetched: &[Cell],
+		verified: &[Position],
+	) -> Self {
+		let fetched = |source| {
+			move |cell: &Cell| SampledCell {
+				row: cell.position.row,
+				col: cell.position.col,
+				source,
+				verified: verified.contains(&cell.position),
+			}
+		};
+		let is_unfetched = |position: &&Position| {
+			[cached, dht_fetched, rpc_fetched]
+				.iter()
+				.all(|cells| cells.iter().all(|cell| cell.position != **position))
+		};
+		let unavailable = |position: &Position| SampledCell {
+			row: position.row,
+			col: position.col,
+			source: CellSource::Unavailable,
+			verified: false,
+		};
+		let cells = cached
+			.iter()
+			.map(fetched(CellSource::Cache))
+			.chain(dht_fetched.iter().map(fetched(CellSource::Dht)))
+			.chain(rpc_fetched.iter().map(fetched(CellSource::Rpc)))
+			.chain(sampled.iter().filter(is_unfetched).map(unavailable))
+			.collect();
+		SamplingRecord { cells }
+	}
+
+	/// Counts verified cells per cell source, cached cells are not counted
+	pub fn confidence_counts(&self) -> ConfidenceCounts {
+		let count = |source| {
+			self.cells
+				.iter()
+				.filter(|cell| cell.verified && cell.source == source)
+				.count() as u32
+		};
+		ConfidenceCounts {
+			dht: count(CellSource::Dht),
+			rpc: count(CellSource::Rpc),
+		}
+	}
+}
+
+/// Reason why data availability of the block could not be verified
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureReason {
+	/// Not enough cells could be fetched from DHT and RPC
+	CellsUnavailable,
+	/// Some of the fetched cells failed proof verification
+	InvalidProofs,
+	/// Confidence calculated from cells fetched from DHT peers is below the required minimum
+	InsufficientDhtConfidence,
+}
+
+/// Failed data availability verification of the block
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
+pub struct AvailabilityFailure {
+	pub reason: FailureReason,
+	/// Number of cells required to achieve the confidence
+	pub cells_requested: u32,
+	/// Number of cells fetched from DHT and RPC
+	pub cells_fetched: u32,
+	/// Number of fetched cells which failed proof verification
+	pub cells_unverified: u32,
 }
 
-#[derive(Debug, Decode, Encode)]
+/// Number of verified cells per cell source, used to calculate confidence separately
+/// for cells served by peers and cells served by the node
+#[derive(Clone, Copy, Debug, Default, Decode, Encode, PartialEq, Eq)]
+pub struct ConfidenceCounts {
+	pub dht: u32,
+	pub rpc: u32,
+}
+
+#[derive(Clone, Debug, Decode, Encode)]
 pub struct FinalitySyncCheckpoint {
 	pub number: u32,
 	pub set_id: u64,
-	pub validator_set: Vec<ed25519::Public>,
+	/// Validators with their voting weights
+	pub validator_set: Vec<(ed25519::Public, u64)>,
+}
+
+/// Kind of the finalized header chain violation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChainViolationKind {
+	/// Parent hash of the finalized header doesn't match hash of the previous finalized header
+	BrokenLink,
+	/// Different header is already finalized at the same height, which is a safety violation
+	ConflictingHeaders,
+}
+
+/// Violation of the finalized header chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainViolation {
+	pub block_number: u32,
+	pub kind: ChainViolationKind,
+	/// Hash of the previous finalized header, or of the header already finalized at the same height
+	pub expected_hash: H256,
+	/// Parent hash of the received header, or hash of the received header
+	pub received_hash: H256,
+}
+
+impl fmt::Display for ChainViolation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let block_number = self.block_number;
+		let (expected, received) = (self.expected_hash, self.received_hash);
+		match self.kind {
+			ChainViolationKind::BrokenLink => write!(
+				f,
+				"Finalized header {block_number} doesn't link to the previous header (expected parent hash {expected:?}, received {received:?})"
+			),
+			ChainViolationKind::ConflictingHeaders => write!(
+				f,
+				"Conflicting headers are finalized at height {block_number} (stored hash {expected:?}, received {received:?})"
+			),
+		}
+	}
+}
+
+/// Verified GRANDPA justification of the finalized block
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct FinalityJustification {
+	/// ID of the validator set which signed the justification
+	pub set_id: u64,
+	pub justification: GrandpaJustification,
+}
+
+/// Conflicting precommits signed by the same validator in the same round of the same validator set
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+pub struct Equivocation {
+	pub set_id: u64,
+	pub round: u64,
+	/// Validator which signed both precommits
+	pub offender: ed25519::Public,
+	pub first: SignedPrecommit,
+	pub second: SignedPrecommit,
+}
+
+/// Trusted finality checkpoint (weak subjectivity checkpoint) used to start finality sync
+/// from the given block instead of genesis
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TrustedCheckpoint {
+	/// Number of the trusted finalized block
+	pub block_number: u32,
+	/// Hash of the trusted finalized block
+	pub block_hash: H256,
+	/// ID of the validator set which finalizes blocks after the trusted block
+	pub set_id: u64,
+	/// Hex encoded ed25519 public keys and voting weights of the validator set which finalizes blocks after the trusted block
+	pub validator_set: Vec<(H256, u64)>,
+}
+
+impl TryFrom<&TrustedCheckpoint> for FinalitySyncCheckpoint {
+	type Error = anyhow::Error;
+
+	/// Converts trusted checkpoint into finality sync checkpoint, which starts from the block after the trusted block
+	fn try_from(checkpoint: &TrustedCheckpoint) -> Result<Self, Self::Error> {
+		if checkpoint
+			.validator_set
+			.iter()
+			.all(|&(_, weight)| weight == 0)
+		{
+			return Err(anyhow!(
+				"Trusted checkpoint validator set has no voting weight"
+			));
+		}
+		let number = checkpoint
+			.block_number
+			.checked_add(1)
+			.context("Invalid trusted checkpoint block number")?;
+		let validator_set = checkpoint
+			.validator_set
+			.iter()
+			.map(|&(public_key, weight)| (ed25519::Public::from_raw(public_key.0), weight))
+			.collect();
+		Ok(FinalitySyncCheckpoint {
+			number,
+			set_id: checkpoint.set_id,
+			validator_set,
+		})
+	}
 }
 
 #[derive(Debug, Encode)]
@@ -758,14 +1389,14 @@ pub enum SignerMessage {
 	PrecommitMessage(Precommit),
 }
 
-#[derive(Clone, Debug, Decode, Encode, Deserialize)]
+#[derive(Clone, Debug, Decode, Encode, Deserialize, PartialEq, Eq)]
 pub struct Precommit {
 	pub target_hash: H256,
 	/// The target block's number
 	pub target_number: u32,
 }
 
-#[derive(Clone, Debug, Decode, Deserialize)]
+#[derive(Clone, Debug, Decode, Encode, Deserialize, PartialEq, Eq)]
 pub struct SignedPrecommit {
 	pub precommit: Precommit,
 	/// The signature on the message.
@@ -773,7 +1404,7 @@ pub struct SignedPrecommit {
 	/// The Id of the signer.
 	pub id: ed25519::Public,
 }
-#[derive(Clone, Debug, Decode, Deserialize)]
+#[derive(Clone, Debug, Decode, Encode, Deserialize)]
 pub struct Commit {
 	pub target_hash: H256,
 	/// The target block's number.
@@ -782,11 +1413,12 @@ pub struct Commit {
 	pub precommits: Vec<SignedPrecommit>,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode)]
 pub struct GrandpaJustification {
 	pub round: u64,
 	pub commit: Commit,
-	pub _votes_ancestries: Vec<DaHeader>,
+	/// Headers proving that precommit targets are descendants of the commit target
+	pub votes_ancestries: Vec<DaHeader>,
 }
 
 impl<'de> Deserialize<'de> for GrandpaJustification {
@@ -799,3 +1431,225 @@ impl<'de> Deserialize<'de> for GrandpaJustification {
 			.map_err(|codec_err| D::Error::custom(format!("Invalid decoding: {:?}", codec_err)))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		BlockRange, CellSource, CliOpts, ConfidenceCounts, FinalitySyncCheckpoint, Network,
+		OptionBlockRange, Retention, RetryConfig, RuntimeConfig, SampledCell, SamplingRecord,
+		State, TrustedCheckpoint,
+	};
+	use avail_subxt::utils::H256;
+	use clap::Parser;
+	use kate_recovery::{data::Cell, matrix::Position};
+	use std::time::Duration;
+	use test_case::test_case;
+
+	#[test_case("1000" => Retention::Blocks(1000) ; "blocks")]
+	#[test_case(" 90s " => Retention::Duration(Duration::from_secs(90)) ; "seconds")]
+	#[test_case("30m" => Retention::Duration(Duration::from_secs(30 * 60)) ; "minutes")]
+	#[test_case("24h" => Retention::Duration(Duration::from_secs(24 * 60 * 60)) ; "hours")]
+	#[test_case("7d" => Retention::Duration(Duration::from_secs(7 * 24 * 60 * 60)) ; "days")]
+	fn retention_parse(value: &str) -> Retention {
+		Retention::try_from(value.to_string()).unwrap()
+	}
+
+	#[test_case("" ; "empty")]
+	#[test_case("d" ; "missing amount")]
+	#[test_case("10w" ; "unknown unit")]
+	#[test_case("-10" ; "negative blocks")]
+	fn retention_parse_fails(value: &str) {
+		assert!(Retention::try_from(value.to_string()).is_err());
+	}
+
+	#[test_case(1 => Duration::from_millis(500) ; "first retry")]
+	#[test_case(2 => Duration::from_millis(1000) ; "second retry")]
+	#[test_case(4 => Duration::from_millis(4000) ; "fourth retry")]
+	fn retry_backoff(attempt: u32) -> Duration {
+		RetryConfig::from(&RuntimeConfig::default()).backoff(attempt)
+	}
+
+	#[test]
+	fn retry_dht_timeout() {
+		let cfg = RuntimeConfig::default();
+		assert_eq!(RetryConfig::from(&cfg).timeout, Duration::from_secs(60));
+		assert_eq!(RetryConfig::dht(&cfg).timeout, Duration::from_secs(10));
+	}
+
+	fn trusted_checkpoint(validator_set: Vec<(H256, u64)>) -> TrustedCheckpoint {
+		TrustedCheckpoint {
+			block_number: 100,
+			block_hash: H256::repeat_byte(1),
+			set_id: 5,
+			validator_set,
+		}
+	}
+
+	#[test]
+	fn trusted_checkpoint_into_finality_sync_checkpoint() {
+		let checkpoint = trusted_checkpoint(vec![(H256::repeat_byte(2), 1)]);
+		let checkpoint = FinalitySyncCheckpoint::try_from(&checkpoint).unwrap();
+		assert_eq!(checkpoint.number, 101);
+		assert_eq!(checkpoint.set_id, 5);
+		assert_eq!(checkpoint.validator_set.len(), 1);
+		assert_eq!(checkpoint.validator_set[0].0 .0, [2; 32]);
+		assert_eq!(checkpoint.validator_set[0].1, 1);
+	}
+
+	#[test_case(vec![] ; "empty validator set")]
+	#[test_case(vec![(H256::repeat_byte(2), 0)] ; "no voting weight")]
+	fn trusted_checkpoint_invalid(validator_set: Vec<(H256, u64)>) {
+		let checkpoint = trusted_checkpoint(validator_set);
+		assert!(FinalitySyncCheckpoint::try_from(&checkpoint).is_err());
+	}
+
+	#[test]
+	fn trusted_checkpoint_deserialize() {
+		let checkpoint = r#"{
+			"block_number": 100,
+			"block_hash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+			"set_id": 5,
+			"validator_set": [["0x0202020202020202020202020202020202020202020202020202020202020202", 1]]
+		}"#;
+		let checkpoint: TrustedCheckpoint = serde_json::from_str(checkpoint).unwrap();
+		let expected = trusted_checkpoint(vec![(H256::repeat_byte(2), 1)]);
+		assert_eq!(checkpoint, expected);
+	}
+
+	#[test_case("local", Network::Local ; "local")]
+	#[test_case("biryani", Network::Biryani ; "biryani")]
+	fn trusted_checkpoint_network_default(name: &str, network: Network) {
+		let opts = CliOpts::parse_from(["avail-light", "--network", name]);
+		let mut cfg = RuntimeConfig::default();
+		cfg.load_runtime_config(&opts).unwrap();
+		assert_eq!(cfg.trusted_checkpoint, network.trusted_checkpoint());
+
+		// Configured checkpoint is not overridden by the network default
+		let checkpoint = trusted_checkpoint(vec![(H256::repeat_byte(2), 1)]);
+		let mut cfg = RuntimeConfig {
+			trusted_checkpoint: Some(checkpoint.clone()),
+			..Default::default()
+		};
+		cfg.load_runtime_config(&opts).unwrap();
+		assert_eq!(cfg.trusted_checkpoint, Some(checkpoint));
+	}
+
+	#[test_case(Retention::Blocks(0) => 1 ; "at least one block")]
+	#[test_case(Retention::Blocks(100) => 100 ; "blocks")]
+	#[test_case(Retention::Duration(Duration::from_secs(60 * 60)) => 180 ; "hour")]
+	#[test_case(Retention::Duration(Duration::from_secs(30)) => 2 ; "rounded up")]
+	fn retention_blocks(retention: Retention) -> u32 {
+		retention.blocks()
+	}
+
+	#[test]
+	fn block_range_retained() {
+		let range = Some(BlockRange { first: 5, last: 10 });
+		let retained = |pruned_below| {
+			range
+				.retained(pruned_below)
+				.map(|range| (range.first, range.last))
+		};
+		assert_eq!(retained(None), Some((5, 10)));
+		assert_eq!(retained(Some(3)), Some((5, 10)));
+		assert_eq!(retained(Some(7)), Some((7, 10)));
+		assert_eq!(retained(Some(10)), Some((10, 10)));
+		assert_eq!(retained(Some(11)), None);
+		assert!(None::<BlockRange>.retained(Some(1)).is_none());
+	}
+
+	#[test_case(&[] => Vec::<(u32, u32)>::new() ; "no missed blocks")]
+	#[test_case(&[5] => vec![(5, 5)] ; "single block")]
+	#[test_case(&[5, 6, 7] => vec![(5, 7)] ; "consecutive blocks")]
+	#[test_case(&[5, 6, 9, 10] => vec![(5, 6), (9, 10)] ; "separate gaps")]
+	fn state_add_missed_block(missed_blocks: &[u32]) -> Vec<(u32, u32)> {
+		let mut state = State::default();
+		for &block_number in missed_blocks {
+			state.add_missed_block(block_number);
+		}
+		state.gaps.iter().map(|gap| (gap.first, gap.last)).collect()
+	}
+
+	#[test_case(5 => vec![(6, 7), (9, 10)] ; "first block of gap")]
+	#[test_case(6 => vec![(5, 5), (7, 7), (9, 10)] ; "middle block of gap")]
+	#[test_case(7 => vec![(5, 6), (9, 10)] ; "last block of gap")]
+	#[test_case(8 => vec![(5, 7), (9, 10)] ; "block outside gaps")]
+	fn state_remove_missed_block(block_number: u32) -> Vec<(u32, u32)> {
+		let mut state = State::default();
+		for missed_block in [5, 6, 7, 9, 10] {
+			state.add_missed_block(missed_block);
+		}
+		state.remove_missed_block(block_number);
+		state.gaps.iter().map(|gap| (gap.first, gap.last)).collect()
+	}
+
+	#[test_case(None, &[] => Vec::<u32>::new() ; "no apps")]
+	#[test_case(Some(0), &[] => Vec::<u32>::new() ; "app 0 is ignored")]
+	#[test_case(Some(1), &[] => vec![1] ; "single app")]
+	#[test_case(Some(2), &[3, 1, 2] => vec![1, 2, 3] ; "multiple apps")]
+	fn runtime_config_app_ids(app_id: Option<u32>, app_ids: &[u32]) -> Vec<u32> {
+		let config = RuntimeConfig {
+			app_id,
+			app_ids: app_ids.to_vec(),
+			..Default::default()
+		};
+		config.app_ids()
+	}
+
+	#[test]
+	fn sampling_record_confidence_counts() {
+		let cell = |source, verified| SampledCell {
+			row: 0,
+			col: 0,
+			source,
+			verified,
+		};
+		let record = SamplingRecord {
+			cells: vec![
+				cell(CellSource::Dht, true),
+				cell(CellSource::Dht, true),
+				cell(CellSource::Dht, false),
+				cell(CellSource::Rpc, true),
+				cell(CellSource::Rpc, false),
+				cell(CellSource::Cache, true),
+			],
+		};
+		let expected = ConfidenceCounts { dht: 2, rpc: 1 };
+		assert_eq!(record.confidence_counts(), expected);
+	}
+
+	#[test]
+	fn sampling_record_new() {
+		let position = |row, col| Position { row, col };
+		let cell = |row, col| Cell {
+			position: position(row, col),
+			content: [0u8; 80],
+		};
+		let sampled = [
+			position(0, 0),
+			position(0, 1),
+			position(1, 0),
+			position(1, 1),
+		];
+		let record = SamplingRecord::new(
+			&sampled,
+			&[cell(0, 0)],
+			&[cell(0, 1)],
+			&[cell(1, 0)],
+			&[position(0, 0), position(0, 1)],
+		);
+		let sampled_cell = |row, col, source, verified| SampledCell {
+			row,
+			col,
+			source,
+			verified,
+		};
+		let expected = vec![
+			sampled_cell(0, 0, CellSource::Cache, true),
+			sampled_cell(0, 1, CellSource::Dht, true),
+			sampled_cell(1, 0, CellSource::Rpc, false),
+			sampled_cell(1, 1, CellSource::Unavailable, false),
+		];
+		assert_eq!(record.cells, expected);
+	}
+}
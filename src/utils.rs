@@ -1,4 +1,4 @@
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use avail_core::{
 	data_lookup::compact::{CompactDataLookup, DataLookupItem},
 	data_lookup::Error as DataLookupError,
@@ -7,6 +7,7 @@ use avail_core::{
 use avail_subxt::{
 	api::runtime_types::{
 		avail_core::{
+			data_lookup::compact::CompactDataLookup as SubxtCompactDataLookup,
 			header::extension::HeaderExtension,
 			header::extension::{v1, v2},
 		},
@@ -18,11 +19,15 @@ use avail_subxt::{
 	},
 	utils::H256,
 };
-use codec::Decode;
+use codec::{Decode, Encode};
 use kate_recovery::{
 	data::Cell,
 	matrix::{Dimensions, Position},
 };
+use std::future::Future;
+use tracing::debug;
+
+use crate::types::RetryConfig;
 
 pub fn decode_app_data(data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
 	let extrisic: AppUncheckedExtrinsic =
@@ -61,6 +66,18 @@ pub(crate) fn extract_kate(extension: &HeaderExtension) -> (u16, u16, H256, Vec<
 	}
 }
 
+/// Returns IDs of applications which have data in the block, in data lookup order
+pub fn extract_app_ids(lookup: &DataLookup) -> anyhow::Result<Vec<AppId>> {
+	let encoded = lookup.encode();
+	let compact = SubxtCompactDataLookup::decode(&mut &encoded[..])
+		.context("Failed to decode data lookup")?;
+	Ok(compact
+		.index
+		.iter()
+		.map(|item| AppId(item.app_id.0))
+		.collect())
+}
+
 pub(crate) fn extract_app_lookup(
 	extension: &HeaderExtension,
 ) -> Result<DataLookup, DataLookupError> {
@@ -101,10 +118,8 @@ pub fn filter_auth_set_changes(header: &DaHeader) -> Vec<Vec<(AuthorityId, u64)>
 	new_auths
 }
 
-// TODO: Remove unused functions if not needed after next iteration
-
-#[allow(dead_code)]
-fn can_reconstruct(dimensions: Dimensions, columns: &[u16], cells: &[Cell]) -> bool {
+/// Checks if there are enough cells to reconstruct given columns
+pub fn can_reconstruct(dimensions: Dimensions, columns: &[u16], cells: &[Cell]) -> bool {
 	columns.iter().all(|&col| {
 		cells
 			.iter()
@@ -114,8 +129,8 @@ fn can_reconstruct(dimensions: Dimensions, columns: &[u16], cells: &[Cell]) -> b
 	})
 }
 
-#[allow(dead_code)]
-fn diff_positions(positions: &[Position], cells: &[Cell]) -> Vec<Position> {
+/// Returns positions for which there are no cells
+pub fn diff_positions(positions: &[Position], cells: &[Cell]) -> Vec<Position> {
 	positions
 		.iter()
 		.cloned()
@@ -123,13 +138,55 @@ fn diff_positions(positions: &[Position], cells: &[Cell]) -> Vec<Position> {
 		.collect::<Vec<_>>()
 }
 
+/// Runs given operation until it succeeds or number of attempts in retry policy is reached.
+/// Each attempt is limited by the policy timeout, and retries are delayed with exponential backoff.
+/// Only timed out attempts and errors for which `is_transient` returns true are retried.
+pub async fn retry<T, F, Fut>(
+	policy: &RetryConfig,
+	is_transient: impl Fn(&anyhow::Error) -> bool,
+	mut operation: F,
+) -> anyhow::Result<T>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = anyhow::Result<T>>,
+{
+	let mut attempt = 1;
+	loop {
+		let result = match tokio::time::timeout(policy.timeout, operation()).await {
+			Ok(result) => result.map_err(|error| (is_transient(&error), error)),
+			Err(_) => Err((true, anyhow!("Timed out after {:?}", policy.timeout))),
+		};
+
+		match result {
+			Ok(value) => return Ok(value),
+			Err((false, error)) => return Err(error),
+			Err((_, error)) if attempt >= policy.attempts => {
+				return Err(error).with_context(|| format!("Failed after {attempt} attempt(s)"))
+			},
+			Err((_, error)) => {
+				let backoff = policy.backoff(attempt);
+				debug!("Attempt {attempt} failed, retrying in {backoff:?}: {error:#}");
+				tokio::time::sleep(backoff).await;
+				attempt += 1;
+			},
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{can_reconstruct, diff_positions};
+	use super::{can_reconstruct, diff_positions, extract_app_ids, retry};
+	use crate::types::RetryConfig;
+	use anyhow::anyhow;
+	use avail_core::{AppId, DataLookup};
 	use kate_recovery::{
 		data::Cell,
 		matrix::{Dimensions, Position},
 	};
+	use std::{
+		sync::atomic::{AtomicU32, Ordering},
+		time::Duration,
+	};
 
 	fn position(row: u32, col: u16) -> Position {
 		Position { row, col }
@@ -172,6 +229,20 @@ mod tests {
 		assert!(!can_reconstruct(dimensions, &columns, &cells));
 	}
 
+	#[test]
+	fn test_extract_app_ids() {
+		let id_lens: Vec<(u32, usize)> = vec![(0, 1), (1, 69), (3, 2)];
+		let lookup = DataLookup::from_id_and_len_iter(id_lens.into_iter()).unwrap();
+		assert_eq!(
+			extract_app_ids(&lookup).unwrap(),
+			vec![AppId(0), AppId(1), AppId(3)]
+		);
+
+		let id_lens: Vec<(u32, usize)> = vec![];
+		let lookup = DataLookup::from_id_and_len_iter(id_lens.into_iter()).unwrap();
+		assert!(extract_app_ids(&lookup).unwrap().is_empty());
+	}
+
 	#[test]
 	fn test_diff_positions() {
 		let positions = vec![position(0, 0), position(1, 1)];
@@ -189,4 +260,81 @@ mod tests {
 		assert_eq!(diff_positions(&positions, &cells)[0], position(0, 0));
 		assert_eq!(diff_positions(&positions, &cells)[1], position(1, 1));
 	}
+
+	fn retry_config(attempts: u32) -> RetryConfig {
+		RetryConfig {
+			attempts,
+			backoff: Duration::from_millis(1),
+			timeout: Duration::from_millis(100),
+		}
+	}
+
+	#[tokio::test]
+	async fn retry_succeeds_after_failures() {
+		let calls = AtomicU32::new(0);
+		let result = retry(
+			&retry_config(3),
+			|_| true,
+			|| async {
+				match calls.fetch_add(1, Ordering::SeqCst) {
+					0 | 1 => Err(anyhow!("Transient error")),
+					_ => Ok(42),
+				}
+			},
+		)
+		.await;
+		assert_eq!(result.unwrap(), 42);
+		assert_eq!(calls.load(Ordering::SeqCst), 3);
+	}
+
+	#[tokio::test]
+	async fn retry_fails_after_attempts() {
+		let calls = AtomicU32::new(0);
+		let result: anyhow::Result<()> = retry(
+			&retry_config(2),
+			|_| true,
+			|| async {
+				calls.fetch_add(1, Ordering::SeqCst);
+				Err(anyhow!("Transient error"))
+			},
+		)
+		.await;
+		assert!(result.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+	}
+
+	#[tokio::test]
+	async fn retry_skips_permanent_error() {
+		let calls = AtomicU32::new(0);
+		let result: anyhow::Result<()> = retry(
+			&retry_config(3),
+			|_| false,
+			|| async {
+				calls.fetch_add(1, Ordering::SeqCst);
+				Err(anyhow!("Permanent error"))
+			},
+		)
+		.await;
+		assert!(result.is_err());
+		assert_eq!(calls.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn retry_times_out() {
+		let calls = AtomicU32::new(0);
+		// Timed out attempts are retried regardless of error kind
+		let result = retry(
+			&retry_config(2),
+			|_| false,
+			|| async {
+				if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+					tokio::time::sleep(Duration::from_secs(1)).await;
+				}
+				Ok(())
+			},
+		)
+		.await;
+		assert!(result.is_ok());
+		assert_eq!(calls.load(Ordering::SeqCst), 2);
+	}
 }